@@ -0,0 +1,125 @@
+//! `--allocate`: after a pilot pass over every scenario, spends what is left
+//! of `--budget` on further iterations where they shrink the uncertainty of
+//! the results the most.
+//!
+//! With a scenario's relative spread `s` and its time per iteration `c`,
+//! the summed squared relative error of all medians is smallest for a fixed
+//! budget when each scenario gets iterations in proportion to `s / sqrt(c)`:
+//! noisy and fast scenarios get more, slow and stable ones fewer.
+
+use std::time::Duration;
+
+/// Iterations of every scenario in the pilot pass, the fewest that show a
+/// spread.
+pub(crate) const PILOT_ITERATIONS: u32 = 2;
+/// No scenario is run more often than this, however much budget is left.
+const MAX_ITERATIONS: u32 = 30;
+
+/// What the pilot pass measured for one scenario.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Pilot {
+    pub iterations: u32,
+    /// Coefficient of variation of the scenario's noisiest phase.
+    pub spread: f64,
+    /// Wall time of one iteration.
+    pub cost: Duration,
+}
+
+/// Total iterations for each pilot so the extra ones fit into `remaining`.
+pub(crate) fn allocate(pilots: &[Pilot], remaining: Duration) -> Vec<u32> {
+    let weights: Vec<f64> = pilots
+        .iter()
+        .map(|pilot| pilot.spread / pilot.cost.as_secs_f64().max(f64::EPSILON).sqrt())
+        .collect();
+    let weighted_cost: f64 = pilots
+        .iter()
+        .zip(&weights)
+        .map(|(pilot, weight)| weight * pilot.cost.as_secs_f64())
+        .sum();
+    pilots
+        .iter()
+        .zip(&weights)
+        .map(|(pilot, weight)| {
+            let extra = if weighted_cost > 0.0 {
+                (remaining.as_secs_f64() * weight / weighted_cost).floor()
+            } else {
+                0.0
+            };
+            (f64::from(pilot.iterations) + extra)
+                .min(f64::from(MAX_ITERATIONS.max(pilot.iterations))) as u32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pilot(spread: f64, secs: u64) -> Pilot {
+        Pilot {
+            iterations: PILOT_ITERATIONS,
+            spread,
+            cost: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn no_pilots_get_no_iterations() {
+        assert!(allocate(&[], Duration::from_secs(600)).is_empty());
+    }
+
+    #[test]
+    fn budget_below_one_iteration_adds_nothing() {
+        let pilots = [pilot(0.1, 60), pilot(0.2, 90)];
+
+        assert_eq!(allocate(&pilots, Duration::ZERO), [2, 2]);
+        assert_eq!(allocate(&pilots, Duration::from_secs(59)), [2, 2]);
+    }
+
+    #[test]
+    fn stable_pilots_get_no_extra_iterations() {
+        let pilots = [pilot(0.0, 10), pilot(0.0, 20)];
+
+        assert_eq!(allocate(&pilots, Duration::from_secs(600)), [2, 2]);
+    }
+
+    #[test]
+    fn tied_pilots_share_equally() {
+        let pilots = [pilot(0.1, 10); 3];
+
+        assert_eq!(allocate(&pilots, Duration::from_secs(90)), [5, 5, 5]);
+    }
+
+    #[test]
+    fn noisy_and_fast_pilots_get_more() {
+        let noisy = allocate(&[pilot(0.2, 10), pilot(0.1, 10)], Duration::from_secs(120));
+        let fast = allocate(&[pilot(0.1, 10), pilot(0.1, 40)], Duration::from_secs(120));
+
+        assert!(noisy[0] > noisy[1], "{noisy:?}");
+        assert!(fast[0] > fast[1], "{fast:?}");
+    }
+
+    #[test]
+    fn extra_iterations_fit_the_budget() {
+        let pilots = [pilot(0.3, 7), pilot(0.05, 13), pilot(0.1, 3)];
+        let remaining = Duration::from_secs(200);
+
+        let spent: Duration = allocate(&pilots, remaining)
+            .iter()
+            .zip(&pilots)
+            .map(|(total, pilot)| pilot.cost * (total - pilot.iterations))
+            .sum();
+
+        assert!(spent <= remaining, "{spent:?}");
+    }
+
+    #[test]
+    fn iterations_are_capped() {
+        let mut long = pilot(0.1, 1);
+        long.iterations = MAX_ITERATIONS + 5;
+
+        let totals = allocate(&[pilot(0.1, 1), long], Duration::from_secs(3600));
+
+        assert_eq!(totals, [MAX_ITERATIONS, MAX_ITERATIONS + 5]);
+    }
+}
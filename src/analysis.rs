@@ -0,0 +1,140 @@
+//! Factorial analysis of a finished run: how much each axis value moves the
+//! clean, incremental, and hotpatch times, averaged over every other axis.
+
+use std::collections::BTreeMap;
+
+use crate::{ScenarioRecord, stats};
+
+/// Phases worth analysing; the others are either derived from these or only
+/// measured for a handful of scenarios.
+const PHASES: [&str; 3] = ["clean", "second", "hotpatch"];
+
+/// Phases of the grouped summary, the same as the comparison table's.
+const SUMMARY_PHASES: [&str; 4] = ["clean", "second", "modified", "hotpatch"];
+
+struct AxisEffect {
+    axis: String,
+    /// Mean seconds relative to the phase's overall mean, per axis value.
+    effects: Vec<(String, f64)>,
+}
+
+impl AxisEffect {
+    fn spread(&self) -> f64 {
+        let (min, max) = self.effects.iter().fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (_, effect)| (min.min(*effect), max.max(*effect)),
+        );
+        max - min
+    }
+}
+
+/// Successful scenarios with a measurement of `phase`.
+fn samples<'a>(records: &'a [ScenarioRecord], phase: &str) -> Vec<(&'a ScenarioRecord, f64)> {
+    records
+        .iter()
+        .filter(|record| record.error.is_none())
+        .filter_map(|record| Some((record, record.timings.phase(phase)?)))
+        .collect()
+}
+
+/// Prints, per phase and axis, the median of all scenarios sharing each axis
+/// value, fastest first, e.g. "linker: rust-lld 12.10s (6) vs default 19.40s
+/// (6)". Axes with a single value in the run are left out.
+pub fn summarize(records: &[ScenarioRecord]) {
+    let mut printed_header = false;
+    for phase in SUMMARY_PHASES {
+        let samples = samples(records, phase);
+        let axes = group_by_axis(&samples);
+        if axes.is_empty() {
+            continue;
+        }
+        if !printed_header {
+            println!("\nGrouped medians (scenario count in parentheses):");
+            printed_header = true;
+        }
+        println!("  {phase}:");
+        for (axis, groups) in axes {
+            let mut medians: Vec<(String, f64, usize)> = groups
+                .into_iter()
+                .map(|(value, values)| (value, stats::median(&values), values.len()))
+                .collect();
+            medians.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let values: Vec<String> = medians
+                .iter()
+                .map(|(value, median, count)| format!("{value} {median:.2}s ({count})"))
+                .collect();
+            println!("    {axis}: {}", values.join(" vs "));
+        }
+    }
+}
+
+/// Prints a "what matters most" section ranking axes by the spread between
+/// their best and worst value. Axes with a single value in the run are left
+/// out since they cannot explain any difference.
+pub fn report(records: &[ScenarioRecord]) {
+    let mut printed_header = false;
+    for phase in PHASES {
+        let samples = samples(records, phase);
+        let mut axes = axis_effects(&samples);
+        if axes.is_empty() {
+            continue;
+        }
+        axes.sort_by(|a, b| b.spread().total_cmp(&a.spread()));
+
+        if !printed_header {
+            println!("\nWhat matters most (mean effect vs. the phase average):");
+            printed_header = true;
+        }
+        println!("  {phase} ({} scenario(s)):", samples.len());
+        for axis in &axes {
+            println!("    {} (spread {:.1}s)", axis.axis, axis.spread());
+            for (value, effect) in &axis.effects {
+                println!("      {value:<18} {effect:+.1}s");
+            }
+        }
+    }
+}
+
+fn axis_effects(samples: &[(&ScenarioRecord, f64)]) -> Vec<AxisEffect> {
+    let overall = mean(samples.iter().map(|(_, seconds)| *seconds));
+    group_by_axis(samples)
+        .into_iter()
+        .map(|(axis, groups)| {
+            let mut effects: Vec<(String, f64)> = groups
+                .into_iter()
+                .map(|(value, values)| (value, mean(values.into_iter()) - overall))
+                .collect();
+            effects.sort_by(|a, b| a.1.total_cmp(&b.1));
+            AxisEffect { axis, effects }
+        })
+        .collect()
+}
+
+/// The samples' seconds grouped by value, for every axis with more than one
+/// value among them.
+fn group_by_axis(samples: &[(&ScenarioRecord, f64)]) -> Vec<(String, BTreeMap<String, Vec<f64>>)> {
+    if samples.len() < 2 {
+        return Vec::new();
+    }
+    let axes: Vec<Vec<(String, String)>> =
+        samples.iter().map(|(record, _)| record.axes()).collect();
+    let axis_count = axes.iter().map(Vec::len).min().unwrap_or_default();
+
+    (0..axis_count)
+        .filter_map(|index| {
+            let mut groups: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+            for (record_axes, (_, seconds)) in axes.iter().zip(samples) {
+                let (_, value) = &record_axes[index];
+                groups.entry(value.clone()).or_default().push(*seconds);
+            }
+            (groups.len() > 1).then(|| (axes[0][index].0.clone(), groups))
+        })
+        .collect()
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    sum / count as f64
+}
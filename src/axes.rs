@@ -0,0 +1,129 @@
+//! User-defined axes declared in the config file. Each value contributes
+//! environment variables, rustflags, `[profile.dev]` keys, Bevy features, or
+//! cargo arguments, and the axes are crossed with the built-in matrix.
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// `[[axis]]` entry in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomAxis {
+    pub name: String,
+    pub values: Vec<AxisValue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxisValue {
+    /// Appended to the scenario slug, so it must be unique across all axes.
+    pub name: String,
+    /// Variables for the `[env]` section of the generated cargo config.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Added to `build.rustflags`.
+    #[serde(default)]
+    pub rustflags: Vec<String>,
+    /// Keys set in the payload's `[profile.dev]`.
+    #[serde(default)]
+    pub profile: BTreeMap<String, toml::Value>,
+    /// Extra features enabled on the `bevy` dependency.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Extra arguments of the scenario's `cargo build` and `cargo run`
+    /// invocations, such as `--features` or `--bin`.
+    #[serde(default)]
+    pub cargo_args: Vec<String>,
+    /// Tags of every scenario with this value, for `--tags` and
+    /// `--skip-tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// One value picked for one custom axis.
+#[derive(Debug, Clone)]
+pub struct AxisChoice {
+    pub axis: String,
+    pub value: AxisValue,
+}
+
+/// Cargo arguments the built-in axes decide.
+const RESERVED_CARGO_ARGS: &[&str] = &[
+    "--profile",
+    "--release",
+    "-r",
+    "--target",
+    "--target-dir",
+    "--manifest-path",
+];
+
+/// Rejects axes that would produce ambiguous or unusable slugs.
+pub fn validate(axes: &[CustomAxis]) -> Result<()> {
+    let mut axis_names = HashSet::new();
+    let mut value_names = HashSet::new();
+    for axis in axes {
+        if !axis_names.insert(axis.name.as_str()) {
+            bail!("custom axis `{}` is declared twice", axis.name);
+        }
+        if axis.values.is_empty() {
+            bail!("custom axis `{}` has no values", axis.name);
+        }
+        for value in &axis.values {
+            if !is_slug_safe(&value.name) {
+                bail!(
+                    "value `{}` of custom axis `{}` must be non-empty and use only a-z, 0-9, and `-`",
+                    value.name,
+                    axis.name
+                );
+            }
+            if let Some(tag) = value.tags.iter().find(|tag| !is_slug_safe(tag)) {
+                bail!(
+                    "tag `{tag}` of custom axis value `{}` must be non-empty and use only a-z, 0-9, and `-`",
+                    value.name
+                );
+            }
+            if let Some(arg) = value.cargo_args.iter().find(|arg| {
+                let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+                RESERVED_CARGO_ARGS.contains(&flag)
+            }) {
+                bail!(
+                    "cargo argument `{arg}` of custom axis value `{}` is chosen by the harness",
+                    value.name
+                );
+            }
+            if !value_names.insert(value.name.as_str()) {
+                bail!("custom axis value `{}` is used more than once", value.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_slug_safe(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Every combination of one value per axis, in declaration order. With no
+/// axes there is exactly one (empty) combination.
+pub fn combinations(axes: &[CustomAxis]) -> Vec<Vec<AxisChoice>> {
+    let mut combinations = vec![Vec::new()];
+    for axis in axes {
+        let mut expanded = Vec::with_capacity(combinations.len() * axis.values.len());
+        for combination in &combinations {
+            for value in &axis.values {
+                let mut next: Vec<AxisChoice> = combination.clone();
+                next.push(AxisChoice {
+                    axis: axis.name.clone(),
+                    value: value.clone(),
+                });
+                expanded.push(next);
+            }
+        }
+        combinations = expanded;
+    }
+    combinations
+}
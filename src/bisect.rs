@@ -0,0 +1,380 @@
+//! Bisection of compile-time regressions: finds the first candidate (a Bevy
+//! commit or a nightly toolchain) whose timing of one phase of one scenario
+//! exceeds the oldest candidate's by more than a threshold.
+
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::{BisectArgs, BisectBevyArgs, BisectToolchainArgs, RunArgs};
+use crate::config::Config;
+use crate::remote::Host;
+use crate::{BevySource, Matrix, PreparedScenario, RunContext, prepare_scenarios, process_tree};
+
+/// One point of the searched history.
+trait Candidate {
+    /// Short form for progress lines.
+    fn label(&self) -> String;
+}
+
+/// Measures the scenario with the run settings of the bisection.
+struct Probe<'a> {
+    args: &'a BisectArgs,
+    context: RunContext,
+}
+
+impl Probe<'_> {
+    fn measure(&self, prepared: &PreparedScenario) -> Result<f64> {
+        let run = crate::run_iterations(
+            prepared,
+            &self.context,
+            Vec::new(),
+            self.args.run.iterations,
+            self.args.run.rerun_outliers,
+        )?;
+        run.phase_seconds(&self.args.metric).with_context(|| {
+            format!(
+                "{} does not measure the {} phase",
+                prepared.slug, self.args.metric
+            )
+        })
+    }
+}
+
+/// The host of the bisection and a context to run the scenario on it.
+fn probe<'a>(args: &'a BisectArgs, config: &Config) -> Result<(Host, Probe<'a>)> {
+    let host = Host::from_remote(args.run.remote.as_deref(), &args.run.remote_dir);
+    process_tree::forward_interrupts().context("failed to install signal handlers")?;
+    let context = RunContext::new(&args.run, &host, config, false);
+    Ok((host, Probe { args, context }))
+}
+
+/// Finds the scenario named `slug` in the matrix selected by the run flags.
+pub(crate) fn find_scenario(
+    run: &RunArgs,
+    slug: &str,
+    config: &Config,
+) -> Result<PreparedScenario> {
+    prepare_scenarios(&Matrix::from_args(run, config))?
+        .into_iter()
+        .find(|prepared| prepared.slug == slug)
+        .with_context(|| {
+            format!("no scenario named {slug} in the selected matrix; see `bench list`")
+        })
+}
+
+/// Binary search between a good first and a regressed last candidate.
+/// Candidates that cannot be measured are skipped, like `git bisect skip`,
+/// so the result may be a range rather than a single candidate.
+fn search<T: Candidate>(
+    candidates: &[T],
+    limit: f64,
+    mut measure: impl FnMut(&T) -> Result<f64>,
+) -> Result<(usize, usize)> {
+    let (mut good, mut bad) = (0, candidates.len() - 1);
+    let mut skipped = vec![false; candidates.len()];
+    loop {
+        let middle = (good + bad) / 2;
+        // The untested candidate closest to the middle.
+        let Some(next) = (good + 1..bad)
+            .filter(|&index| !skipped[index])
+            .min_by_key(|&index| index.abs_diff(middle))
+        else {
+            return Ok((good, bad));
+        };
+        let left = (good + 1..bad).filter(|&index| !skipped[index]).count();
+        println!(
+            "\n[bench] Bisecting: {left} candidate(s) left, roughly {} step(s). Measuring {}",
+            usize::BITS - left.leading_zeros(),
+            candidates[next].label()
+        );
+        match measure(&candidates[next]) {
+            Ok(seconds) if seconds > limit => {
+                println!(
+                    "[bench] {}: {seconds:.1}s, regressed",
+                    candidates[next].label()
+                );
+                bad = next;
+            }
+            Ok(seconds) => {
+                println!("[bench] {}: {seconds:.1}s, good", candidates[next].label());
+                good = next;
+            }
+            Err(_) if process_tree::interrupted() => bail!("bisection interrupted"),
+            Err(err) => {
+                eprintln!(
+                    "[bench][warn] Skipping {}: {err:#}",
+                    candidates[next].label()
+                );
+                skipped[next] = true;
+            }
+        }
+    }
+}
+
+/// Measures both ends, checks the last one regressed, and bisects between
+/// them. Prints the result and returns the indices of the last good and
+/// first regressed candidates.
+fn run<T: Candidate>(
+    args: &BisectArgs,
+    candidates: &[T],
+    mut measure: impl FnMut(&T) -> Result<f64>,
+) -> Result<(usize, usize)> {
+    let (Some(first), Some(last)) = (candidates.first(), candidates.last()) else {
+        bail!("nothing to bisect");
+    };
+    if candidates.len() < 2 {
+        bail!("--from and --to resolve to the same candidate");
+    }
+    println!(
+        "[bench] Bisecting {} candidate(s) by the {} phase of {}",
+        candidates.len(),
+        args.metric,
+        args.scenario
+    );
+    println!("\n[bench] Measuring the baseline {}", first.label());
+    let baseline =
+        measure(first).with_context(|| format!("failed to measure {}", first.label()))?;
+    let limit = baseline * (1.0 + args.threshold / 100.0);
+    println!(
+        "[bench] Baseline: {baseline:.1}s; regressed above {limit:.1}s (+{}%)",
+        args.threshold
+    );
+    println!("\n[bench] Measuring {}", last.label());
+    let end = measure(last).with_context(|| format!("failed to measure {}", last.label()))?;
+    if end <= limit {
+        bail!(
+            "{} takes {end:.1}s, within {}% of the baseline; nothing to bisect",
+            last.label(),
+            args.threshold
+        );
+    }
+    println!("[bench] {}: {end:.1}s, regressed", last.label());
+
+    let (good, bad) = search(candidates, limit, measure)?;
+    println!("\n[bench] Last good: {}", candidates[good].label());
+    println!("[bench] First regressed: {}", candidates[bad].label());
+    if bad - good > 1 {
+        println!(
+            "[bench] The regression may also be in one of these {} candidate(s), which could not be measured:",
+            bad - good - 1
+        );
+        for candidate in &candidates[good + 1..bad] {
+            println!("    {}", candidate.label());
+        }
+    }
+    Ok((good, bad))
+}
+
+/// A commit of the Bevy repository.
+struct Revision {
+    sha: String,
+    subject: String,
+}
+
+impl Candidate for Revision {
+    fn label(&self) -> String {
+        format!("{} {}", &self.sha[..self.sha.len().min(10)], self.subject)
+    }
+}
+
+/// `bench bisect-bevy`: bisects the first-parent history between two Bevy
+/// versions, building the scenario against each commit as a git dependency.
+pub fn bisect_bevy(args: &BisectBevyArgs, config: &Config) -> Result<()> {
+    let bisect = &args.bisect;
+    let mut prepared = find_scenario(&bisect.run, &bisect.scenario, config)?;
+    let revisions = bevy_revisions(&args.repo, &version_ref(&args.from), &version_ref(&args.to))?;
+    let (_, probe) = probe(bisect, config)?;
+    run(bisect, &revisions, |revision| {
+        prepared.use_bevy(
+            &BevySource::Git {
+                repo: args.repo.clone(),
+                rev: revision.sha.clone(),
+            },
+            &config.patch,
+        );
+        probe.measure(&prepared)
+    })?;
+    Ok(())
+}
+
+/// `0.15` and `0.15.0` become the release tag `v0.15.0`; anything else is
+/// used as a git revision.
+fn version_ref(version: &str) -> String {
+    let parts: Vec<&str> = version.split('.').collect();
+    let numeric = parts
+        .iter()
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    match parts.len() {
+        2 if numeric => format!("v{version}.0"),
+        3 if numeric => format!("v{version}"),
+        _ => version.to_string(),
+    }
+}
+
+/// `from` followed by the first-parent commits up to and including `to`,
+/// read from a blobless clone cached next to the shared target directories.
+fn bevy_revisions(repo: &str, from: &str, to: &str) -> Result<Vec<Revision>> {
+    let dir = Host::Local.shared_dir("bevy.git")?;
+    if Path::new(&dir).is_dir() {
+        println!("[bench] Updating the Bevy clone in {dir}...");
+        git(
+            &dir,
+            &[
+                "fetch",
+                "--quiet",
+                "--tags",
+                "--force",
+                repo,
+                "+refs/heads/*:refs/heads/*",
+            ],
+        )?;
+    } else {
+        println!("[bench] Cloning {repo} into {dir} (history only)...");
+        let status = Command::new("git")
+            .args([
+                "clone",
+                "--quiet",
+                "--bare",
+                "--filter=blob:none",
+                repo,
+                &dir,
+            ])
+            .status()
+            .context("failed to run git clone")?;
+        if !status.success() {
+            bail!("git clone {repo} failed with status {status}");
+        }
+    }
+    let resolve = |name: &str| {
+        git(
+            &dir,
+            &["rev-parse", "--verify", &format!("{name}^{{commit}}")],
+        )
+        .with_context(|| format!("unknown Bevy version or revision {name}"))
+    };
+    let (from, to) = (resolve(from)?, resolve(to)?);
+    let mut revisions = vec![Revision {
+        subject: git(&dir, &["log", "-1", "--format=%s", &from])?,
+        sha: from.clone(),
+    }];
+    let log = git(
+        &dir,
+        &[
+            "log",
+            "--first-parent",
+            "--reverse",
+            "--format=%H %s",
+            &format!("{from}..{to}"),
+        ],
+    )?;
+    revisions.extend(log.lines().map(|line| {
+        let (sha, subject) = line.split_once(' ').unwrap_or((line, ""));
+        Revision {
+            sha: sha.to_string(),
+            subject: subject.to_string(),
+        }
+    }));
+    Ok(revisions)
+}
+
+pub(crate) fn git(dir: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed with status {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A dated nightly toolchain.
+struct Nightly(NaiveDate);
+
+impl Nightly {
+    fn name(&self) -> String {
+        format!("nightly-{}", self.0.format("%Y-%m-%d"))
+    }
+}
+
+impl Candidate for Nightly {
+    fn label(&self) -> String {
+        self.name()
+    }
+}
+
+/// `bench bisect-toolchain`: bisects the nightlies between two dates,
+/// installing each one with rustup before building the scenario with it.
+pub fn bisect_toolchain(args: &BisectToolchainArgs, config: &Config) -> Result<()> {
+    let bisect = &args.bisect;
+    if args.to <= args.from {
+        bail!("--to must be after --from");
+    }
+    let mut prepared = find_scenario(&bisect.run, &bisect.scenario, config)?;
+    let nightlies: Vec<Nightly> = args
+        .from
+        .iter_days()
+        .take_while(|date| *date <= args.to)
+        .map(Nightly)
+        .collect();
+    let (host, probe) = probe(bisect, config)?;
+    let (good, bad) = run(bisect, &nightlies, |nightly| {
+        install(&host, &prepared, &nightly.name())?;
+        prepared.use_toolchain(&nightly.name());
+        probe.measure(&prepared)
+    })?;
+
+    let commit = |nightly: &Nightly| {
+        let output = host
+            .command(".", &[], "rustc")
+            .arg(format!("+{}", nightly.name()))
+            .arg("-vV")
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("commit-hash: "))
+            .map(str::to_string)
+    };
+    if let (Some(good), Some(bad)) = (commit(&nightlies[good]), commit(&nightlies[bad])) {
+        println!("[bench] Changes: https://github.com/rust-lang/rust/compare/{good}...{bad}");
+    }
+    Ok(())
+}
+
+/// Installs `toolchain` with the components and target the scenario's
+/// `rust-toolchain.toml` asks for. A date without a nightly, or without one
+/// of the components, fails here and the date is skipped.
+fn install(host: &Host, prepared: &PreparedScenario, toolchain: &str) -> Result<()> {
+    let mut command = host.command(".", &[], "rustup");
+    command.args([
+        "toolchain",
+        "install",
+        toolchain,
+        "--profile",
+        "minimal",
+        "--component",
+        "llvm-tools-preview",
+    ]);
+    if prepared.scenario.build_std.is_some() {
+        command.args(["--component", "rust-src"]);
+    }
+    if let Some(target) = prepared.scenario.target {
+        command.args(["--target", target.triple()]);
+    }
+    println!("[bench] Installing {toolchain}...");
+    let status = command.status().context("failed to run rustup")?;
+    if !status.success() {
+        bail!("rustup toolchain install {toolchain} failed with status {status}");
+    }
+    Ok(())
+}
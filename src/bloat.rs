@@ -0,0 +1,158 @@
+//! Optional `cargo bloat` analysis of what the payload binary is made of, so
+//! a size change can be attributed to crates and functions.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{PreparedScenario, ScenarioRecord, Workspace, format_bytes};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bloat {
+    pub file_bytes: u64,
+    pub text_bytes: u64,
+    /// Largest crates in the `.text` section, largest first.
+    pub crates: Vec<SizedItem>,
+    /// Largest functions in the `.text` section, largest first.
+    pub functions: Vec<SizedItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizedItem {
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// `cargo bloat --message-format json` output; crate mode fills `crates`,
+/// function mode fills `functions`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct BloatJson {
+    file_size: u64,
+    text_section_size: u64,
+    #[serde(default)]
+    crates: Vec<JsonItem>,
+    #[serde(default)]
+    functions: Vec<JsonItem>,
+}
+
+#[derive(Deserialize)]
+struct JsonItem {
+    #[serde(rename = "crate")]
+    krate: Option<String>,
+    name: String,
+    size: u64,
+}
+
+/// Runs `cargo bloat` twice, per crate and per function, keeping the `top`
+/// largest of each. Not timed; the payload is already built.
+pub fn measure(workspace: &Workspace, prepared: &PreparedScenario, top: usize) -> Result<Bloat> {
+    println!("[bench] Running cargo bloat in {}", workspace.exec_dir);
+    let by_crate = run(workspace, prepared, top, true)?;
+    let by_function = run(workspace, prepared, top, false)?;
+    Ok(Bloat {
+        file_bytes: by_crate.file_size,
+        text_bytes: by_crate.text_section_size,
+        crates: by_crate
+            .crates
+            .into_iter()
+            .map(SizedItem::crate_size)
+            .collect(),
+        functions: by_function
+            .functions
+            .into_iter()
+            .map(SizedItem::function_size)
+            .collect(),
+    })
+}
+
+fn run(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    top: usize,
+    crates: bool,
+) -> Result<BloatJson> {
+    let mut command = workspace.command("cargo");
+    // `--locked`, like every build, so the analysis cannot rewrite Cargo.lock.
+    command
+        .arg("bloat")
+        .arg("--locked")
+        .arg("--message-format")
+        .arg("json");
+    command.arg("-n").arg(top.to_string());
+    if crates {
+        command.arg("--crates");
+    }
+    if let Some(target) = prepared.scenario.target {
+        if !target.cargo_plugin().is_empty() {
+            bail!(
+                "cargo bloat cannot build through the {} cargo plugin",
+                target.triple()
+            );
+        }
+        command.arg("--target").arg(target.triple());
+    }
+    let output = command.output().context("failed to run cargo bloat")?;
+    if !output.status.success() {
+        bail!(
+            "cargo bloat failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    serde_json::from_slice(&output.stdout).context("failed to parse cargo bloat output")
+}
+
+impl SizedItem {
+    fn crate_size(item: JsonItem) -> Self {
+        Self {
+            name: item.name,
+            bytes: item.size,
+        }
+    }
+
+    /// Functions are shown with their crate, when `cargo bloat` knows it.
+    fn function_size(item: JsonItem) -> Self {
+        let name = match item.krate {
+            Some(krate) => format!("[{krate}] {}", item.name),
+            None => item.name,
+        };
+        Self {
+            name,
+            bytes: item.size,
+        }
+    }
+}
+
+/// Prints each scenario's largest crates and functions.
+pub fn report(records: &[ScenarioRecord]) {
+    let measured: Vec<(&str, &Bloat)> = records
+        .iter()
+        .filter_map(|record| Some((record.slug.as_str(), record.bloat.as_ref()?)))
+        .collect();
+    if measured.is_empty() {
+        return;
+    }
+
+    println!("\nBinary composition (cargo bloat):");
+    for (slug, bloat) in measured {
+        println!(
+            "  {slug}: {} file, {} .text",
+            format_bytes(bloat.file_bytes),
+            format_bytes(bloat.text_bytes)
+        );
+        for item in &bloat.crates {
+            println!(
+                "    crate    {:>10}  {}",
+                format_bytes(item.bytes),
+                item.name
+            );
+        }
+        for item in &bloat.functions {
+            println!(
+                "    function {:>10}  {}",
+                format_bytes(item.bytes),
+                item.name
+            );
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Validated construction of [`Scenario`] values for library users.
+
+use std::path::Path;
+
+use crate::error::ScenarioError;
+use crate::{
+    BuildStd, Cache, Channel, DebugAssertions, Dynamic, Hotpatching, Linker, Profile, Registry,
+    Resolver, Scenario, Target, Trace, WindowBackend,
+};
+
+/// Builder returned by [`Scenario::builder`]. Every setter mirrors one axis;
+/// [`ScenarioBuilder::build`] rejects combinations that cannot work before any
+/// build is started.
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioBuilder {
+    scenario: Scenario,
+}
+
+impl Scenario {
+    pub fn builder() -> ScenarioBuilder {
+        ScenarioBuilder::default()
+    }
+
+    /// Checks that the axis values can be combined at all.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        if self.hotpatching.is_some()
+            && let Some(target) = self.target
+        {
+            // Hotpatching runs the payload, which a cross-compiled binary
+            // cannot do on the build machine.
+            return Err(ScenarioError::HotpatchCrossTarget {
+                target: target.triple(),
+            });
+        }
+        if matches!(self.dynamic, Some(Dynamic::DynamicLinking))
+            && self.target == Some(Target::X86_64LinuxMusl)
+        {
+            // musl targets default to `+crt-static`, which cannot load the
+            // dylib that `bevy/dynamic_linking` builds.
+            return Err(ScenarioError::StaticTarget {
+                target: Target::X86_64LinuxMusl.triple(),
+            });
+        }
+        if self.build_std.is_some() && self.target.is_none() {
+            // Cargo only rebuilds std for an explicit `--target`.
+            return Err(ScenarioError::BuildStdNeedsTarget);
+        }
+        if self.trace == Some(Trace::Tracy) && self.target == Some(Target::Wasm32) {
+            // The Tracy client is C++ and needs a native toolchain.
+            return Err(ScenarioError::TracyUnsupported {
+                target: Target::Wasm32.triple(),
+            });
+        }
+        if let Some(backend) = self.window_backend
+            && !self.builds_for_linux()
+        {
+            return Err(ScenarioError::LinuxOnly {
+                axis: "window-backend",
+                value: backend.label(),
+            });
+        }
+        if self.linker == Some(Linker::Gold) && !self.builds_for_linux() {
+            return Err(ScenarioError::LinuxOnly {
+                axis: "linker",
+                value: self.linker_label(),
+            });
+        }
+        if let Some(linker) = self.linker
+            && linker.macos_only()
+            && (!cfg!(target_os = "macos") || self.target.is_some())
+        {
+            return Err(ScenarioError::MacosOnly {
+                value: self.linker_label(),
+            });
+        }
+        if self.linker == Some(Linker::Zig) {
+            // `dx` and the cross-compiling cargo plugins drive their own
+            // builds, and zig has no wasm or MSVC linking to offer.
+            let other = match (self.hotpatching, self.target) {
+                (Some(Hotpatching::Dx), _) => Some("hotpatch=dx"),
+                (_, Some(Target::X86_64WindowsMsvc)) => Some("cargo-xwin"),
+                (_, Some(Target::Aarch64Android)) => Some("cargo-ndk"),
+                (_, Some(Target::Wasm32)) => Some("target=wasm32-unknown-unknown"),
+                _ => None,
+            };
+            if let Some(other) = other {
+                return Err(ScenarioError::ZigConflict { other });
+            }
+        }
+        if self.warm_cache && self.cache.and_then(Cache::server).is_none() {
+            return Err(ScenarioError::WarmCacheWithoutServer);
+        }
+        if let Some(profile) = self.profile {
+            // `dx serve` builds the dev profile, and rustc refuses LTO when
+            // Bevy is linked as a dylib.
+            let other = if self.hotpatching.is_some() {
+                Some("hotpatch=dx")
+            } else if matches!(self.dynamic, Some(Dynamic::DynamicLinking)) {
+                Some("dynamic=dynamic-linking")
+            } else {
+                None
+            };
+            if let Some(other) = other {
+                return Err(ScenarioError::ProfileConflict {
+                    profile: profile.label(),
+                    other,
+                });
+            }
+        }
+        if self.channel == Channel::Stable
+            && let Some((axis, value)) = self.nightly_only_axis()
+        {
+            return Err(ScenarioError::NightlyOnly { axis, value });
+        }
+        Ok(())
+    }
+
+    /// Whether the payload is built for Linux, natively or cross-compiled.
+    fn builds_for_linux(&self) -> bool {
+        match self.target {
+            Some(target) => matches!(
+                target,
+                Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu | Target::X86_64LinuxMusl
+            ),
+            None => cfg!(target_os = "linux"),
+        }
+    }
+
+    /// The first axis value that relies on unstable `-Z` flags.
+    fn nightly_only_axis(&self) -> Option<(&'static str, &'static str)> {
+        if matches!(self.dynamic, Some(Dynamic::ShareGenerics)) {
+            Some(("dynamic", "share-generics"))
+        } else if self.build_std.is_some() {
+            Some(("build-std", "std"))
+        } else {
+            None
+        }
+    }
+}
+
+impl ScenarioBuilder {
+    pub fn linker(mut self, linker: Linker) -> Self {
+        self.scenario.linker = Some(linker);
+        self
+    }
+
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.scenario.cache = Some(cache);
+        self
+    }
+
+    pub fn dynamic(mut self, dynamic: Dynamic) -> Self {
+        self.scenario.dynamic = Some(dynamic);
+        self
+    }
+
+    pub fn hotpatching(mut self, hotpatching: Hotpatching) -> Self {
+        self.scenario.hotpatching = Some(hotpatching);
+        self
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.scenario.target = Some(target);
+        self
+    }
+
+    pub fn channel(mut self, channel: Channel) -> Self {
+        self.scenario.channel = channel;
+        self
+    }
+
+    pub fn build_std(mut self, build_std: BuildStd) -> Self {
+        self.scenario.build_std = Some(build_std);
+        self
+    }
+
+    pub fn trace(mut self, trace: Trace) -> Self {
+        self.scenario.trace = Some(trace);
+        self
+    }
+
+    pub fn window_backend(mut self, backend: WindowBackend) -> Self {
+        self.scenario.window_backend = Some(backend);
+        self
+    }
+
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.scenario.registry = Some(registry);
+        self
+    }
+
+    pub fn resolver(mut self, resolver: Resolver) -> Self {
+        self.scenario.resolver = Some(resolver);
+        self
+    }
+
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.scenario.profile = Some(profile);
+        self
+    }
+
+    pub fn debug_assertions(mut self, assertions: DebugAssertions) -> Self {
+        self.scenario.debug_assertions = Some(assertions);
+        self
+    }
+
+    pub fn warm_cache(mut self) -> Self {
+        self.scenario.warm_cache = true;
+        self
+    }
+
+    /// Validates the combination and checks that the tools it needs are on
+    /// the local `PATH`.
+    pub fn build(self) -> Result<Scenario, ScenarioError> {
+        let scenario = self.scenario;
+        scenario.validate()?;
+        if let Some(Hotpatching::Dx) = scenario.hotpatching {
+            require_tool("dx", "hotpatching")?;
+        }
+        match scenario.cache {
+            Some(Cache::Sscache) => require_tool("sccache", "the sccache cache")?,
+            Some(Cache::Cachepot) => require_tool("cachepot", "the cachepot cache")?,
+            _ => {}
+        }
+        if scenario.linker == Some(Linker::Zig) {
+            require_tool("zig", "the zig linker")?;
+            require_tool("cargo-zigbuild", "the zig linker")?;
+        }
+        if scenario.linker == Some(Linker::Gold) {
+            require_tool("ld.gold", "the gold linker")?;
+        }
+        Ok(scenario)
+    }
+}
+
+impl Scenario {
+    /// Tools this scenario needs that are not on the local `PATH`.
+    pub fn missing_tools(&self) -> Vec<&'static str> {
+        let mut tools = Vec::new();
+        if let Some(Hotpatching::Dx) = self.hotpatching {
+            tools.push("dx");
+        }
+        match self.cache {
+            Some(Cache::Sscache) => tools.push("sccache"),
+            Some(Cache::Cachepot) => tools.push("cachepot"),
+            _ => {}
+        }
+        match self.target {
+            Some(Target::X86_64WindowsMsvc) => tools.push("cargo-xwin"),
+            Some(Target::Aarch64Android) => tools.push("cargo-ndk"),
+            Some(Target::Wasm32) => tools.push("wasm-opt"),
+            _ => {}
+        }
+        if self.linker == Some(Linker::Zig) {
+            tools.extend(["zig", "cargo-zigbuild"]);
+        } else {
+            if self.linker == Some(Linker::Gold) {
+                tools.push("ld.gold");
+            }
+            tools.extend(self.target.and_then(|target| target.cross_linker()));
+        }
+        tools.retain(|tool| !on_path(tool));
+        tools
+    }
+
+    /// Built-in tags for `--tags` and `--skip-tags`.
+    pub fn tags(&self) -> Vec<&'static str> {
+        let mut tags = Vec::new();
+        if self.target.is_some() {
+            tags.push("cross");
+        }
+        // `dx serve` opens the payload's window.
+        if self.hotpatching.is_some() {
+            tags.push("needs-gpu");
+        }
+        if self.nightly_only_axis().is_some() {
+            tags.push("nightly-only");
+        }
+        // Rebuilding the standard library dwarfs every other clean build.
+        if self.build_std.is_some() {
+            tags.push("slow");
+        }
+        tags
+    }
+}
+
+fn require_tool(tool: &'static str, needed_for: &'static str) -> Result<(), ScenarioError> {
+    if on_path(tool) {
+        Ok(())
+    } else {
+        Err(ScenarioError::ToolMissing { tool, needed_for })
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    let file = format!("{program}{}", std::env::consts::EXE_SUFFIX);
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| Path::new(&dir).join(&file).is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(builder: ScenarioBuilder) -> ScenarioError {
+        builder.scenario.validate().unwrap_err()
+    }
+
+    #[test]
+    fn compatible_axes_validate() {
+        let scenario = Scenario::builder()
+            .linker(Linker::RustLld)
+            .cache(Cache::Sscache)
+            .warm_cache()
+            .dynamic(Dynamic::ShareGenerics)
+            .target(Target::X86_64LinuxGnu)
+            .build_std(BuildStd::Std);
+
+        assert_eq!(scenario.scenario.validate(), Ok(()));
+    }
+
+    #[test]
+    fn hotpatching_rejects_cross_targets() {
+        let builder = Scenario::builder()
+            .hotpatching(Hotpatching::Dx)
+            .target(Target::Aarch64LinuxGnu);
+
+        assert_eq!(
+            rejection(builder),
+            ScenarioError::HotpatchCrossTarget {
+                target: Target::Aarch64LinuxGnu.triple()
+            }
+        );
+    }
+
+    #[test]
+    fn dynamic_linking_rejects_musl() {
+        let builder = Scenario::builder()
+            .dynamic(Dynamic::DynamicLinking)
+            .target(Target::X86_64LinuxMusl);
+
+        assert_eq!(
+            rejection(builder),
+            ScenarioError::StaticTarget {
+                target: Target::X86_64LinuxMusl.triple()
+            }
+        );
+    }
+
+    #[test]
+    fn build_std_needs_a_target() {
+        let builder = Scenario::builder().build_std(BuildStd::Std);
+
+        assert_eq!(rejection(builder), ScenarioError::BuildStdNeedsTarget);
+    }
+
+    #[test]
+    fn tracy_rejects_wasm() {
+        let builder = Scenario::builder()
+            .trace(Trace::Tracy)
+            .target(Target::Wasm32);
+
+        assert_eq!(
+            rejection(builder),
+            ScenarioError::TracyUnsupported {
+                target: Target::Wasm32.triple()
+            }
+        );
+    }
+
+    #[test]
+    fn window_backend_is_linux_only() {
+        let builder = Scenario::builder()
+            .window_backend(WindowBackend::Wayland)
+            .target(Target::X86_64WindowsMsvc);
+
+        assert_eq!(
+            rejection(builder),
+            ScenarioError::LinuxOnly {
+                axis: "window-backend",
+                value: WindowBackend::Wayland.label(),
+            }
+        );
+    }
+
+    #[test]
+    fn gold_is_linux_only() {
+        let builder = Scenario::builder()
+            .linker(Linker::Gold)
+            .target(Target::Aarch64Android);
+
+        assert!(matches!(
+            rejection(builder),
+            ScenarioError::LinuxOnly { axis: "linker", .. }
+        ));
+    }
+
+    #[test]
+    fn macos_linkers_reject_cross_targets() {
+        for linker in [Linker::LdClassic, Linker::MacLld] {
+            let builder = Scenario::builder()
+                .linker(linker)
+                .target(Target::X86_64LinuxGnu);
+
+            assert!(matches!(
+                rejection(builder),
+                ScenarioError::MacosOnly { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn zig_rejects_tools_that_drive_their_own_builds() {
+        let conflicts = [
+            (
+                Scenario::builder().hotpatching(Hotpatching::Dx),
+                "hotpatch=dx",
+            ),
+            (
+                Scenario::builder().target(Target::X86_64WindowsMsvc),
+                "cargo-xwin",
+            ),
+            (
+                Scenario::builder().target(Target::Aarch64Android),
+                "cargo-ndk",
+            ),
+            (
+                Scenario::builder().target(Target::Wasm32),
+                "target=wasm32-unknown-unknown",
+            ),
+        ];
+
+        for (builder, other) in conflicts {
+            assert_eq!(
+                rejection(builder.linker(Linker::Zig)),
+                ScenarioError::ZigConflict { other }
+            );
+        }
+    }
+
+    #[test]
+    fn warm_cache_needs_a_cache_server() {
+        for builder in [
+            Scenario::builder().warm_cache(),
+            Scenario::builder().cache(Cache::Wrapper).warm_cache(),
+        ] {
+            assert_eq!(rejection(builder), ScenarioError::WarmCacheWithoutServer);
+        }
+    }
+
+    #[test]
+    fn profile_rejects_hotpatching_and_dynamic_linking() {
+        let conflicts = [
+            (
+                Scenario::builder().hotpatching(Hotpatching::Dx),
+                "hotpatch=dx",
+            ),
+            (
+                Scenario::builder().dynamic(Dynamic::DynamicLinking),
+                "dynamic=dynamic-linking",
+            ),
+        ];
+
+        for (builder, other) in conflicts {
+            assert_eq!(
+                rejection(builder.profile(Profile::ThinLto)),
+                ScenarioError::ProfileConflict {
+                    profile: Profile::ThinLto.label(),
+                    other,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn stable_rejects_nightly_only_axes() {
+        let conflicts = [
+            (
+                Scenario::builder().dynamic(Dynamic::ShareGenerics),
+                ("dynamic", "share-generics"),
+            ),
+            (
+                Scenario::builder()
+                    .build_std(BuildStd::Std)
+                    .target(Target::X86_64LinuxGnu),
+                ("build-std", "std"),
+            ),
+        ];
+
+        for (builder, (axis, value)) in conflicts {
+            assert_eq!(
+                rejection(builder.channel(Channel::Stable)),
+                ScenarioError::NightlyOnly { axis, value }
+            );
+        }
+    }
+
+    #[test]
+    fn missing_tools_are_reported() {
+        assert_eq!(
+            require_tool("sample-test-no-such-tool", "this test"),
+            Err(ScenarioError::ToolMissing {
+                tool: "sample-test-no-such-tool",
+                needed_for: "this test",
+            })
+        );
+    }
+}
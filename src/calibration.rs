@@ -0,0 +1,128 @@
+//! Machine calibration: the time to build a fixed reference crate with a
+//! pinned toolchain, stored with each run so results from different machines
+//! can be compared relative to the machine that produced them.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::time::Instant;
+
+use crate::remote::Host;
+use crate::stats;
+
+/// Toolchain the reference crate is always built with, independent of the
+/// toolchain the scenarios use.
+const TOOLCHAIN: &str = "1.90.0";
+
+/// Timed builds; the median is stored.
+const SAMPLES: usize = 3;
+
+/// Number of generated items in the reference crate, sized so a build takes a
+/// few seconds on a typical workstation.
+const ITEMS: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calibration {
+    pub toolchain: String,
+    /// Median wall time of a clean release build of the reference crate.
+    pub seconds: f64,
+}
+
+pub fn run(host: &Host) -> Result<Calibration> {
+    let dir = tempfile::Builder::new()
+        .prefix("bench-calibration-")
+        .tempdir()
+        .context("failed to create calibration workspace")?;
+    fs::create_dir_all(dir.path().join("src")).context("failed to create calibration src")?;
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"bench-calibration\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .context("failed to write calibration Cargo.toml")?;
+    // Written up front so every cargo call can pass `--locked`.
+    fs::write(
+        dir.path().join("Cargo.lock"),
+        "version = 4\n\n[[package]]\nname = \"bench-calibration\"\nversion = \"0.1.0\"\n",
+    )
+    .context("failed to write calibration Cargo.lock")?;
+    fs::write(
+        dir.path().join("rust-toolchain.toml"),
+        format!("[toolchain]\nchannel = \"{TOOLCHAIN}\"\nprofile = \"minimal\"\n"),
+    )
+    .context("failed to write calibration rust-toolchain.toml")?;
+    fs::write(dir.path().join("src/main.rs"), reference_source())
+        .context("failed to write calibration source")?;
+
+    let exec_dir = host.workspace_dir(dir.path());
+    host.push(dir.path(), &exec_dir)?;
+    let result = time_builds(host, &exec_dir);
+    host.remove(&exec_dir)?;
+    result
+}
+
+fn time_builds(host: &Host, exec_dir: &str) -> Result<Calibration> {
+    // The first build may install the pinned toolchain; it is not timed.
+    cargo(
+        host,
+        exec_dir,
+        &["build", "--release", "--quiet", "--locked"],
+    )?;
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        cargo(host, exec_dir, &["clean", "--quiet", "--locked"])?;
+        let start = Instant::now();
+        cargo(
+            host,
+            exec_dir,
+            &["build", "--release", "--quiet", "--locked"],
+        )?;
+        samples.push(start.elapsed().as_secs_f64());
+    }
+    Ok(Calibration {
+        toolchain: TOOLCHAIN.to_string(),
+        seconds: stats::median(&samples),
+    })
+}
+
+fn cargo(host: &Host, exec_dir: &str, args: &[&str]) -> Result<()> {
+    let status = host
+        .command(exec_dir, &[], "cargo")
+        .args(args)
+        .status()
+        .context("failed to run cargo for calibration")?;
+    if !status.success() {
+        bail!(
+            "calibration `cargo {}` failed with status {status}",
+            args.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// Deterministic source with enough monomorphization and codegen work to make
+/// the build time reflect CPU and disk speed rather than process startup.
+fn reference_source() -> String {
+    let mut source = String::from(
+        "trait Work {\n    fn work(&self, n: u64) -> u64;\n}\n\n\
+         fn fold<T: Work>(item: &T, n: u64) -> u64 {\n    \
+         (0..n).fold(0, |acc, x| acc.wrapping_add(item.work(x)))\n}\n\n",
+    );
+    for i in 0..ITEMS {
+        let _ = write!(
+            source,
+            "struct S{i}(u64);\n\
+             impl Work for S{i} {{\n    \
+             fn work(&self, n: u64) -> u64 {{\n        \
+             let v: Vec<u64> = (0..n % 64).map(|x| x.wrapping_mul(self.0 ^ {i})).collect();\n        \
+             v.iter().rev().fold(n, |acc, x| acc.rotate_left(3) ^ x)\n    \
+             }}\n}}\n\n"
+        );
+    }
+    source.push_str("fn main() {\n    let mut total = 0u64;\n");
+    for i in 0..ITEMS {
+        let _ = writeln!(source, "    total ^= fold(&S{i}({i}), 8);");
+    }
+    source.push_str("    println!(\"{total}\");\n}\n");
+    source
+}
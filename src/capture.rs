@@ -0,0 +1,134 @@
+//! Byte-level capture of tool output. Tools print invalid UTF-8, progress
+//! bars redrawn with carriage returns, and lines of several megabytes (cargo's
+//! JSON messages), none of which may end or stall a reader. Each line is kept
+//! byte for byte for the log files, and the rest of the harness matches
+//! markers against a sanitized view of it.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+/// Longest raw line kept in one piece; longer ones are split, so a tool that
+/// never prints a newline cannot exhaust memory.
+const MAX_RAW_LINE: usize = 16 * 1024 * 1024;
+/// Characters of a line the sanitized view keeps.
+const MAX_TEXT_CHARS: usize = 4096;
+
+/// Reads `reader` to its end and calls `on_line` with each line, without its
+/// `\n` or `\r\n`, until `on_line` returns `false`.
+pub(crate) fn read_lines(
+    mut reader: impl BufRead,
+    mut on_line: impl FnMut(&[u8]) -> bool,
+) -> io::Result<()> {
+    let mut line = Vec::new();
+    let mut emit = |line: &mut Vec<u8>| {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let more = on_line(line);
+        line.clear();
+        more
+    };
+    loop {
+        let buffer = match reader.fill_buf() {
+            Ok(buffer) => buffer,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        if buffer.is_empty() {
+            break;
+        }
+        let room = MAX_RAW_LINE - line.len();
+        let window = &buffer[..buffer.len().min(room)];
+        match window.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                line.extend_from_slice(&window[..end]);
+                reader.consume(end + 1);
+                if !emit(&mut line) {
+                    return Ok(());
+                }
+            }
+            None => {
+                let read = window.len();
+                line.extend_from_slice(window);
+                reader.consume(read);
+                if line.len() == MAX_RAW_LINE && !emit(&mut line) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if !line.is_empty() {
+        emit(&mut line);
+    }
+    Ok(())
+}
+
+/// The [normalized](normalize) text of `raw`, decoded with invalid UTF-8
+/// replaced and cut to `MAX_TEXT_CHARS`.
+pub(crate) fn sanitize(raw: &[u8]) -> String {
+    let text = String::from_utf8_lossy(raw);
+    let shown = normalize(&text);
+    match shown.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => format!("{} [... {} bytes in total]", &shown[..end], raw.len()),
+        None => shown.into_owned(),
+    }
+}
+
+/// What a terminal would show of `line`: the last carriage-return segment
+/// that leaves anything visible, without ANSI escape sequences or other
+/// control characters. Markers are matched against this, so neither colors
+/// nor a progress bar redrawn over a line can hide or split them.
+pub(crate) fn normalize(line: &str) -> Cow<'_, str> {
+    if !line.contains(|c: char| c.is_control() && c != '\t') {
+        return Cow::Borrowed(line);
+    }
+    let shown = line
+        .split('\r')
+        .map(strip_escapes)
+        .rfind(|segment| !segment.trim().is_empty())
+        .unwrap_or_default();
+    Cow::Owned(shown)
+}
+
+fn strip_escapes(text: &str) -> String {
+    let mut clean = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            match chars.next() {
+                // CSI, such as a color: parameters up to a final `@`..=`~`.
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, such as a hyperlink: up to BEL or ESC `\`.
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                // Character set selection names the set in one more character.
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                }
+                // Everything else, such as saving the cursor, is ESC and one
+                // character.
+                _ => {}
+            }
+            continue;
+        }
+        if !c.is_control() || c == '\t' {
+            clean.push(c);
+        }
+    }
+    clean
+}
@@ -0,0 +1,811 @@
+//! Command-line interface.
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::NaiveDate;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::filesystem::{WorkspaceRoot, parse_workspace_root};
+use crate::hotpatch::ReadyChannel;
+use crate::mutation::{EditTarget, Mutation};
+use crate::selection::Design;
+use crate::slug::{SlugScheme, parse_slug_scheme};
+use crate::watchdog::StallAction;
+use crate::{
+    Cache, CleanMode, DebugAssertions, Dynamic, Linker, Profile, Registry, Resolver, Target, Trace,
+    WindowBackend,
+};
+
+/// Build and hotpatch benchmarks for a minimal Bevy application.
+#[derive(Debug, Parser)]
+#[command(name = "bench", version, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Configuration file. Defaults to `bench.toml` in the current directory
+    /// when it exists.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Run the benchmark matrix (the default when no subcommand is given).
+    Run(Box<RunCommand>),
+    /// Check a results log against per-phase time budgets and exit non-zero
+    /// if any measured phase exceeds its budget.
+    Gate(GateArgs),
+    /// Compare two results logs phase by phase, marking which changes are
+    /// statistically significant.
+    Diff(DiffArgs),
+    /// Feed a recorded `dx serve` session through the hotpatch detection and
+    /// check it reaches the verdict of the live run.
+    Replay(ReplayArgs),
+    /// Show where a run's timings fall in the community dataset for
+    /// machines like this one.
+    CompareCommunity(CompareCommunityArgs),
+    /// Open an interactive Bevy app charting the results history (needs the
+    /// `dashboard` feature).
+    #[cfg(feature = "dashboard")]
+    Dashboard(DashboardArgs),
+    /// List the scenarios of the matrix with their axis values and whether
+    /// the tools they need are installed.
+    List(Box<ListArgs>),
+    /// Print a shell completion script to stdout.
+    Completions(CompletionsArgs),
+    /// Time clean and touched builds of official examples in a Bevy checkout
+    /// under each scenario.
+    Examples(Box<ExamplesArgs>),
+    /// Time clean and touched builds of a binary in an existing project or
+    /// multi-crate workspace under each scenario.
+    Project(Box<ProjectArgs>),
+    /// Measure the clean and second build cost of single Bevy features on
+    /// top of `default-features = false`, under one scenario.
+    FeatureDelta(Box<FeatureDeltaArgs>),
+    /// Find the Bevy commit where a phase of one scenario got slower than a
+    /// threshold, by bisecting the history between two versions.
+    BisectBevy(Box<BisectBevyArgs>),
+    /// Find the nightly toolchain where a phase of one scenario got slower
+    /// than a threshold, by bisecting the nightlies between two dates.
+    BisectToolchain(Box<BisectToolchainArgs>),
+    /// Write a commented starter `bench.toml`, and optionally a GitHub
+    /// Actions workflow, into the current directory.
+    Init(InitArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RunCommand {
+    /// Run only the scenario with this slug (see `bench list`), even when an
+    /// unchanged result is cached.
+    pub slug: Option<String>,
+
+    /// With a slug: show cargo's full output, keep the workspace afterwards,
+    /// and print the effective environment and every generated file.
+    #[arg(long, requires = "slug")]
+    pub verbose: bool,
+
+    /// Print the files, environment, and commands of the scenario with this
+    /// slug without building anything.
+    #[arg(long, value_name = "SLUG", conflicts_with_all = ["slug", "verbose"])]
+    pub explain: Option<String>,
+
+    #[command(flatten)]
+    pub args: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RunArgs {
+    /// Run every phase on a remote builder over SSH (`user@host`). Workspaces
+    /// are generated locally and mirrored with rsync.
+    #[arg(long, value_name = "USER@HOST")]
+    pub remote: Option<String>,
+
+    /// Directory on the remote host, relative to the login directory, that
+    /// holds the mirrored workspaces.
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "bevy-bench",
+        requires = "remote"
+    )]
+    pub remote_dir: String,
+
+    /// Only benchmark these linkers (comma-separated): default, rust-lld, zig
+    /// (through cargo-zigbuild), on Linux gold, and on macOS ld-classic and
+    /// lld.
+    #[arg(long, value_name = "LIST", value_delimiter = ',', value_parser = parse_linker)]
+    pub linkers: Vec<Option<Linker>>,
+
+    /// Only benchmark these cache settings (comma-separated): none (plain
+    /// incremental), no-incremental, sccache, cachepot, wrapper (the config
+    /// file's `[wrapper]`).
+    #[arg(long, value_name = "LIST", value_delimiter = ',', value_parser = parse_cache)]
+    pub caches: Vec<Option<Cache>>,
+
+    /// Only benchmark these dynamic settings (comma-separated): none,
+    /// dynamic-linking, share-generics.
+    #[arg(long, value_name = "LIST", value_delimiter = ',', value_parser = parse_dynamic)]
+    pub dynamics: Vec<Option<Dynamic>>,
+
+    /// Only benchmark scenarios with at least one of these tags
+    /// (comma-separated). Built-in tags are cross, needs-gpu, nightly-only,
+    /// and slow; custom axis values can add more.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub tags: Vec<String>,
+
+    /// Leave out scenarios with any of these tags (comma-separated).
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub skip_tags: Vec<String>,
+
+    /// How scenarios are named: full (every axis label), short (a hash of
+    /// the full slug), or a pattern such as `{linker}-{cache}-{hash}` with
+    /// axis names as placeholders. Run logs keep the full slug alongside.
+    #[arg(long, value_name = "SCHEME", default_value = "full", value_parser = parse_slug_scheme)]
+    pub slug_scheme: SlugScheme,
+
+    /// Also benchmark every scenario under `dx serve --hot-patch`.
+    #[arg(long, overrides_with = "no_hotpatch")]
+    pub hotpatch: bool,
+
+    /// Skip the `dx serve` hotpatch scenarios (the default), overriding an
+    /// earlier `--hotpatch`.
+    #[arg(long, overrides_with = "hotpatch")]
+    pub no_hotpatch: bool,
+
+    /// Run the full cross-product, or a balanced fraction that still
+    /// estimates each axis's main effect.
+    #[arg(long, value_enum, default_value_t = Design::Full)]
+    pub design: Design,
+
+    /// Run only N scenarios picked at random from the matrix, reproducibly
+    /// for a given `--seed`.
+    #[arg(long, value_name = "N", conflicts_with = "design")]
+    pub sample: Option<usize>,
+
+    /// Seed for `--sample`.
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    pub seed: u64,
+
+    /// Stop starting scenarios once this much time has passed (e.g. `2h`),
+    /// ordering them so every axis value is covered as early as possible.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub budget: Option<Duration>,
+
+    /// Also benchmark cross builds for this target (repeatable). Host builds
+    /// are always included.
+    #[arg(long = "target", value_name = "TRIPLE")]
+    pub targets: Vec<Target>,
+
+    /// Also benchmark every scenario with Bevy's tracing instrumentation
+    /// (repeatable): trace, or tracy for `trace_tracy`.
+    #[arg(long = "trace", value_name = "KIND")]
+    pub traces: Vec<Trace>,
+
+    /// On Linux, also benchmark every scenario with only this windowing
+    /// backend instead of Bevy's default of both (repeatable): x11, wayland.
+    #[arg(long = "window-backend", value_name = "BACKEND")]
+    pub window_backends: Vec<WindowBackend>,
+
+    /// Also benchmark every scenario with this crates.io index protocol
+    /// (repeatable): sparse, git. Adds a timed `cargo fetch` into an empty
+    /// `CARGO_HOME` before the clean build.
+    #[arg(long = "registry", value_name = "PROTOCOL")]
+    pub registries: Vec<Registry>,
+
+    /// Also benchmark every scenario with this dependency resolver version
+    /// (repeatable): 1, 2, 3.
+    #[arg(long = "resolver", value_name = "VERSION")]
+    pub resolvers: Vec<Resolver>,
+
+    /// Also benchmark every scenario with this Cargo profile instead of
+    /// `dev` (repeatable): thin-lto, a release build with ThinLTO and its
+    /// incremental cache.
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub profiles: Vec<Profile>,
+
+    /// Also benchmark every scenario with `debug-assertions` set this way
+    /// (repeatable): on, off, payload-only, deps-only.
+    #[arg(long = "debug-assertions", value_name = "SETTING")]
+    pub debug_assertions: Vec<DebugAssertions>,
+
+    /// Build every scenario with each of these rustup toolchains, e.g.
+    /// `stable,beta,nightly,1.80.0`, instead of the scenario's channel.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub toolchains: Vec<String>,
+
+    /// Run each scenario this many times (each in a fresh workspace) and
+    /// report percentiles and confidence intervals per phase.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub iterations: u32,
+
+    /// Successive halving: run one iteration of every scenario, then the
+    /// remaining `--iterations` only for the K fastest by `--halving-phase`.
+    #[arg(long, value_name = "K", conflicts_with = "budget", value_parser = clap::value_parser!(u32).range(1..))]
+    pub halving: Option<u32>,
+
+    /// With --budget: run two iterations of every scenario, then spend the
+    /// rest of the budget on more iterations where they help the statistics
+    /// most, so noisy and fast scenarios get more than slow, stable ones.
+    #[arg(long, requires = "budget", conflicts_with_all = ["halving", "iterations"])]
+    pub allocate: bool,
+
+    /// Phase that ranks scenarios for `--halving`.
+    #[arg(
+        long,
+        default_value = "modified",
+        requires = "halving",
+        value_parser = ["clean", "second", "modified", "hotpatch"]
+    )]
+    pub halving_phase: String,
+
+    /// Run iterations flagged as outliers (more than 3 MAD from the median)
+    /// once more and replace their measurements.
+    #[arg(long)]
+    pub rerun_outliers: bool,
+
+    /// Re-run scenarios even if the history already has a result for the
+    /// exact same workspace, tool versions, and machine.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print the scenarios a run would benchmark, in order, with estimates
+    /// from history and any missing prerequisites, then exit without
+    /// creating a workspace or starting any tool.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print the final table without colors (also disabled by `NO_COLOR`).
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Slug of the scenario the final table shows speedups against. Defaults
+    /// to the one with every built-in axis at its default value.
+    #[arg(long, value_name = "SLUG")]
+    pub reference: Option<String>,
+
+    /// Skip the reference build that calibrates this machine's speed.
+    #[arg(long)]
+    pub no_calibration: bool,
+
+    /// Share target directories between scenarios whose compile settings are
+    /// identical. Much faster, but clean builds that start with warm
+    /// dependencies are marked `warm_deps` in the results.
+    #[arg(long)]
+    pub share_deps: bool,
+
+    /// After each scenario's clean build, pack its target directory into
+    /// `DIR/<slug>.tar.zst` for a later --restore-target.
+    #[arg(long, value_name = "DIR", conflicts_with = "share_deps")]
+    pub save_target: Option<PathBuf>,
+
+    /// Unpack `DIR/<slug>.tar.zst` into each scenario's target directory
+    /// instead of running its clean build, to measure the incremental phases
+    /// against a warm target directory on a machine that starts from a blank
+    /// disk. Scenarios without a snapshot run their clean build.
+    #[arg(long, value_name = "DIR", conflicts_with = "share_deps")]
+    pub restore_target: Option<PathBuf>,
+
+    /// Also run every sccache and cachepot scenario with its cache filled by
+    /// an untimed build first, next to the default cold cache.
+    #[arg(long)]
+    pub warm_cache: bool,
+
+    /// What the clean phase measures: fresh (a new workspace, so every crate
+    /// is built) or payload (dependencies built untimed, then
+    /// `cargo clean -p` of the payload, so only the leaf crate is rebuilt).
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = CleanMode::Fresh)]
+    pub clean_mode: CleanMode,
+
+    /// The edit the modified, hotpatch, and hotpatch-rebuild phases make to
+    /// the payload: constant (a new value for a `const`), system-body (a
+    /// changed expression in a system), add-system (a new system), or
+    /// add-component (a new component type). Hotpatch and incremental costs
+    /// differ widely by edit kind.
+    #[arg(long, value_enum, value_name = "KIND", default_value_t = Mutation::Constant)]
+    pub mutation: Mutation,
+
+    /// Where the payload code that --mutation edits lives: main (the crate
+    /// root), module (`src/payload.rs`, declared by the root), or leaf-crate
+    /// (a library crate the payload crate depends on), to measure how
+    /// rebuild and hotpatch times depend on where in the dependency graph
+    /// the edit lands.
+    #[arg(long, value_enum, value_name = "WHERE", default_value_t = EditTarget::Main)]
+    pub edit_in: EditTarget,
+
+    /// Pin wgpu, naga, and winit in every generated `Cargo.lock` to the
+    /// versions the Bevy release was tested with, so runs weeks apart compile
+    /// the same dependencies. `[pin]` in the config file overrides or adds
+    /// versions.
+    #[arg(long)]
+    pub pin_deps: bool,
+
+    /// Start cargo, dx, and everything they run with niceness N (10 when N
+    /// is omitted), so a long matrix can run in the background of a
+    /// workstation. Windows uses a below-normal or idle priority class.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(i32).range(1..=19)
+    )]
+    pub nice: Option<i32>,
+
+    /// Cap the memory of every build at SIZE (e.g. `8G`, `512M`); a build
+    /// stopped for exceeding it is recorded as out-of-memory. Local runs on
+    /// Linux (cgroup v2, needs a delegated cgroup) and Windows only.
+    #[arg(long, value_name = "SIZE", value_parser = parse_memory_size)]
+    pub memory_limit: Option<u64>,
+
+    /// Treat a cargo build or dx serve session that printed nothing for
+    /// DURATION as stalled: dump its process tree and last output lines,
+    /// then apply --stall-action. Builds run with `--quiet` unless
+    /// `--verbose`, so pick a window longer than the slowest crate.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub stall_timeout: Option<Duration>,
+
+    /// What to do with a stalled tool: kill it and fail the scenario, or
+    /// keep waiting another window.
+    #[arg(long, value_enum, value_name = "ACTION", default_value_t = StallAction::Kill)]
+    pub stall_action: StallAction,
+
+    /// Also benchmark every cross-target scenario with the standard library
+    /// rebuilt from source (`-Zbuild-std`). Host builds need the host triple
+    /// passed with `--target`.
+    #[arg(long)]
+    pub build_std: bool,
+
+    /// After the builds, run `cargo llvm-lines` on the payload and report the
+    /// N functions that generate the most LLVM IR (10 when N is omitted).
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "10"
+    )]
+    pub llvm_lines: Option<usize>,
+
+    /// After the builds, run `cargo bloat` on the payload binary and report
+    /// its N largest crates and functions (10 when N is omitted).
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "10"
+    )]
+    pub bloat: Option<usize>,
+
+    /// Run the clean build with `cargo build --timings` and report the N
+    /// Bevy crates that take longest to compile (10 when N is omitted).
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "10"
+    )]
+    pub crate_timings: Option<usize>,
+
+    /// After the builds, start the payload with `cargo run` and time its
+    /// first frame. Needs a display; skipped for cross-compiled targets.
+    #[arg(long)]
+    pub first_frame: bool,
+
+    /// After the second build, copy the workspace, target directory
+    /// included, to another path and time a rebuild there, recording how
+    /// many build units cargo reused. Shows whether caching target
+    /// directories in CI pays off for each configuration.
+    #[arg(long)]
+    pub relocate: bool,
+
+    /// Link through a generated wrapper that times every linker invocation,
+    /// and store the time each build phase spent linking as its `link_time`
+    /// metric. Local, non-Windows builds with the system linker driver only.
+    #[arg(long)]
+    pub link_time: bool,
+
+    /// Save every `dx serve` session (output lines with timestamps and the
+    /// verdict) to DIR/<slug>.ron, for `bench replay`.
+    #[arg(long, value_name = "DIR")]
+    pub record_dx: Option<PathBuf>,
+
+    /// How the payload reports readiness and its value under `dx serve`:
+    /// printed to stdout as forwarded by dx, appended to a file the harness
+    /// follows, or sent over the harness protocol along with frame times.
+    /// `file` and `socket` need a local run.
+    #[arg(long, value_enum, default_value_t = ReadyChannel::Stdout)]
+    pub ready_channel: ReadyChannel,
+
+    /// How long a hotpatch gets to show up in the running payload after the
+    /// source change, before the session is stopped and the scenario fails.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "2m")]
+    pub patch_timeout: Duration,
+
+    /// After the run, upload the successful scenarios' axis values and
+    /// timings with a coarse hardware class (platform, CPU model and count,
+    /// rounded memory, rustc version) to this URL. No paths, hostnames, or
+    /// slugs are sent.
+    #[arg(long, value_name = "ENDPOINT")]
+    pub submit: Option<String>,
+
+    /// Create workspaces under NAME=PATH instead of the system temp dir.
+    /// Repeat to compare filesystems (e.g. `ntfs=C:\bench devdrive=D:\bench`);
+    /// NAME is appended to the slug.
+    #[arg(long = "workspace-root", value_name = "NAME=PATH", value_parser = parse_workspace_root)]
+    pub workspace_roots: Vec<WorkspaceRoot>,
+
+    /// Windows only, needs an elevated shell: run every scenario twice, in a
+    /// directory Defender scans and in one temporarily excluded from it.
+    #[arg(long)]
+    pub defender_pair: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DiffArgs {
+    /// Results log to compare against.
+    pub baseline: PathBuf,
+    /// Results log being evaluated.
+    pub candidate: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReplayArgs {
+    /// Recording written with `--record-dx`.
+    pub recording: PathBuf,
+    /// Keep the recorded spacing between lines instead of replaying at once.
+    #[arg(long)]
+    pub realtime: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CompareCommunityArgs {
+    /// Results log written by a previous run on this machine.
+    pub results: PathBuf,
+    /// URL of the aggregated community dataset (JSON).
+    #[arg(long, value_name = "URL")]
+    pub dataset: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ListArgs {
+    /// Print a JSON array instead of a table.
+    #[arg(long)]
+    pub json: bool,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Scenario, metric, and threshold shared by the bisection commands.
+#[derive(Debug, Clone, Args)]
+pub struct BisectArgs {
+    /// Slug of the scenario to measure (see `bench list`).
+    #[arg(long, value_name = "SLUG")]
+    pub scenario: String,
+
+    /// Phase whose time is compared.
+    #[arg(
+        long,
+        default_value = "second",
+        value_parser = ["clean", "second", "modified", "hotpatch"]
+    )]
+    pub metric: String,
+
+    /// Slowdown over the `--from` timing, in percent, that counts as a
+    /// regression.
+    #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+    pub threshold: f64,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExamplesArgs {
+    /// Examples to build, by their name in Bevy's `Cargo.toml`, e.g.
+    /// `--example 3d_scene breakout`.
+    #[arg(long = "example", value_name = "NAME", num_args = 1.., required = true)]
+    pub examples: Vec<String>,
+
+    /// Existing Bevy checkout to build in, instead of a cached clone of
+    /// `--repo`.
+    #[arg(long, value_name = "DIR")]
+    pub bevy: Option<PathBuf>,
+
+    /// Bevy repository to clone when no `--bevy` checkout is given.
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://github.com/bevyengine/bevy",
+        conflicts_with = "bevy"
+    )]
+    pub repo: String,
+
+    /// Branch, tag, or commit of `--repo` to build.
+    #[arg(
+        long,
+        value_name = "REV",
+        default_value = "main",
+        conflicts_with = "bevy"
+    )]
+    pub rev: String,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ProjectArgs {
+    /// Directory of the project, or of one package of its workspace.
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub path: PathBuf,
+
+    /// Package to build, for workspaces of several packages. Defaults to the
+    /// package in `--path`.
+    #[arg(long, value_name = "NAME")]
+    pub package: Option<String>,
+
+    /// Binary of the package to build, when it has several.
+    #[arg(long, value_name = "NAME")]
+    pub bin: Option<String>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FeatureDeltaArgs {
+    /// Slug of the scenario to build (see `bench list`); dx hotpatch
+    /// scenarios need Bevy's default features and cannot be used.
+    #[arg(long, value_name = "SLUG")]
+    pub scenario: String,
+
+    /// Bevy features to add one at a time (comma-separated), e.g.
+    /// `bevy_pbr,bevy_audio,multi_threaded`.
+    #[arg(long, value_name = "LIST", value_delimiter = ',', required = true)]
+    pub features: Vec<String>,
+
+    /// Features every build keeps on top of `default-features = false`
+    /// (comma-separated).
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub base: Vec<String>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BisectBevyArgs {
+    /// Bevy version (e.g. `0.15`) or git revision known to be fast.
+    #[arg(long, value_name = "VERSION")]
+    pub from: String,
+
+    /// Bevy version or git revision known to be slow.
+    #[arg(long, value_name = "VERSION")]
+    pub to: String,
+
+    /// Bevy repository to take the commits from.
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://github.com/bevyengine/bevy"
+    )]
+    pub repo: String,
+
+    #[command(flatten)]
+    pub bisect: BisectArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BisectToolchainArgs {
+    /// Date of a nightly known to be fast, e.g. `2025-06-01`.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub from: NaiveDate,
+
+    /// Date of a nightly known to be slow.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub to: NaiveDate,
+
+    #[command(flatten)]
+    pub bisect: BisectArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CompletionsArgs {
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct InitArgs {
+    /// Also write `.github/workflows/bevy-build-bench.yml`.
+    #[arg(long)]
+    pub github_actions: bool,
+    /// Overwrite files that already exist.
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Writes the completion script for `shell` to stdout.
+pub fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), "bench", &mut std::io::stdout());
+}
+
+#[cfg(feature = "dashboard")]
+#[derive(Debug, Clone, Args)]
+pub struct DashboardArgs {
+    /// Directory holding the run logs.
+    #[arg(long, value_name = "DIR", default_value = "results")]
+    pub results: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GateArgs {
+    /// Results log written by a previous run.
+    pub results: PathBuf,
+
+    #[command(flatten)]
+    pub budgets: PhaseBudgets,
+}
+
+/// Maximum allowed duration per phase, e.g. `30s`, `2m30s`, or `1.5` seconds.
+#[derive(Debug, Clone, Copy, Default, Args, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseBudgets {
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_clean: Option<Duration>,
+
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_second: Option<Duration>,
+
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_modified: Option<Duration>,
+
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_hotpatch: Option<Duration>,
+}
+
+impl PhaseBudgets {
+    /// Fills every budget missing from `self` with the one from `fallback`.
+    pub fn or(self, fallback: PhaseBudgets) -> PhaseBudgets {
+        PhaseBudgets {
+            max_clean: self.max_clean.or(fallback.max_clean),
+            max_second: self.max_second.or(fallback.max_second),
+            max_modified: self.max_modified.or(fallback.max_modified),
+            max_hotpatch: self.max_hotpatch.or(fallback.max_hotpatch),
+        }
+    }
+}
+
+fn parse_linker(input: &str) -> Result<Option<Linker>> {
+    Ok(match input {
+        "default" | "none" => None,
+        "rust-lld" => Some(Linker::RustLld),
+        "ld-classic" => Some(Linker::LdClassic),
+        "lld" => Some(Linker::MacLld),
+        "zig" => Some(Linker::Zig),
+        "gold" => Some(Linker::Gold),
+        _ => {
+            bail!("unknown linker `{input}` (use default, rust-lld, gold, zig, ld-classic, or lld)")
+        }
+    })
+}
+
+fn parse_cache(input: &str) -> Result<Option<Cache>> {
+    Ok(match input {
+        "none" | "default" | "incremental" => None,
+        "no-incremental" => Some(Cache::DisableIncremental),
+        "sccache" => Some(Cache::Sscache),
+        "cachepot" => Some(Cache::Cachepot),
+        "wrapper" => Some(Cache::Wrapper),
+        _ => bail!(
+            "unknown cache `{input}` (use none, no-incremental, sccache, cachepot, or wrapper)"
+        ),
+    })
+}
+
+fn parse_dynamic(input: &str) -> Result<Option<Dynamic>> {
+    Ok(match input {
+        "none" | "default" => None,
+        "dynamic-linking" => Some(Dynamic::DynamicLinking),
+        "share-generics" => Some(Dynamic::ShareGenerics),
+        _ => bail!(
+            "unknown dynamic setting `{input}` (use none, dynamic-linking, or share-generics)"
+        ),
+    })
+}
+
+fn parse_date(input: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d").context("expected a date like 2025-06-01")
+}
+
+/// Parses durations such as `90`, `1.5s`, `500ms`, `2m30s`, `1h 30m`, or
+/// `1h`. A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("invalid duration: empty");
+    }
+    if let Ok(seconds) = input.parse::<f64>() {
+        return seconds_to_duration(seconds, input);
+    }
+
+    let mut total = 0.0;
+    let mut rest = input;
+    while !rest.is_empty() {
+        rest = rest.trim_start();
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let unit = unit.trim();
+        let Ok(value) = number.parse::<f64>() else {
+            bail!("invalid duration `{input}`");
+        };
+        let scale = match unit {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => bail!("invalid duration unit `{unit}` in `{input}` (use ms, s, m, or h)"),
+        };
+        total += value * scale;
+        rest = tail;
+    }
+    seconds_to_duration(total, input)
+}
+
+/// Parses sizes such as `8G`, `8GiB`, `512M`, or `1048576`. Units are binary;
+/// a bare number is taken as bytes.
+fn parse_memory_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(digits);
+    let Ok(value) = number.parse::<u64>() else {
+        bail!("invalid size `{input}`");
+    };
+    let scale: u64 = match unit.trim().trim_end_matches("iB").trim_end_matches('B') {
+        "" => 1,
+        "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        "T" | "t" => 1 << 40,
+        _ => bail!("invalid size unit `{unit}` in `{input}` (use K, M, G, or T)"),
+    };
+    value
+        .checked_mul(scale)
+        .filter(|bytes| *bytes > 0)
+        .with_context(|| format!("invalid size `{input}`"))
+}
+
+/// Negative, NaN, and durations too long to represent are rejected rather
+/// than panicking in `Duration`.
+fn seconds_to_duration(seconds: f64, input: &str) -> Result<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|err| anyhow!("invalid duration `{input}` ({err})"))
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_duration(&value)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
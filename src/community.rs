@@ -0,0 +1,359 @@
+//! Opt-in sharing of results with a community dataset of Bevy build times,
+//! and comparing a run against it. Only axis values, timings, and a coarse
+//! hardware class are sent: no paths, hostnames, slugs, custom axis names,
+//! or command lines.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::cli::CompareCommunityArgs;
+use crate::history;
+use crate::metadata::SCHEMA_VERSION;
+use crate::redact;
+use crate::remote::Host;
+use crate::{RunRecord, ScenarioTimingRecord};
+
+/// Percentile from which a timing counts as slow for its class.
+const SLOW_PERCENTILE: f64 = 80.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub schema_version: u32,
+    pub tool_version: String,
+    pub hardware: HardwareClass,
+    /// Reference build time on the machine, so submissions from different
+    /// hardware can be normalized.
+    pub calibration_seconds: Option<f64>,
+    pub scenarios: Vec<SubmittedScenario>,
+}
+
+/// What kind of machine ran the benchmark, without identifying it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwareClass {
+    /// Kernel name and architecture, e.g. `Linux x86_64`.
+    pub platform: String,
+    pub cpu_model: Option<String>,
+    pub cpus: Option<u32>,
+    /// Installed memory rounded to a power of two.
+    pub memory_gib: Option<u32>,
+    pub rustc: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedScenario {
+    /// Built-in axis name and value pairs.
+    pub axes: Vec<(String, String)>,
+    timings: ScenarioTimingRecord,
+    pub iterations: usize,
+}
+
+impl Submission {
+    /// The successful scenarios of `record`, measured on `host`.
+    fn from_run(record: &RunRecord, host: &Host) -> Self {
+        let scenarios = record
+            .scenarios
+            .iter()
+            .filter(|scenario| scenario.error.is_none())
+            .map(|scenario| SubmittedScenario {
+                axes: scenario
+                    .scenario
+                    .axes()
+                    .iter()
+                    .map(|(axis, value)| (axis.to_string(), value.to_string()))
+                    .collect(),
+                timings: scenario.timings.clone(),
+                iterations: scenario.iterations.len().max(1),
+            })
+            .collect();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            hardware: HardwareClass::probe(host),
+            calibration_seconds: record
+                .calibration
+                .as_ref()
+                .map(|calibration| calibration.seconds),
+            scenarios,
+        }
+    }
+}
+
+impl HardwareClass {
+    pub fn probe(host: &Host) -> Self {
+        let platform = host
+            .probe("uname", &["-sm"])
+            .unwrap_or_else(|| format!("{} {}", std::env::consts::OS, std::env::consts::ARCH));
+        Self {
+            platform,
+            cpu_model: cpu_model(host),
+            cpus: host.probe("nproc", &[]).and_then(|cpus| cpus.parse().ok()),
+            memory_gib: memory_bytes(host).map(|bytes| {
+                let gib = (bytes as f64 / (1u64 << 30) as f64).round().max(1.0) as u32;
+                gib.next_power_of_two()
+            }),
+            rustc: host.probe("rustc", &["--version"]),
+        }
+    }
+
+    /// The grouping the community dataset uses for comparable machines.
+    pub fn cpu_class(&self) -> Option<String> {
+        let model = self.cpu_model.as_deref()?;
+        Some(match self.cpus {
+            Some(cpus) => format!("{model}, {cpus} CPUs"),
+            None => model.to_string(),
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.platform.clone()];
+        parts.extend(self.cpu_model.clone());
+        parts.extend(self.cpus.map(|cpus| format!("{cpus} CPUs")));
+        parts.extend(self.memory_gib.map(|gib| format!("{gib} GiB")));
+        parts.join(", ")
+    }
+}
+
+fn cpu_model(host: &Host) -> Option<String> {
+    if let Ok(cpuinfo) = host.read("/proc/cpuinfo") {
+        return String::from_utf8_lossy(&cpuinfo)
+            .lines()
+            .find_map(|line| line.strip_prefix("model name"))
+            .and_then(|rest| rest.split_once(':'))
+            .map(|(_, model)| model.trim().to_string());
+    }
+    host.probe("sysctl", &["-n", "machdep.cpu.brand_string"])
+        .or_else(|| {
+            matches!(host, Host::Local)
+                .then(|| std::env::var("PROCESSOR_IDENTIFIER").ok())
+                .flatten()
+        })
+}
+
+fn memory_bytes(host: &Host) -> Option<u64> {
+    if let Ok(meminfo) = host.read("/proc/meminfo") {
+        let kib: u64 = String::from_utf8_lossy(&meminfo)
+            .lines()
+            .find_map(|line| line.strip_prefix("MemTotal:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        return Some(kib * 1024);
+    }
+    host.probe("sysctl", &["-n", "hw.memsize"])?.parse().ok()
+}
+
+/// Uploads the successful scenarios of `record` to `endpoint` as JSON with
+/// `curl`.
+pub fn submit(endpoint: &str, record: &RunRecord, host: &Host) -> Result<()> {
+    let submission = Submission::from_run(record, host);
+    if submission.scenarios.is_empty() {
+        println!("[bench] No successful scenarios to submit.");
+        return Ok(());
+    }
+    let body = serde_json::to_string(&submission).context("failed to serialize submission")?;
+    let body = redact::redact(&body).into_owned().into_bytes();
+    println!(
+        "[bench] Submitting {} scenario(s) ({}) to {endpoint}",
+        submission.scenarios.len(),
+        submission.hardware.describe()
+    );
+
+    let mut child = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--request", "POST"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", endpoint])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run curl")?;
+    child
+        .stdin
+        .take()
+        .context("curl stdin pipe missing")?
+        .write_all(&body)
+        .context("failed to send the submission to curl")?;
+    let output = child
+        .wait_with_output()
+        .context("failed to wait for curl")?;
+    if !output.status.success() {
+        bail!(
+            "submitting to {endpoint} failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    println!("[bench] Submission accepted.");
+    Ok(())
+}
+
+/// Aggregated community timings, as served at the `--dataset` URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dataset {
+    pub entries: Vec<DatasetEntry>,
+}
+
+/// Percentiles of one phase of one axis combination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetEntry {
+    pub axes: Vec<(String, String)>,
+    /// [`HardwareClass::cpu_class`] of the submissions, or `None` for all
+    /// hardware combined.
+    pub cpu_class: Option<String>,
+    pub phase: String,
+    pub samples: u32,
+    /// `(percentile, seconds)` pairs in increasing order.
+    pub percentiles: Vec<(f64, f64)>,
+}
+
+impl Dataset {
+    /// The entry for this CPU class, falling back to all hardware.
+    fn find(
+        &self,
+        axes: &[(String, String)],
+        phase: &str,
+        cpu_class: Option<&str>,
+    ) -> Option<&DatasetEntry> {
+        let matching = |entry: &&DatasetEntry| entry.phase == phase && entry.axes == axes;
+        cpu_class
+            .and_then(|class| {
+                self.entries
+                    .iter()
+                    .filter(matching)
+                    .find(|entry| entry.cpu_class.as_deref() == Some(class))
+            })
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .filter(matching)
+                    .find(|entry| entry.cpu_class.is_none())
+            })
+    }
+}
+
+/// Where a timing falls among the dataset's percentiles.
+#[derive(Debug, Clone, Copy)]
+enum Rank {
+    Below(f64),
+    At(f64),
+    Above(f64),
+}
+
+impl Rank {
+    fn percentile(self) -> f64 {
+        match self {
+            Self::Below(rank) | Self::At(rank) | Self::Above(rank) => rank,
+        }
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Below(rank) => write!(f, "below p{rank:.0}"),
+            Self::At(rank) => write!(f, "p{rank:.0}"),
+            Self::Above(rank) => write!(f, "above p{rank:.0}"),
+        }
+    }
+}
+
+impl DatasetEntry {
+    /// Interpolated percentile of `seconds` within the known range.
+    fn rank(&self, seconds: f64) -> Option<Rank> {
+        let (first, last) = (self.percentiles.first()?, self.percentiles.last()?);
+        if seconds < first.1 {
+            return Some(Rank::Below(first.0));
+        }
+        if seconds > last.1 {
+            return Some(Rank::Above(last.0));
+        }
+        self.percentiles.windows(2).find_map(|pair| {
+            let ((low_rank, low), (high_rank, high)) = (pair[0], pair[1]);
+            (seconds >= low && seconds <= high).then(|| {
+                Rank::At(if high > low {
+                    low_rank + (seconds - low) / (high - low) * (high_rank - low_rank)
+                } else {
+                    low_rank
+                })
+            })
+        })
+    }
+}
+
+/// `bench compare-community`: reports each phase's percentile among
+/// comparable machines and flags the slow ones.
+pub fn compare(args: &CompareCommunityArgs) -> Result<()> {
+    let record = history::read_run(&args.results)?;
+    let dataset = fetch(&args.dataset)?;
+    let hardware = HardwareClass::probe(&Host::Local);
+    let cpu_class = hardware.cpu_class();
+    println!(
+        "Comparing {} with {} community entries for {}",
+        args.results.display(),
+        dataset.entries.len(),
+        hardware.describe()
+    );
+
+    let mut slow = 0usize;
+    for scenario in record
+        .scenarios
+        .iter()
+        .filter(|scenario| scenario.error.is_none())
+    {
+        let axes: Vec<(String, String)> = scenario
+            .scenario
+            .axes()
+            .iter()
+            .map(|(axis, value)| (axis.to_string(), value.to_string()))
+            .collect();
+        for (phase, seconds) in scenario.timings.phases() {
+            let Some(entry) = dataset.find(&axes, phase, cpu_class.as_deref()) else {
+                continue;
+            };
+            let Some(rank) = entry.rank(seconds) else {
+                continue;
+            };
+            let class = entry.cpu_class.as_deref().unwrap_or("all hardware");
+            let verdict = if rank.percentile() >= SLOW_PERCENTILE {
+                slow += 1;
+                "  <- slow"
+            } else {
+                ""
+            };
+            println!(
+                "  {} {phase}: {seconds:.1}s is {rank} for {class} (n={}){verdict}",
+                scenario.slug, entry.samples
+            );
+        }
+    }
+    if slow > 0 {
+        println!(
+            "\n{slow} phase(s) are at p{SLOW_PERCENTILE:.0} or slower for comparable machines. \
+             Antivirus scanning of the workspace, thermal throttling, or background load \
+             are common causes."
+        );
+    }
+    Ok(())
+}
+
+/// Downloads the dataset with `curl`.
+fn fetch(url: &str) -> Result<Dataset> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .context("failed to run curl")?;
+    if !output.status.success() {
+        bail!(
+            "downloading {url} failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("failed to parse the community dataset from {url}"))
+}
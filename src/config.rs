@@ -0,0 +1,85 @@
+//! Optional `bench.toml` configuration file.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::axes::{self, CustomAxis};
+use crate::cli::PhaseBudgets;
+use crate::constraint::{self, Constraint};
+use crate::hooks::Hooks;
+use crate::patch::Patches;
+use crate::redact::RedactConfig;
+use crate::wrapper::RustcWrapper;
+
+pub(crate) const DEFAULT_CONFIG: &str = "bench.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub gate: GateConfig,
+    /// Extra matrix axes, `[[axis]]` in the file.
+    #[serde(default, rename = "axis")]
+    pub axes: Vec<CustomAxis>,
+    /// Rules that drop scenarios from the matrix, e.g.
+    /// `"hotpatch=dx requires channel=nightly"`.
+    #[serde(default)]
+    pub constraints: Vec<Constraint>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// `[patch.<registry>]` overrides added to every generated `Cargo.toml`.
+    #[serde(default)]
+    pub patch: Patches,
+    /// `[wrapper]`: the `RUSTC_WRAPPER` of the `wrapper` cache value.
+    #[serde(default)]
+    pub wrapper: Option<RustcWrapper>,
+    /// `[cargo_config]`: a base `.cargo/config.toml` each scenario's
+    /// generated config is merged on top of.
+    #[serde(default)]
+    pub cargo_config: toml::Table,
+    /// `[pin]`: exact versions of crates in the payload's dependency graph,
+    /// set in every generated `Cargo.lock`.
+    #[serde(default)]
+    pub pin: BTreeMap<String, String>,
+    /// `[redact]`: extra secrets to mask in logs and results.
+    #[serde(default)]
+    pub redact: RedactConfig,
+}
+
+/// Budgets for `bench gate`. Top-level keys apply to every scenario; entries
+/// under `[gate.slugs."<slug>"]` override them for one scenario.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateConfig {
+    #[serde(flatten)]
+    pub budgets: PhaseBudgets,
+    /// Ordered so the config's hash in the run metadata is stable.
+    #[serde(default)]
+    pub slugs: BTreeMap<String, PhaseBudgets>,
+}
+
+impl Config {
+    /// Loads `path`, or `bench.toml` when no path is given and it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG).exists() => Path::new(DEFAULT_CONFIG),
+            None => return Ok(Self::default()),
+        };
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let mut config: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse config {}", path.display()))?;
+        axes::validate(&config.axes)
+            .with_context(|| format!("invalid custom axes in {}", path.display()))?;
+        constraint::validate(&config.constraints, &config.axes)
+            .with_context(|| format!("invalid constraints in {}", path.display()))?;
+        config
+            .patch
+            .resolve_paths(path.parent().unwrap_or(Path::new(".")));
+        Ok(config)
+    }
+}
@@ -0,0 +1,298 @@
+//! Constraints from the config file that prune the scenario matrix, so a
+//! growing number of axes does not need hard-coded pruning:
+//!
+//! ```text
+//! exclude cache=sscache, dynamic=dynamic-linking
+//! hotpatch=dx requires channel=nightly
+//! exclude (cache=sscache|cachepot, profile!=dev)
+//! ```
+//!
+//! Conditions compare an axis, as printed by `bench list`, with one value or
+//! `|`-separated alternatives; `,` joins conditions that must all hold, and
+//! a condition list may be wrapped in parentheses.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::axes::CustomAxis;
+
+/// One line of `constraints` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Constraint {
+    source: String,
+    rule: Rule,
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    /// Scenarios matching every condition are dropped.
+    Exclude(Vec<Condition>),
+    /// Scenarios matching `when` are dropped unless they also match `then`.
+    Requires {
+        when: Vec<Condition>,
+        then: Vec<Condition>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    axis: String,
+    values: Vec<String>,
+    negated: bool,
+}
+
+impl TryFrom<String> for Constraint {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        let text = source.trim();
+        let rule = if let Some(rest) = text.strip_prefix("exclude ") {
+            Rule::Exclude(parse_conditions(rest)?)
+        } else if let Some((when, then)) = text.split_once(" requires ") {
+            Rule::Requires {
+                when: parse_conditions(when)?,
+                then: parse_conditions(then)?,
+            }
+        } else {
+            bail!(
+                "constraint `{source}` must be `exclude <conditions>` or `<conditions> requires <conditions>`"
+            );
+        };
+        Ok(Self { source, rule })
+    }
+}
+
+fn parse_conditions(text: &str) -> Result<Vec<Condition>> {
+    let text = text.trim();
+    let text = match text.strip_prefix('(') {
+        Some(inner) => inner
+            .strip_suffix(')')
+            .with_context(|| format!("unbalanced parentheses in `{text}`"))?,
+        None => text,
+    };
+    text.split(',').map(parse_condition).collect()
+}
+
+fn parse_condition(text: &str) -> Result<Condition> {
+    let text = text.trim();
+    let (axis, values, negated) = match text.split_once("!=") {
+        Some((axis, values)) => (axis, values, true),
+        None => match text.split_once('=') {
+            Some((axis, values)) => (axis, values, false),
+            None => bail!("condition `{text}` must be `axis=value` or `axis!=value`"),
+        },
+    };
+    let axis = axis.trim();
+    let values: Vec<String> = values
+        .split('|')
+        .map(|value| value.trim().to_string())
+        .collect();
+    if axis.is_empty() || values.iter().any(String::is_empty) {
+        bail!("condition `{text}` is missing an axis or a value");
+    }
+    Ok(Condition {
+        axis: axis.to_string(),
+        values,
+        negated,
+    })
+}
+
+impl Condition {
+    /// An axis the scenario does not have, such as `toolchain` without
+    /// `--toolchains`, has no value, so only `!=` conditions hold for it.
+    fn holds(&self, axes: &[(String, String)]) -> bool {
+        let value = axes
+            .iter()
+            .find(|(axis, _)| *axis == self.axis)
+            .map(|(_, value)| value.as_str());
+        let listed = value.is_some_and(|value| self.values.iter().any(|listed| listed == value));
+        listed != self.negated
+    }
+}
+
+impl Constraint {
+    /// Whether a scenario with these axis name/value pairs stays in the matrix.
+    pub fn allows(&self, axes: &[(String, String)]) -> bool {
+        let all = |conditions: &[Condition]| conditions.iter().all(|c| c.holds(axes));
+        match &self.rule {
+            Rule::Exclude(conditions) => !all(conditions),
+            Rule::Requires { when, then } => !all(when) || all(then),
+        }
+    }
+
+    fn conditions(&self) -> impl Iterator<Item = &Condition> {
+        let (first, second): (&[Condition], &[Condition]) = match &self.rule {
+            Rule::Exclude(conditions) => (conditions, &[]),
+            Rule::Requires { when, then } => (when, then),
+        };
+        first.iter().chain(second)
+    }
+}
+
+/// Rejects constraints on axes or values that do not exist, which would
+/// otherwise silently never match. Toolchains and workspace roots are free
+/// form, so any value is accepted for them.
+pub fn validate(constraints: &[Constraint], custom: &[CustomAxis]) -> Result<()> {
+    let mut known: BTreeMap<&str, Option<Vec<&str>>> = crate::Scenario::axis_values()
+        .into_iter()
+        .map(|(axis, values)| (axis, Some(values)))
+        .collect();
+    known.extend([("toolchain", None), ("workspace-root", None)]);
+    known.extend(custom.iter().map(|axis| {
+        let values = axis
+            .values
+            .iter()
+            .map(|value| value.name.as_str())
+            .collect();
+        (axis.name.as_str(), Some(values))
+    }));
+    for constraint in constraints {
+        for condition in constraint.conditions() {
+            let Some(values) = known.get(condition.axis.as_str()) else {
+                bail!(
+                    "constraint `{}` uses unknown axis `{}`",
+                    constraint.source,
+                    condition.axis
+                );
+            };
+            let Some(values) = values else {
+                continue;
+            };
+            if let Some(unknown) = condition
+                .values
+                .iter()
+                .find(|value| !values.contains(&value.as_str()))
+            {
+                bail!(
+                    "constraint `{}` uses unknown value `{unknown}` for axis `{}` (expected one of {})",
+                    constraint.source,
+                    condition.axis,
+                    values.join(", ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::axes::AxisValue;
+
+    fn constraint(text: &str) -> Constraint {
+        Constraint::try_from(text.to_string()).unwrap()
+    }
+
+    fn axes(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(axis, value)| (axis.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_both_rule_forms() {
+        assert!(matches!(
+            constraint("exclude cache=sscache, dynamic=dynamic-linking").rule,
+            Rule::Exclude(conditions) if conditions.len() == 2
+        ));
+        assert!(matches!(
+            constraint("hotpatch=dx requires channel=nightly").rule,
+            Rule::Requires { when, then } if when.len() == 1 && then.len() == 1
+        ));
+        assert!(matches!(
+            constraint("exclude (cache=sscache|cachepot, profile!=dev)").rule,
+            Rule::Exclude(conditions) if conditions.len() == 2
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_constraints() {
+        for text in [
+            "cache=sscache",
+            "exclude cache",
+            "exclude =sscache",
+            "exclude cache=",
+            "exclude cache=sscache|",
+            "exclude (cache=sscache",
+        ] {
+            assert!(
+                Constraint::try_from(text.to_string()).is_err(),
+                "{text} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn exclude_drops_scenarios_matching_every_condition() {
+        let rule = constraint("exclude cache=sscache, dynamic=dynamic-linking");
+
+        assert!(!rule.allows(&axes(&[
+            ("cache", "sscache"),
+            ("dynamic", "dynamic-linking")
+        ])));
+        assert!(rule.allows(&axes(&[("cache", "sscache"), ("dynamic", "default")])));
+        assert!(rule.allows(&axes(&[
+            ("cache", "incremental"),
+            ("dynamic", "dynamic-linking")
+        ])));
+    }
+
+    #[test]
+    fn alternatives_match_any_listed_value() {
+        let rule = constraint("exclude cache=sscache|cachepot");
+
+        assert!(!rule.allows(&axes(&[("cache", "sscache")])));
+        assert!(!rule.allows(&axes(&[("cache", "cachepot")])));
+        assert!(rule.allows(&axes(&[("cache", "wrapper")])));
+    }
+
+    #[test]
+    fn negated_conditions_match_other_and_missing_values() {
+        let rule = constraint("exclude profile!=dev|release");
+
+        assert!(rule.allows(&axes(&[("profile", "dev")])));
+        assert!(rule.allows(&axes(&[("profile", "release")])));
+        assert!(!rule.allows(&axes(&[("profile", "profiling")])));
+        // Without the axis only `!=` holds.
+        assert!(!rule.allows(&[]));
+        assert!(constraint("exclude profile=dev").allows(&[]));
+    }
+
+    #[test]
+    fn requires_only_applies_when_its_conditions_hold() {
+        let rule = constraint("hotpatch=dx requires channel=nightly");
+
+        assert!(rule.allows(&axes(&[("hotpatch", "dx"), ("channel", "nightly")])));
+        assert!(!rule.allows(&axes(&[("hotpatch", "dx"), ("channel", "stable")])));
+        assert!(rule.allows(&axes(&[("hotpatch", "none"), ("channel", "stable")])));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_axes_and_values() {
+        let custom = [CustomAxis {
+            name: "alloc".to_string(),
+            values: vec![AxisValue {
+                name: "mimalloc".to_string(),
+                ..AxisValue::default()
+            }],
+        }];
+        let check = |text: &str| validate(&[constraint(text)], &custom);
+
+        assert!(check("exclude linker=rust-lld, cache=sscache|cachepot").is_ok());
+        assert!(check("exclude profile!=dev, target=host").is_ok());
+        assert!(check("exclude alloc=mimalloc").is_ok());
+        assert!(check("exclude toolchain=nightly-2025-06-01").is_ok());
+
+        let err = check("exclude linker=mlod").unwrap_err().to_string();
+        assert!(err.contains("unknown value `mlod`"), "{err}");
+        assert!(check("exclude cache=sscache|cachpot").is_err());
+        assert!(check("exclude alloc=jemalloc").is_err());
+        assert!(check("hotpatch=dx requires channel=nighty").is_err());
+        let err = check("exclude linkr=rust-lld").unwrap_err().to_string();
+        assert!(err.contains("unknown axis `linkr`"), "{err}");
+    }
+}
@@ -0,0 +1,136 @@
+//! Optional per-crate breakdown of the clean build: the clean build runs with
+//! `cargo build --timings`, and the unit durations from the report's embedded
+//! JSON are summed per `bevy_*` crate.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+use crate::runner::{self, CommandRunner};
+use crate::{PreparedScenario, ScenarioRecord, Workspace, cargo_command};
+
+/// Marker before the JSON array in cargo's `cargo-timing.html`.
+const UNIT_DATA: &str = "const UNIT_DATA = ";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateTimings {
+    /// Sum of every unit's compile time. Units build in parallel, so this is
+    /// more than the wall time of the build.
+    pub total_seconds: f64,
+    /// Compile time of each Bevy crate, slowest first.
+    pub crates: Vec<CrateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateTime {
+    pub name: String,
+    /// Build script and library units combined.
+    pub seconds: f64,
+}
+
+/// One compilation unit of cargo's timing report.
+#[derive(Debug, Deserialize)]
+struct Unit {
+    name: String,
+    duration: f64,
+}
+
+/// Runs the clean build with `--timings` and reads the breakdown from the
+/// report, which is also kept in the scenario's output directory. A report
+/// that cannot be read only prints a warning, so the timing is kept.
+pub fn run_clean_build(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<(Duration, Option<CrateTimings>)> {
+    println!(
+        "[bench] Running clean cargo build with --timings in {}",
+        workspace.exec_dir
+    );
+    let mut command = cargo_command(workspace, prepared, "build");
+    command.arg("--timings");
+    let elapsed = runner::run_build_logged(
+        runner,
+        &mut command,
+        "clean",
+        workspace.log_file("clean").as_deref(),
+    )?;
+    let timings = read(workspace)
+        .inspect_err(|err| eprintln!("[bench][warn] Could not read cargo's timing report: {err:#}"))
+        .ok();
+    Ok((elapsed, timings))
+}
+
+fn read(workspace: &Workspace) -> Result<CrateTimings> {
+    let host = &workspace.host;
+    let report = host.join(
+        &host.join(&workspace.target_dir, "cargo-timings"),
+        "cargo-timing.html",
+    );
+    let html = host
+        .read(&report)
+        .with_context(|| format!("failed to read {report}"))?;
+    if let Some(output) = &workspace.output {
+        let saved = output.join("cargo-timings.html");
+        fs::write(&saved, &html).with_context(|| format!("failed to write {}", saved.display()))?;
+    }
+    parse(&String::from_utf8_lossy(&html))
+}
+
+fn parse(html: &str) -> Result<CrateTimings> {
+    let start = html
+        .find(UNIT_DATA)
+        .context("no unit data in cargo's timing report")?;
+    // The array is followed by `;` and more script, so read one value only.
+    let units: Vec<Unit> = serde_json::Deserializer::from_str(&html[start + UNIT_DATA.len()..])
+        .into_iter()
+        .next()
+        .context("no unit data in cargo's timing report")?
+        .context("failed to parse the unit data in cargo's timing report")?;
+
+    let mut bevy: BTreeMap<String, f64> = BTreeMap::new();
+    for unit in &units {
+        if unit.name == "bevy" || unit.name.starts_with("bevy_") {
+            *bevy.entry(unit.name.clone()).or_default() += unit.duration;
+        }
+    }
+    let mut crates: Vec<CrateTime> = bevy
+        .into_iter()
+        .map(|(name, seconds)| CrateTime { name, seconds })
+        .collect();
+    crates.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+    Ok(CrateTimings {
+        total_seconds: units.iter().map(|unit| unit.duration).sum(),
+        crates,
+    })
+}
+
+/// Prints the `top` slowest Bevy crates of each scenario's clean build.
+pub fn report(records: &[ScenarioRecord], top: usize) {
+    let measured: Vec<(&str, &CrateTimings)> = records
+        .iter()
+        .filter_map(|record| Some((record.slug.as_str(), record.crate_timings.as_ref()?)))
+        .collect();
+    if measured.is_empty() {
+        return;
+    }
+
+    println!("\nBevy crates in the clean build (cargo --timings):");
+    for (slug, timings) in measured {
+        let bevy: f64 = timings.crates.iter().map(|time| time.seconds).sum();
+        println!(
+            "  {slug}: Bevy crates {bevy:.1}s of {:.1}s total unit time",
+            timings.total_seconds
+        );
+        for time in timings.crates.iter().take(top) {
+            println!(
+                "    {:>7.1}s {:>5.1}%  {}",
+                time.seconds,
+                time.seconds / timings.total_seconds.max(f64::EPSILON) * 100.0,
+                time.name
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! `bench dashboard`: a small Bevy app that charts the results history.
+//! Each scenario gets a bar for its latest time in the selected phase and a
+//! trend of that phase across every run that measured it.
+
+use anyhow::{Result, bail};
+use bevy::prelude::*;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::cli::DashboardArgs;
+use crate::history;
+
+/// Phases the dashboard can switch between.
+const PHASES: [&str; 4] = ["clean", "second", "modified", "hotpatch"];
+/// Runs shown in each scenario's trend, most recent last.
+const TREND_RUNS: usize = 24;
+const BAR_WIDTH: f32 = 360.0;
+const TREND_HEIGHT: f32 = 16.0;
+
+const BACKGROUND: Color = Color::srgb(0.08, 0.09, 0.11);
+const BAR: Color = Color::srgb(0.35, 0.62, 0.95);
+const TREND: Color = Color::srgb(0.55, 0.8, 0.45);
+const BUTTON: Color = Color::srgb(0.18, 0.2, 0.24);
+const BUTTON_SELECTED: Color = Color::srgb(0.3, 0.42, 0.62);
+const TEXT: Color = Color::srgb(0.9, 0.9, 0.92);
+const MUTED: Color = Color::srgb(0.55, 0.57, 0.6);
+
+/// One scenario's timings across the history, oldest first.
+struct Series {
+    slug: String,
+    runs: Vec<(DateTime<Utc>, [Option<f64>; PHASES.len()])>,
+}
+
+impl Series {
+    fn values(&self, phase: usize) -> impl Iterator<Item = f64> + '_ {
+        self.runs
+            .iter()
+            .filter_map(move |(_, phases)| phases[phase])
+    }
+
+    fn latest(&self, phase: usize) -> Option<f64> {
+        self.values(phase).last()
+    }
+}
+
+#[derive(Resource)]
+struct RunHistory {
+    runs: usize,
+    series: Vec<Series>,
+}
+
+#[derive(Resource)]
+struct SelectedPhase(usize);
+
+#[derive(Component)]
+struct PhaseButton(usize);
+
+/// Parent of the per-scenario rows, rebuilt when the phase changes.
+#[derive(Component)]
+struct Chart;
+
+/// Opens the dashboard window for the run logs in `args.results`.
+pub fn run(args: &DashboardArgs) -> Result<()> {
+    let history = load(&args.results)?;
+    if history.series.is_empty() {
+        bail!(
+            "no successful scenarios in {}; run the benchmark first",
+            args.results.display()
+        );
+    }
+    println!(
+        "Opening the dashboard for {} run(s) and {} scenario(s)...",
+        history.runs,
+        history.series.len()
+    );
+
+    let exit = App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Bevy build benchmarks".to_string(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(ClearColor(BACKGROUND))
+        .insert_resource(history)
+        // The incremental rebuild is what most comparisons are about.
+        .insert_resource(SelectedPhase(1))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (select_phase, redraw_chart).chain())
+        .run();
+    match exit {
+        AppExit::Success => Ok(()),
+        AppExit::Error(code) => bail!("the dashboard exited with code {code}"),
+    }
+}
+
+fn load(dir: &Path) -> Result<RunHistory> {
+    let runs = history::read_runs(dir)?;
+    let mut series: BTreeMap<String, Series> = BTreeMap::new();
+    for (_, record) in &runs {
+        for scenario in record.scenarios.iter().filter(|s| s.error.is_none()) {
+            let phases = PHASES.map(|phase| scenario.timings.phase(phase));
+            series
+                .entry(scenario.slug.clone())
+                .or_insert_with(|| Series {
+                    slug: scenario.slug.clone(),
+                    runs: Vec::new(),
+                })
+                .runs
+                .push((record.started_at, phases));
+        }
+    }
+    let mut series: Vec<Series> = series.into_values().collect();
+    for scenario in &mut series {
+        scenario.runs.sort_by_key(|(started_at, _)| *started_at);
+    }
+    Ok(RunHistory {
+        runs: runs.len(),
+        series,
+    })
+}
+
+fn text(value: impl Into<String>, size: f32, color: Color) -> impl Bundle {
+    (
+        Text::new(value),
+        TextFont {
+            font_size: size,
+            ..default()
+        },
+        TextColor(color),
+    )
+}
+
+fn setup(mut commands: Commands, history: Res<RunHistory>) {
+    commands.spawn(Camera2d);
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(16.0)),
+            row_gap: Val::Px(12.0),
+            ..default()
+        })
+        .with_children(|root| {
+            root.spawn(text(
+                format!(
+                    "{} run(s), {} scenario(s). Bars show the latest run; the trend shows up to {TREND_RUNS} runs.",
+                    history.runs,
+                    history.series.len()
+                ),
+                16.0,
+                TEXT,
+            ));
+            root.spawn(Node {
+                column_gap: Val::Px(8.0),
+                ..default()
+            })
+            .with_children(|buttons| {
+                for (index, phase) in PHASES.iter().enumerate() {
+                    buttons
+                        .spawn((
+                            Button,
+                            PhaseButton(index),
+                            Node {
+                                padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON),
+                        ))
+                        .with_children(|button| {
+                            button.spawn(text(*phase, 14.0, TEXT));
+                        });
+                }
+            });
+            root.spawn((
+                Chart,
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn select_phase(
+    buttons: Query<(&Interaction, &PhaseButton), Changed<Interaction>>,
+    mut selected: ResMut<SelectedPhase>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed && selected.0 != button.0 {
+            selected.0 = button.0;
+        }
+    }
+}
+
+/// Rebuilds the rows whenever the selected phase changes, including the
+/// first frame.
+fn redraw_chart(
+    mut commands: Commands,
+    history: Res<RunHistory>,
+    selected: Res<SelectedPhase>,
+    chart: Single<Entity, With<Chart>>,
+    mut buttons: Query<(&PhaseButton, &mut BackgroundColor)>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+    let phase = selected.0;
+    for (button, mut color) in &mut buttons {
+        color.0 = if button.0 == phase {
+            BUTTON_SELECTED
+        } else {
+            BUTTON
+        };
+    }
+
+    let longest = history
+        .series
+        .iter()
+        .filter_map(|series| series.latest(phase))
+        .fold(0.0, f64::max);
+    commands
+        .entity(*chart)
+        .despawn_children()
+        .with_children(|chart| {
+            for series in &history.series {
+                let Some(latest) = series.latest(phase) else {
+                    continue;
+                };
+                chart
+                    .spawn(Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(12.0),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Node {
+                                width: Val::Px(440.0),
+                                ..default()
+                            },
+                            children![text(series.slug.clone(), 12.0, TEXT)],
+                        ));
+                        row.spawn((
+                            Node {
+                                width: Val::Px((latest / longest) as f32 * BAR_WIDTH),
+                                height: Val::Px(14.0),
+                                ..default()
+                            },
+                            BackgroundColor(BAR),
+                        ));
+                        row.spawn(text(format!("{latest:.1}s"), 12.0, MUTED));
+                        row.spawn(trend(series, phase));
+                    });
+            }
+        });
+}
+
+/// A column per run, scaled to the scenario's slowest run in the phase.
+fn trend(series: &Series, phase: usize) -> impl Bundle {
+    let values: Vec<f64> = series.values(phase).collect();
+    let values = &values[values.len().saturating_sub(TREND_RUNS)..];
+    let slowest = values.iter().copied().fold(0.0, f64::max);
+    let columns: Vec<Node> = values
+        .iter()
+        .map(|value| Node {
+            width: Val::Px(5.0),
+            height: Val::Px((value / slowest) as f32 * TREND_HEIGHT),
+            ..default()
+        })
+        .collect();
+    (
+        Node {
+            height: Val::Px(TREND_HEIGHT),
+            align_items: AlignItems::End,
+            column_gap: Val::Px(2.0),
+            ..default()
+        },
+        Children::spawn(SpawnIter(
+            columns
+                .into_iter()
+                .map(|column| (column, BackgroundColor(TREND))),
+        )),
+    )
+}
@@ -0,0 +1,75 @@
+//! Extra output for `bench run <slug> --verbose`: what the scenario's tools
+//! actually see, for debugging why one configuration fails or is slow.
+
+use crate::redact;
+use crate::remote::Host;
+use crate::{PreparedScenario, Workspace};
+
+/// Prefixes of inherited variables that change how cargo and rustc behave.
+const RELEVANT_ENV: [&str; 5] = ["CARGO", "RUST", "SCCACHE", "ANDROID", "BEVY"];
+
+/// Prints the toolchain, environment, and generated files of `workspace`.
+pub(crate) fn print(workspace: &Workspace, prepared: &PreparedScenario) {
+    println!("[bench] Workspace: {}", workspace.exec_dir);
+    println!("[bench] Target directory: {}", workspace.target_dir);
+
+    // Run in the workspace so its rust-toolchain.toml picks the toolchain.
+    match workspace.command("rustc").arg("-vV").output() {
+        Ok(output) if output.status.success() => {
+            println!("[bench] Toolchain:");
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                println!("    {line}");
+            }
+        }
+        Ok(output) => eprintln!(
+            "[bench][warn] rustc -vV failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(err) => eprintln!("[bench][warn] Could not run rustc -vV: {err}"),
+    }
+
+    println!("[bench] Environment set by the harness:");
+    if workspace.env.is_empty() {
+        println!("    (none)");
+    }
+    for (key, value) in &workspace.env {
+        println!("    {key}={}", redact::redact(value));
+    }
+    // A remote build inherits the remote login environment instead.
+    if matches!(workspace.host, Host::Local) {
+        println!("[bench] Inherited environment:");
+        let mut inherited: Vec<(String, String)> = std::env::vars()
+            .filter(|(key, _)| {
+                key == "PATH" || RELEVANT_ENV.iter().any(|prefix| key.starts_with(prefix))
+            })
+            .collect();
+        inherited.sort();
+        for (key, value) in inherited {
+            println!("    {key}={}", redact::redact(&value));
+        }
+    }
+
+    print_files(prepared);
+}
+
+/// Prints every file generated for `prepared`.
+pub(crate) fn print_files(prepared: &PreparedScenario) {
+    let code = &prepared.code;
+    for (file, contents) in [
+        ("Cargo.toml", &code.cargo_toml),
+        (".cargo/config.toml", &code.cargo_config_toml),
+        ("rust-toolchain.toml", &code.rust_toolchain_toml),
+        (code.payload_file, &code.src_main_rs),
+    ]
+    .into_iter()
+    .chain(
+        code.entry_files
+            .iter()
+            .map(|(file, contents)| (*file, contents)),
+    ) {
+        println!("[bench] --- {file} ---");
+        println!("{}", contents.trim_end());
+    }
+    println!("[bench] --- end of generated files ---");
+}
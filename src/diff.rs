@@ -0,0 +1,85 @@
+//! `bench diff`: compare two results logs phase by phase.
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::cli::DiffArgs;
+use crate::stats::{self, Significance};
+use crate::{ScenarioRecord, slug_seed};
+use crate::{history, metadata};
+
+pub fn run(args: &DiffArgs) -> Result<()> {
+    let baseline = history::read_run(&args.baseline)?;
+    let candidate = history::read_run(&args.candidate)?;
+    metadata::check_comparable(&baseline, &candidate)?;
+    // Ratio of the machines' speeds; candidate medians are scaled by it so the
+    // normalized change reflects the configuration rather than the hardware.
+    let speed_ratio = match (&baseline.calibration, &candidate.calibration) {
+        (Some(old), Some(new)) if old.toolchain == new.toolchain => {
+            println!(
+                "[diff] calibration: {:.3}s -> {:.3}s; normalized change in brackets",
+                old.seconds, new.seconds
+            );
+            Some(old.seconds / new.seconds)
+        }
+        _ => None,
+    };
+    let baseline: HashMap<&str, &ScenarioRecord> = baseline
+        .scenarios
+        .iter()
+        .filter(|scenario| scenario.error.is_none())
+        .map(|scenario| (scenario.slug.as_str(), scenario))
+        .collect();
+
+    let mut compared = 0usize;
+    for scenario in candidate
+        .scenarios
+        .iter()
+        .filter(|scenario| scenario.error.is_none())
+    {
+        let Some(before) = baseline.get(scenario.slug.as_str()) else {
+            println!("[diff] {}: not present in baseline", scenario.slug);
+            continue;
+        };
+
+        println!("[diff] {}", scenario.slug);
+        if before.nice != scenario.nice {
+            let describe = |nice: Option<i32>| {
+                nice.map(|nice| format!("nice {nice}"))
+                    .unwrap_or_else(|| "normal priority".to_string())
+            };
+            println!(
+                "  [warn] built at {} vs {}",
+                describe(before.nice),
+                describe(scenario.nice)
+            );
+        }
+        for (phase, _) in scenario.timings.phases() {
+            let old = before.phase_samples(phase);
+            let new = scenario.phase_samples(phase);
+            if old.is_empty() || new.is_empty() {
+                continue;
+            }
+            compared += 1;
+            let (old_median, new_median) = (stats::median(&old), stats::median(&new));
+            let change = (new_median - old_median) / old_median * 100.0;
+            let verdict = match stats::compare(&old, &new, slug_seed(&scenario.slug)) {
+                Significance::Significant => "significant",
+                Significance::NotSignificant => "not significant",
+                Significance::Unknown => "n/a (needs --iterations > 1 on both runs)",
+            };
+            let normalized = speed_ratio
+                .map(|ratio| {
+                    let change = (new_median * ratio - old_median) / old_median * 100.0;
+                    format!(" [{change:+.1}%]")
+                })
+                .unwrap_or_default();
+            println!(
+                "  {phase:<9} {old_median:>9.3}s -> {new_median:>9.3}s  {change:>+7.1}%{normalized}  {verdict}"
+            );
+        }
+    }
+
+    println!("[diff] {compared} phase(s) compared.");
+    Ok(())
+}
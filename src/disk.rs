@@ -0,0 +1,44 @@
+//! Disk footprint of a scenario: everything its builds left in the workspace
+//! (target directory, sccache cache) and the size of the payload artifact.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{PreparedScenario, Target, Workspace};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// Total size of the workspace after the builds, dominated by `target/`.
+    pub workspace_bytes: u64,
+    /// Size of the payload binary (or shared library / wasm module).
+    pub binary_bytes: u64,
+}
+
+pub fn measure(workspace: &Workspace, prepared: &PreparedScenario) -> Result<DiskUsage> {
+    let artifact = workspace
+        .host
+        .join(&workspace.artifact_dir(prepared), &artifact_name(prepared));
+    let binary_bytes = workspace
+        .host
+        .file_size(&artifact)
+        .context("failed to measure payload artifact")?;
+    let workspace_bytes = workspace
+        .host
+        .dir_size(&workspace.exec_dir)
+        .context("failed to measure workspace size")?;
+    Ok(DiskUsage {
+        workspace_bytes,
+        binary_bytes,
+    })
+}
+
+fn artifact_name(prepared: &PreparedScenario) -> String {
+    let name = format!("bench-payload-{}", prepared.slug);
+    match prepared.scenario.target {
+        None => format!("{name}{}", std::env::consts::EXE_SUFFIX),
+        Some(Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu | Target::X86_64LinuxMusl) => name,
+        Some(Target::X86_64WindowsMsvc) => format!("{name}.exe"),
+        Some(Target::Aarch64Android) => format!("lib{}.so", name.replace('-', "_")),
+        Some(Target::Wasm32) => format!("{name}.wasm"),
+    }
+}
@@ -0,0 +1,111 @@
+//! `--dry-run`: the scenarios a run would benchmark, in the order it would
+//! start them, checked against what this machine has installed. Nothing is
+//! created or started, so config changes can be sanity-checked before
+//! committing hours of machine time.
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+use crate::cli::RunArgs;
+use crate::config::Config;
+use crate::history::{self, History};
+use crate::{Cache, PreparedScenario, Target, detect_android_ndk};
+
+/// Prints the plan for `prepared` and fails if any scenario is missing a
+/// prerequisite, as the run would fail that scenario.
+pub(crate) fn report(
+    prepared: &[PreparedScenario],
+    history: &History,
+    args: &RunArgs,
+    config: &Config,
+) -> Result<()> {
+    println!(
+        "Dry run: {} scenario(s), {} iteration(s) each.",
+        prepared.len(),
+        args.iterations
+    );
+    if args.remote.is_some() {
+        println!("Prerequisites are checked on this machine, not on the remote host.");
+    }
+    if args.defender_pair {
+        println!("Each scenario would run once in each --defender-pair root.");
+    }
+    let rows: Vec<[String; 4]> = prepared
+        .iter()
+        .scan(Duration::ZERO, |elapsed, scenario| {
+            let estimate = history
+                .estimate(&scenario.slug)
+                .map(|estimate| estimate * args.iterations);
+            let starts = *elapsed;
+            *elapsed += estimate.unwrap_or_default();
+            let mut problems = prerequisites(scenario, config);
+            if let Some(budget) = args.budget
+                && starts >= budget
+            {
+                problems.push("past --budget".to_string());
+            }
+            Some([
+                scenario.slug.clone(),
+                estimate.map_or_else(|| "?".to_string(), history::format_eta),
+                history::format_eta(starts),
+                if problems.is_empty() {
+                    "ok".to_string()
+                } else {
+                    problems.join(", ")
+                },
+            ])
+        })
+        .collect();
+    print_table(&rows);
+    history.report_forecast(prepared, args.iterations);
+
+    let blocked = prepared
+        .iter()
+        .filter(|scenario| !prerequisites(scenario, config).is_empty())
+        .count();
+    if blocked > 0 {
+        bail!(
+            "{blocked} of {} scenario(s) are missing prerequisites",
+            prepared.len()
+        );
+    }
+    println!("Every scenario can run here.");
+    Ok(())
+}
+
+/// What `scenario` needs that this machine or the config file lacks.
+fn prerequisites(scenario: &PreparedScenario, config: &Config) -> Vec<String> {
+    let scenario_axes = &scenario.scenario;
+    let mut problems: Vec<String> = scenario_axes
+        .missing_tools()
+        .into_iter()
+        .map(|tool| format!("missing {tool}"))
+        .collect();
+    if scenario_axes.cache == Some(Cache::Wrapper) && config.wrapper.is_none() {
+        problems.push("no [wrapper] in the config file".to_string());
+    }
+    if scenario_axes.target == Some(Target::Aarch64Android) && detect_android_ndk().is_err() {
+        problems.push("no Android NDK".to_string());
+    }
+    problems
+}
+
+/// Times are for all iterations; "starts after" sums the estimates before
+/// each scenario, so scenarios without history count as zero.
+fn print_table(rows: &[[String; 4]]) {
+    let header = ["slug", "estimate", "starts after", "prerequisites"].map(String::from);
+    let mut widths = header.each_ref().map(String::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
@@ -0,0 +1,164 @@
+//! Typed failures for code embedding the harness as a library.
+//!
+//! Functions return `anyhow::Result` so context can be layered on freely, but
+//! the failures below are raised as `BenchError` values; callers can react to
+//! one with `err.downcast_ref::<BenchError>()` or by walking `err.chain()`.
+
+use std::fmt;
+use std::io;
+use std::process::ExitStatus;
+
+use crate::first_frame;
+use crate::tail::OutputTail;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BenchError {
+    /// A required program (cargo, dx, sccache, the Android NDK, ...) is not
+    /// installed or not on `PATH`.
+    #[error("`{tool}` is not installed or not on PATH")]
+    ToolMissing { tool: String },
+    #[error("cargo build ({phase}) failed with status {status}")]
+    BuildFailed {
+        phase: String,
+        status: ExitStatus,
+        #[source]
+        output: CapturedOutput,
+    },
+    #[error("timeout waiting for ready marker {marker}")]
+    HotpatchTimeout {
+        marker: String,
+        #[source]
+        output: CapturedOutput,
+    },
+    /// The patched payload line did not show up within `--patch-timeout`.
+    #[error("timeout waiting for the patched payload line {expected}")]
+    PatchTimeout {
+        expected: String,
+        #[source]
+        output: CapturedOutput,
+    },
+    /// `dx serve` stopped before the patched payload value was observed.
+    #[error("dx serve exited before the hotpatch was observed ({reason})")]
+    HotpatchExited {
+        reason: String,
+        #[source]
+        output: CapturedOutput,
+    },
+    /// The payload stopped or stalled before its first frame
+    /// (`--first-frame`).
+    #[error("the payload did not reach its first frame ({reason})")]
+    PayloadLaunch {
+        reason: String,
+        #[source]
+        output: CapturedOutput,
+    },
+    /// A tool printed nothing for `--stall-timeout` and was stopped.
+    #[error("{tool} produced no output for {silent_seconds}s and was stopped")]
+    Stalled {
+        tool: String,
+        silent_seconds: u64,
+        #[source]
+        output: CapturedOutput,
+    },
+    /// A tool was stopped for exceeding `--memory-limit`.
+    #[error("a build exceeded the {limit_mib} MiB memory limit")]
+    OutOfMemory { limit_mib: u64 },
+    /// SIGINT or SIGTERM stopped the run; no further tools are started.
+    #[error("interrupted by a signal")]
+    Interrupted,
+    #[error("failed to {action}")]
+    WorkspaceIo {
+        action: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Why [`ScenarioBuilder::build`](crate::ScenarioBuilder::build) rejected a
+/// combination of axis values.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ScenarioError {
+    #[error(
+        "hotpatching needs to run the payload, which is not possible when cross-compiling to {target}"
+    )]
+    HotpatchCrossTarget { target: &'static str },
+    #[error("{axis}={value} needs a nightly toolchain")]
+    NightlyOnly {
+        axis: &'static str,
+        value: &'static str,
+    },
+    #[error("dynamic=dynamic-linking cannot be used with the statically linked {target}")]
+    StaticTarget { target: &'static str },
+    #[error("build-std=std needs an explicit target; pass the host triple with --target")]
+    BuildStdNeedsTarget,
+    #[error("trace=tracy builds the native Tracy client, which does not compile for {target}")]
+    TracyUnsupported { target: &'static str },
+    #[error("{axis}={value} only applies to Linux builds")]
+    LinuxOnly {
+        axis: &'static str,
+        value: &'static str,
+    },
+    #[error("a warm cache needs cache=sccache or cache=cachepot")]
+    WarmCacheWithoutServer,
+    #[error("profile={profile} cannot be combined with {other}")]
+    ProfileConflict {
+        profile: &'static str,
+        other: &'static str,
+    },
+    #[error("linker=zig builds through cargo-zigbuild, which cannot be combined with {other}")]
+    ZigConflict { other: &'static str },
+    #[error("linker={value} is only available when building for a macOS host")]
+    MacosOnly { value: &'static str },
+    #[error("`{tool}` is required for {needed_for} but was not found on PATH")]
+    ToolMissing {
+        tool: &'static str,
+        needed_for: &'static str,
+    },
+}
+
+impl BenchError {
+    /// Whether the harness itself stopped the tool for taking too long.
+    pub(crate) fn is_harness_timeout(&self) -> bool {
+        match self {
+            Self::HotpatchTimeout { .. } | Self::PatchTimeout { .. } | Self::Stalled { .. } => true,
+            Self::PayloadLaunch { reason, .. } => reason.starts_with(first_frame::TIMED_OUT),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn workspace_io(action: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        let action = action.into();
+        move |source| Self::WorkspaceIo { action, source }
+    }
+}
+
+/// The last output lines of a failed child process, rendered as part of the
+/// error so the failure shows what the tool printed right before it stopped.
+#[derive(Debug)]
+pub struct CapturedOutput {
+    command: String,
+    tail: OutputTail,
+}
+
+impl CapturedOutput {
+    pub(crate) fn new(command: impl Into<String>, tail: OutputTail) -> Self {
+        Self {
+            command: command.into(),
+            tail,
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+}
+
+impl fmt::Display for CapturedOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "output of {}:\n{}", self.command, self.tail)
+    }
+}
+
+impl std::error::Error for CapturedOutput {}
@@ -0,0 +1,350 @@
+//! `bench examples`: builds official Bevy examples in a Bevy checkout under
+//! each scenario, so results line up with the example build times quoted in
+//! Bevy's own compile-time discussions. Apart from touching the example's
+//! source, the checkout is left alone: each scenario's settings reach cargo
+//! through `--config`, `--features`, and the environment.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Instant, SystemTime};
+
+use crate::bisect::git;
+use crate::cli::{ExamplesArgs, RunArgs};
+use crate::config::Config;
+use crate::remote::Host;
+use crate::sccache::SccacheServer;
+use crate::wrapper::RustcWrapper;
+use crate::{Cache, Linker, Matrix, PreparedScenario, bevy_features, prepare_scenarios};
+
+/// The `[[example]]` entries of Bevy's `Cargo.toml`.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    example: Vec<ExampleTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExampleTarget {
+    name: String,
+    path: Option<PathBuf>,
+}
+
+/// Build times of one example or binary under one scenario.
+pub(crate) struct Timing {
+    pub(crate) slug: String,
+    pub(crate) target: String,
+    /// Build into an empty target directory, dependencies included.
+    pub(crate) clean: f64,
+    /// Rebuild after touching the target's source file.
+    pub(crate) touched: f64,
+}
+
+/// What reaches cargo of a scenario in a checkout the harness did not
+/// generate: a fresh target directory, the toolchain, and the compiler
+/// cache. Keeps the scenario's sccache server running until dropped.
+pub(crate) struct BuildEnv {
+    pub(crate) vars: Vec<(String, String)>,
+    _sccache: Option<SccacheServer>,
+}
+
+impl BuildEnv {
+    /// The environment of `prepared`, with its target directory in `dir`.
+    pub(crate) fn new(
+        prepared: &PreparedScenario,
+        wrapper: Option<&RustcWrapper>,
+        dir: &Path,
+    ) -> Result<Self> {
+        let mut vars = vec![
+            (
+                "CARGO_TARGET_DIR".to_string(),
+                dir.join("target").display().to_string(),
+            ),
+            (
+                "RUSTUP_TOOLCHAIN".to_string(),
+                prepared
+                    .toolchain
+                    .clone()
+                    .unwrap_or_else(|| prepared.scenario.channel.label().to_string()),
+            ),
+        ];
+        let sccache = match prepared.scenario.cache.and_then(Cache::server) {
+            Some(tool) => Some(SccacheServer::start(
+                &Host::Local,
+                &dir.display().to_string(),
+                tool,
+            )?),
+            None => None,
+        };
+        vars.extend(sccache.iter().flat_map(SccacheServer::env));
+        // The shim that verifies the wrapper only runs inside benchmark
+        // workspaces.
+        if prepared.scenario.cache == Some(Cache::Wrapper) {
+            let wrapper = wrapper
+                .context("the wrapper cache needs a [wrapper] command in the config file")?;
+            vars.extend(wrapper.env.clone());
+            vars.push(("RUSTC_WRAPPER".to_string(), wrapper.command.clone()));
+        }
+        Ok(Self {
+            vars,
+            _sccache: sccache,
+        })
+    }
+}
+
+pub fn run(args: &ExamplesArgs, config: &Config) -> Result<()> {
+    if args.run.remote.is_some() {
+        bail!("`bench examples` builds in a local Bevy checkout and cannot use --remote");
+    }
+    let checkout = match &args.bevy {
+        Some(dir) => dir.clone(),
+        None => clone(&args.repo, &args.rev)?,
+    };
+    let sources = example_sources(&checkout, &args.examples)?;
+    let locked = use_locked(&checkout);
+
+    let mut timings = Vec::new();
+    for scenario in &host_scenarios(&args.run, config)? {
+        for (example, source) in args.examples.iter().zip(&sources) {
+            println!(
+                "[bench] Building example {example} for {}...",
+                scenario.slug
+            );
+            match time_example(
+                &checkout,
+                scenario,
+                config.wrapper.as_ref(),
+                example,
+                source,
+                locked,
+            ) {
+                Ok(timing) => timings.push(timing),
+                Err(error) => eprintln!(
+                    "[bench][warn] Example {example} failed for {}: {error:#}",
+                    scenario.slug
+                ),
+            }
+        }
+    }
+    print_table("example", &timings);
+    Ok(())
+}
+
+/// The scenarios of `run` that can build in a checkout the harness did not
+/// generate, leaving out those whose tools are missing.
+pub(crate) fn host_scenarios(run: &RunArgs, config: &Config) -> Result<Vec<PreparedScenario>> {
+    let mut prepared = prepare_scenarios(&Matrix::from_args(run, config))?;
+    let total = prepared.len();
+    // Only host builds, and `dx` sessions are out of scope. A single
+    // windowing backend means listing the other default features, which are
+    // only known for the release the payload uses.
+    prepared.retain(|prepared| {
+        prepared.scenario.target.is_none()
+            && prepared.scenario.hotpatching.is_none()
+            && prepared.scenario.window_backend.is_none()
+    });
+    if prepared.len() < total {
+        println!(
+            "[bench] Skipping {} cross-compiled, hotpatch, or window-backend scenario(s).",
+            total - prepared.len()
+        );
+    }
+    prepared.retain(|prepared| {
+        let missing = prepared.scenario.missing_tools();
+        if !missing.is_empty() {
+            eprintln!(
+                "[bench][warn] Skipping {}: missing {}",
+                prepared.slug,
+                missing.join(", ")
+            );
+        }
+        missing.is_empty()
+    });
+    Ok(prepared)
+}
+
+/// Clones `repo` once into the shared directory and checks out `rev`.
+fn clone(repo: &str, rev: &str) -> Result<PathBuf> {
+    let dir = Host::Local.shared_dir("bevy-checkout")?;
+    if !Path::new(&dir).join(".git").is_dir() {
+        println!("[bench] Cloning {repo} into {dir}...");
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--filter=blob:none", repo, &dir])
+            .status()
+            .context("failed to run git clone")?;
+        if !status.success() {
+            bail!("git clone {repo} failed with status {status}");
+        }
+    }
+    println!("[bench] Checking out {rev}...");
+    git(&dir, &["fetch", "--quiet", repo, rev])?;
+    git(&dir, &["checkout", "--quiet", "--detach", "FETCH_HEAD"])?;
+    Ok(PathBuf::from(dir))
+}
+
+/// Source file of each example, from the `[[example]]` table of the
+/// checkout's manifest.
+fn example_sources(checkout: &Path, examples: &[String]) -> Result<Vec<PathBuf>> {
+    let path = checkout.join("Cargo.toml");
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let manifest: Manifest =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    examples
+        .iter()
+        .map(|name| {
+            let target = manifest
+                .example
+                .iter()
+                .find(|target| &target.name == name)
+                .with_context(|| format!("no example named {name} in {}", path.display()))?;
+            Ok(checkout.join(
+                target
+                    .path
+                    .clone()
+                    .unwrap_or_else(|| Path::new("examples").join(format!("{name}.rs"))),
+            ))
+        })
+        .collect()
+}
+
+fn time_example(
+    checkout: &Path,
+    prepared: &PreparedScenario,
+    wrapper: Option<&RustcWrapper>,
+    example: &str,
+    source: &Path,
+    locked: bool,
+) -> Result<Timing> {
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("bench-{}-{example}-", prepared.slug))
+        .tempdir()
+        .context("failed to create the example's target directory")?;
+    let config = dir.path().join("config.toml");
+    fs::write(&config, &prepared.code.cargo_config_toml)
+        .context("failed to write the scenario's cargo config")?;
+    let env = BuildEnv::new(prepared, wrapper, dir.path())?;
+
+    let build = || -> Result<f64> {
+        let mut command = Command::new("cargo");
+        command
+            .current_dir(checkout)
+            .envs(env.vars.iter().map(|(key, value)| (key, value)))
+            .arg(Linker::build_subcommand(prepared.scenario.linker))
+            .args(["--quiet", "--example", example]);
+        if locked {
+            command.arg("--locked");
+        }
+        config_args(&mut command, prepared, &config);
+        let features = bevy_features(&prepared.scenario, &prepared.custom);
+        if !features.is_empty() {
+            command.arg("--features").arg(features.join(","));
+        }
+        let start = Instant::now();
+        let status = command.status().context("failed to run cargo")?;
+        if !status.success() {
+            bail!("cargo build --example {example} failed with status {status}");
+        }
+        Ok(start.elapsed().as_secs_f64())
+    };
+
+    let clean = build()?;
+    touch(source)?;
+    let touched = build()?;
+    Ok(Timing {
+        slug: prepared.slug.clone(),
+        target: example.to_string(),
+        clean,
+        touched,
+    })
+}
+
+/// Whether builds in the workspace at `root` can pass `--locked`, like every
+/// build of the payload. Without a `Cargo.lock` there is nothing to keep, and
+/// `--locked` would refuse to create one, so the builds run unlocked.
+pub(crate) fn use_locked(root: &Path) -> bool {
+    let found = root.join("Cargo.lock").is_file();
+    if !found {
+        eprintln!(
+            "[bench][warn] {} has no Cargo.lock; building without --locked, so cargo resolves dependencies afresh.",
+            root.display()
+        );
+    }
+    found
+}
+
+/// Passes the scenario's cargo config, written to `config`, and its profile
+/// settings to `command` with `--config`, so the checkout's own files stay
+/// untouched.
+pub(crate) fn config_args(command: &mut Command, prepared: &PreparedScenario, config: &Path) {
+    command.arg("--config").arg(config);
+    // The checkout's manifest has no profile of ours, so it is defined here
+    // too.
+    let profile = prepared.scenario.profile_name();
+    if let Some(extra) = prepared.scenario.profile {
+        command.arg("--profile").arg(profile);
+        for (key, value) in extra.keys() {
+            command
+                .arg("--config")
+                .arg(format!("profile.{profile}.{key}={value}"));
+        }
+    }
+    if let Some(assertions) = prepared.scenario.debug_assertions {
+        let (payload, dependencies) = assertions.settings();
+        command
+            .arg("--config")
+            .arg(format!("profile.{profile}.debug-assertions={payload}"))
+            .arg("--config")
+            .arg(format!(
+                "profile.{profile}.package.\"*\".debug-assertions={dependencies}"
+            ));
+    }
+    for choice in &prepared.custom {
+        for (key, value) in &choice.value.profile {
+            command
+                .arg("--config")
+                .arg(format!("profile.{profile}.{key}={value}"));
+        }
+    }
+}
+
+/// Marks `source` as modified, so cargo rebuilds the crate it belongs to.
+pub(crate) fn touch(source: &Path) -> Result<()> {
+    File::options()
+        .write(true)
+        .open(source)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+        .with_context(|| format!("failed to touch {}", source.display()))
+}
+
+/// Prints `timings` with the built targets in a column titled `heading`.
+pub(crate) fn print_table(heading: &str, timings: &[Timing]) {
+    if timings.is_empty() {
+        println!("No {heading} was built.");
+        return;
+    }
+    let slug_width = timings
+        .iter()
+        .map(|timing| timing.slug.len())
+        .max()
+        .unwrap_or(0)
+        .max("scenario".len());
+    let target_width = timings
+        .iter()
+        .map(|timing| timing.target.len())
+        .max()
+        .unwrap_or(0)
+        .max(heading.len());
+    println!(
+        "\n{:<slug_width$}  {:<target_width$}  {:>8}  {:>8}",
+        "scenario", heading, "clean", "touched"
+    );
+    for timing in timings {
+        println!(
+            "{:<slug_width$}  {:<target_width$}  {:>7.1}s  {:>7.1}s",
+            timing.slug, timing.target, timing.clean, timing.touched
+        );
+    }
+}
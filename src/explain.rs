@@ -0,0 +1,186 @@
+//! `bench run --explain <slug>`: what a scenario would generate, set, and
+//! run, printed without creating a workspace or starting any tool, so a
+//! configuration can be reviewed or reproduced by hand.
+
+use anyhow::{Result, bail};
+
+use crate::cli::RunArgs;
+use crate::config::Config;
+use crate::phase::Phase;
+use crate::snapshot;
+use crate::{
+    Cache, CleanMode, Matrix, PreparedScenario, Target, cargo_args, detect_android_ndk,
+    diagnostics, dx_serve_args, prepare_scenarios, redact,
+};
+
+/// Stands for the temporary workspace, which only exists during a run.
+const WORKSPACE: &str = "<workspace>";
+
+pub(crate) fn run(args: &RunArgs, config: &Config, slug: &str) -> Result<()> {
+    let prepared = prepare_scenarios(&Matrix::from_args(args, config))?;
+    let Some(prepared) = prepared.iter().find(|prepared| prepared.slug == slug) else {
+        bail!("no scenario named {slug} in the selected matrix; see `bench list`");
+    };
+    println!("[bench] Scenario {slug}: {}", prepared.describe());
+    print_env(prepared, args, config);
+    diagnostics::print_files(prepared);
+    print_commands(prepared, args);
+    Ok(())
+}
+
+/// The variables the harness adds to every cargo and dx invocation. Values
+/// only known during a run are shown in angle brackets.
+fn print_env(prepared: &PreparedScenario, args: &RunArgs, config: &Config) {
+    let scenario = &prepared.scenario;
+    let mut env: Vec<(String, String)> = Vec::new();
+    if args.share_deps {
+        env.push((
+            "CARGO_TARGET_DIR".to_string(),
+            "<shared target directory for these compile settings>".to_string(),
+        ));
+    }
+    if let Some(tool) = scenario.cache.and_then(Cache::server) {
+        let prefix = tool.env_prefix();
+        env.push((
+            format!("{prefix}_DIR"),
+            format!("{WORKSPACE}/{}", tool.dir_name()),
+        ));
+        env.push((format!("{prefix}_SERVER_PORT"), "<free port>".to_string()));
+    }
+    if scenario.cache == Some(Cache::Wrapper) {
+        match &config.wrapper {
+            Some(wrapper) => {
+                env.extend(wrapper.env.clone());
+                // Local runs put the harness in front as a logging shim.
+                env.push(("RUSTC_WRAPPER".to_string(), wrapper.command.clone()));
+            }
+            None => eprintln!(
+                "[bench][warn] The wrapper cache needs a [wrapper] command in the config file"
+            ),
+        }
+    }
+    if scenario.target == Some(Target::Aarch64Android) {
+        let ndk = detect_android_ndk().map_or_else(
+            |_| "<Android NDK, none found here>".to_string(),
+            |path| path.display().to_string(),
+        );
+        env.push(("ANDROID_NDK_HOME".to_string(), ndk));
+    }
+    if scenario.registry.is_some() {
+        env.push((
+            "CARGO_HOME".to_string(),
+            format!("{WORKSPACE}/cargo-home (from the fetch phase on)"),
+        ));
+    }
+    println!("[bench] Environment set by the harness:");
+    if env.is_empty() {
+        println!("    (none)");
+    }
+    for (key, value) in &env {
+        println!("    {key}={}", redact::redact(value));
+    }
+}
+
+/// Every command of the scenario in the order a run starts them, with the
+/// phase each belongs to.
+fn print_commands(prepared: &PreparedScenario, args: &RunArgs) {
+    let (scenario, slug) = (&prepared.scenario, prepared.slug.as_str());
+    let cargo = |subcommand: &str| {
+        let mut args = cargo_args(scenario, subcommand, false);
+        args.extend(prepared.cargo_args());
+        format!("cargo {}", args.join(" "))
+    };
+    let step = |label: &str, command: &str| println!("    {label:<18} {command}");
+    println!("[bench] Commands, each run in {WORKSPACE}:");
+    if let Some(tool) = scenario.cache.and_then(Cache::server) {
+        step("setup", &format!("{} --start-server", tool.program()));
+        if scenario.warm_cache {
+            step("cache pre-warming", &cargo("build"));
+            step("", &format!("rm -r {WORKSPACE}/target/{slug}"));
+            step("", &format!("{} --zero-stats", tool.program()));
+        }
+    }
+    if args.clean_mode == CleanMode::Payload {
+        step("dependency", &cargo("build"));
+        let mut clean = format!(
+            "cargo clean --quiet --locked -p {}",
+            prepared.packages().join(" -p ")
+        );
+        if let Some(profile) = scenario.profile {
+            clean.push_str(&format!(" --profile {}", profile.label()));
+        }
+        if let Some(target) = scenario.target {
+            clean.push_str(&format!(" --target {}", target.triple()));
+        }
+        step("", &clean);
+    }
+    let payload_file = prepared.code.payload_file;
+    let change = prepared.mutation.describe();
+    let edit = format!("(after {change} in {payload_file})");
+    let target_dir = format!("{WORKSPACE}/target/{slug}");
+    if let Some(dir) = &args.restore_target {
+        step(
+            "restore",
+            &format!(
+                "tar --zstd -xf {} -C {target_dir} (the clean phase is skipped if the snapshot exists)",
+                snapshot::path(dir, slug).display()
+            ),
+        );
+    }
+    for phase in Phase::for_scenario(scenario, args.first_frame, args.relocate) {
+        let name = phase.name();
+        match phase {
+            Phase::Fetch => {
+                let mut fetch = "cargo fetch --quiet --locked".to_string();
+                if let Some(target) = scenario.target {
+                    fetch.push_str(&format!(" --target {}", target.triple()));
+                }
+                step(name, &fetch);
+            }
+            Phase::Clean if args.crate_timings.is_some() => {
+                step(name, &format!("{} --timings", cargo("build")));
+            }
+            Phase::Clean | Phase::Second => step(name, &cargo("build")),
+            Phase::Relocated => step(
+                name,
+                &format!("{} (in a copy of the workspace)", cargo("build")),
+            ),
+            Phase::WasmOpt => step(
+                name,
+                &format!(
+                    "wasm-opt -Oz --all-features <artifact dir>/bench-payload-{slug}.wasm -o <artifact dir>/payload.opt.wasm"
+                ),
+            ),
+            Phase::Modified => step(name, &format!("{} {edit}", cargo("build"))),
+            Phase::FirstFrame => step(name, &cargo("run")),
+            Phase::Hotpatch => {
+                step(
+                    name,
+                    &format!("dx {}", dx_serve_args("<free port>").join(" ")),
+                );
+                step(
+                    "",
+                    &format!("then edit {payload_file} by {change} once the payload is ready"),
+                );
+            }
+            Phase::HotpatchRebuild => {
+                step(
+                    name,
+                    &format!("{} (in a copy of the workspace)", cargo("build")),
+                );
+                step("", &format!("{} {edit}", cargo("build")));
+            }
+        }
+        if phase == Phase::Clean
+            && let Some(dir) = &args.save_target
+        {
+            step(
+                "",
+                &format!(
+                    "tar --zstd -cf {} -C {target_dir} .",
+                    snapshot::path(dir, slug).display()
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! `bench feature-delta`: starts the payload from `default-features = false`
+//! and adds one Bevy feature at a time, so the marginal clean and second
+//! build cost of each feature can be read off under one scenario.
+
+use anyhow::{Context, Result, bail};
+
+use crate::bisect::find_scenario;
+use crate::cli::FeatureDeltaArgs;
+use crate::config::Config;
+use crate::remote::Host;
+use crate::{BevyFeatures, PreparedScenario, RunContext, process_tree, run_iterations};
+
+/// Phases compared between the feature sets.
+const PHASES: [&str; 2] = ["clean", "second"];
+
+/// Median time of each of [`PHASES`] for one feature set.
+struct Measurement {
+    label: String,
+    seconds: [f64; PHASES.len()],
+}
+
+pub fn run(args: &FeatureDeltaArgs, config: &Config) -> Result<()> {
+    let mut prepared = find_scenario(&args.run, &args.scenario, config)?;
+    if prepared.scenario.hotpatching.is_some() {
+        bail!(
+            "{} uses dx hotpatching, which needs Bevy's default features; pick a scenario without it",
+            prepared.slug
+        );
+    }
+    let host = Host::from_remote(args.run.remote.as_deref(), &args.run.remote_dir);
+    process_tree::forward_interrupts().context("failed to install signal handlers")?;
+    let context = RunContext::new(&args.run, &host, config, false);
+
+    let mut measure = |label: &str, features: Vec<String>| -> Result<Measurement> {
+        println!("\n[bench] Measuring {label} on {}", prepared.slug);
+        prepared.use_features(&BevyFeatures::Only(features), &config.patch);
+        let run = run_iterations(
+            &prepared,
+            &context,
+            Vec::new(),
+            args.run.iterations,
+            args.run.rerun_outliers,
+        )
+        .with_context(|| format!("failed to measure {label}"))?;
+        let mut seconds = [0.0; PHASES.len()];
+        for (value, phase) in seconds.iter_mut().zip(PHASES) {
+            *value = run
+                .phase_seconds(phase)
+                .with_context(|| format!("{} does not measure the {phase} phase", prepared.slug))?;
+        }
+        Ok(Measurement {
+            label: label.to_string(),
+            seconds,
+        })
+    };
+
+    let baseline = measure("the base feature set", args.base.clone())?;
+    let mut deltas = Vec::new();
+    for feature in &args.features {
+        if args.base.contains(feature) {
+            eprintln!("[bench][warn] Skipping {feature}: it is already in --base");
+            continue;
+        }
+        let mut features = args.base.clone();
+        features.push(feature.clone());
+        match measure(feature, features) {
+            Ok(measurement) => deltas.push(measurement),
+            Err(_) if process_tree::interrupted() => bail!("feature experiment interrupted"),
+            Err(err) => eprintln!("[bench][warn] Skipping {feature}: {err:#}"),
+        }
+    }
+    print_table(&prepared, &args.base, &baseline, &deltas);
+    Ok(())
+}
+
+fn print_table(
+    prepared: &PreparedScenario,
+    base: &[String],
+    baseline: &Measurement,
+    deltas: &[Measurement],
+) {
+    let base = if base.is_empty() {
+        "no features".to_string()
+    } else {
+        base.join(", ")
+    };
+    println!(
+        "\nMarginal build cost of each feature for {} (base: {base}):",
+        prepared.slug
+    );
+    let width = deltas
+        .iter()
+        .map(|measurement| measurement.label.len())
+        .chain(["feature".len(), "base".len()])
+        .max()
+        .unwrap_or(0);
+    print!("{:<width$}", "feature");
+    for phase in PHASES {
+        print!("  {phase:>8}  {:>8}", "delta");
+    }
+    println!();
+    print!("{:<width$}", "base");
+    for seconds in baseline.seconds {
+        print!("  {seconds:>7.1}s  {:>8}", "");
+    }
+    println!();
+    for measurement in deltas {
+        print!("{:<width$}", measurement.label);
+        for (seconds, base) in measurement.seconds.iter().zip(baseline.seconds) {
+            print!("  {seconds:>7.1}s  {:>+7.1}s", seconds - base);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,183 @@
+//! Workspace roots as a matrix axis, so the same scenarios can be measured on
+//! different filesystems (e.g. NTFS vs. a ReFS Dev Drive), plus detection of
+//! the filesystem type and antivirus coverage of a path.
+
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A named directory scenario workspaces are created in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// Appended to the scenario slug.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Parses `NAME=PATH` for `--workspace-root`.
+pub fn parse_workspace_root(input: &str) -> Result<WorkspaceRoot> {
+    let Some((name, path)) = input.split_once('=') else {
+        bail!("expected NAME=PATH, e.g. devdrive=D:\\bench");
+    };
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        bail!("root name `{name}` must use only a-z, 0-9, and `-`");
+    }
+    Ok(WorkspaceRoot {
+        name: name.to_string(),
+        path: PathBuf::from(path),
+    })
+}
+
+/// Filesystem type of `path` on this machine (`NTFS`, `ReFS`, `ext4`, ...).
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    if cfg!(windows) {
+        powershell(&format!(
+            "(Get-Volume -FilePath '{}').FileSystemType",
+            path.display()
+        ))
+    } else {
+        let output = Command::new("stat")
+            .args(["-f", "-c", "%T"])
+            .arg(path)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Whether Windows Defender real-time protection scans `path`: protection is
+/// on and no exclusion covers the path. `None` off Windows or when the
+/// exclusion list cannot be read (it requires administrator rights).
+pub fn defender_scans(path: &Path) -> Option<bool> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let realtime = powershell("(Get-MpComputerStatus).RealTimeProtectionEnabled")?;
+    if !realtime.eq_ignore_ascii_case("true") {
+        return Some(false);
+    }
+    Some(!defender_excludes(path)?)
+}
+
+/// Whether a Defender path exclusion covers `path`.
+pub fn defender_excludes(path: &Path) -> Option<bool> {
+    let exclusions = powershell("(Get-MpPreference).ExclusionPath -join '|'")?;
+    if exclusions.starts_with("N/A") {
+        return None;
+    }
+    let path = path.display().to_string().to_ascii_lowercase();
+    Some(
+        exclusions
+            .split('|')
+            .map(|exclusion| exclusion.trim().trim_end_matches('\\').to_ascii_lowercase())
+            .any(|exclusion| !exclusion.is_empty() && path.starts_with(&exclusion)),
+    )
+}
+
+/// Defender coverage of the places a Windows build reads and writes, for the
+/// run's environment metadata. `None` off Windows.
+pub fn defender_summary() -> Option<String> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let describe = |excluded: Option<bool>| match excluded {
+        Some(true) => "excluded",
+        Some(false) => "scanned",
+        None => "unknown",
+    };
+    let realtime = powershell("(Get-MpComputerStatus).RealTimeProtectionEnabled")
+        .unwrap_or_else(|| "unknown".to_string());
+    let temp = defender_excludes(&std::env::temp_dir());
+    let cargo_home = cargo_home().and_then(|path| defender_excludes(&path));
+    for (name, excluded) in [("temp directory", temp), ("CARGO_HOME", cargo_home)] {
+        if excluded == Some(false) && realtime.eq_ignore_ascii_case("true") {
+            eprintln!(
+                "[bench][warn] Windows Defender scans the {name}; build times will include antivirus overhead"
+            );
+        }
+    }
+    Some(format!(
+        "realtime={realtime}, temp={}, cargo-home={}",
+        describe(temp),
+        describe(cargo_home)
+    ))
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("USERPROFILE").map(|home| PathBuf::from(home).join(".cargo")))
+}
+
+/// Two workspace roots, one excluded from Defender for the lifetime of this
+/// value, for paired scanned-vs-excluded measurements. Needs an elevated
+/// shell to change the exclusion list.
+#[derive(Debug)]
+pub struct DefenderPair {
+    excluded: PathBuf,
+    pub roots: [WorkspaceRoot; 2],
+}
+
+impl DefenderPair {
+    pub fn create() -> Result<Self> {
+        if !cfg!(windows) {
+            bail!("--defender-pair is only available on Windows");
+        }
+        let base = std::env::temp_dir().join("bevy-bench-defender");
+        let scanned = base.join("scanned");
+        let excluded = base.join("excluded");
+        for dir in [&scanned, &excluded] {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        powershell(&format!(
+            "Add-MpPreference -ExclusionPath '{}'; 'ok'",
+            excluded.display()
+        ))
+        .context("failed to add a Defender exclusion (run from an elevated shell)")?;
+        Ok(Self {
+            excluded: excluded.clone(),
+            roots: [
+                WorkspaceRoot {
+                    name: "av-scanned".to_string(),
+                    path: scanned,
+                },
+                WorkspaceRoot {
+                    name: "av-excluded".to_string(),
+                    path: excluded,
+                },
+            ],
+        })
+    }
+}
+
+impl Drop for DefenderPair {
+    fn drop(&mut self) {
+        let removed = powershell(&format!(
+            "Remove-MpPreference -ExclusionPath '{}'; 'ok'",
+            self.excluded.display()
+        ));
+        if removed.is_none() {
+            eprintln!(
+                "[bench][warn] Failed to remove the Defender exclusion for {}",
+                self.excluded.display()
+            );
+        }
+    }
+}
+
+fn powershell(script: &str) -> Option<String> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty()).then_some(stdout)
+}
@@ -0,0 +1,120 @@
+//! Identifies the exact environment a scenario is measured in, so results that
+//! are already in the history can be reused instead of re-measured.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::cli::RunArgs;
+use crate::filesystem;
+use crate::mutation::Mutation;
+use crate::patch::Patches;
+use crate::pin::Pins;
+use crate::remote::Host;
+use crate::{Code, PreparedScenario};
+
+/// Tool versions and machine details that can change timings without any
+/// change to the generated workspace.
+#[derive(Debug)]
+pub struct Environment {
+    facts: Vec<(&'static str, String)>,
+}
+
+const PROBES: &[(&str, &str, &[&str])] = &[
+    ("rustc", "rustc", &["--version"]),
+    ("cargo", "cargo", &["--version"]),
+    ("dx", "dx", &["--version"]),
+    ("sccache", "sccache", &["--version"]),
+    ("cachepot", "cachepot", &["--version"]),
+    ("kernel", "uname", &["-srm"]),
+    ("hostname", "uname", &["-n"]),
+    ("cpus", "nproc", &[]),
+];
+
+impl Environment {
+    pub fn probe(host: &Host, patches: &Patches, pins: &Pins, args: &RunArgs) -> Self {
+        let mut facts: Vec<(&'static str, String)> = PROBES
+            .iter()
+            .map(|&(name, program, args)| {
+                let value = host
+                    .probe(program, args)
+                    .unwrap_or_else(|| "unavailable".to_string());
+                (name, value)
+            })
+            .collect();
+        // Antivirus scanning is one of the largest hidden variables on Windows.
+        if matches!(host, Host::Local)
+            && let Some(defender) = filesystem::defender_summary()
+        {
+            facts.push(("defender", defender));
+        }
+        // A niced build competes differently with the rest of the machine, so
+        // its results are never reused for a normal-priority run.
+        if matches!(host, Host::Local)
+            && let Some(nice) = args.nice
+        {
+            facts.push(("nice", nice.to_string()));
+        }
+        // A successful result without a limit says nothing about whether the
+        // build fits under one.
+        if let Some(bytes) = args.memory_limit {
+            facts.push(("memory-limit", bytes.to_string()));
+        }
+        // The edit decides what the incremental and hotpatch phases rebuild.
+        if args.mutation != Mutation::Constant {
+            facts.push(("mutation", args.mutation.label().to_string()));
+        }
+        // A patched Bevy checkout changes timings without changing any
+        // generated file.
+        for revision in patches.revisions(host) {
+            facts.push(("patch", revision));
+        }
+        // Restored scenarios skip the clean build and start warm.
+        if args.restore_target.is_some() {
+            facts.push(("restore-target", "yes".to_string()));
+        }
+        // Pins change the dependency graph but no generated file.
+        for pin in pins.facts() {
+            facts.push(("pin", pin));
+        }
+        Self { facts }
+    }
+
+    pub fn describe(&self) -> String {
+        self.facts
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Hash of everything that determines the scenario's timings: the
+    /// generated workspace files plus this environment.
+    pub fn scenario_hash(&self, scenario: &PreparedScenario) -> String {
+        let Code {
+            cargo_config_toml,
+            payload_file,
+            src_main_rs,
+            entry_files,
+            cargo_toml,
+            rust_toolchain_toml,
+        } = &scenario.code;
+
+        let mut hasher = DefaultHasher::new();
+        cargo_config_toml.hash(&mut hasher);
+        payload_file.hash(&mut hasher);
+        src_main_rs.hash(&mut hasher);
+        // Only split payloads have entry files; others keep their old hash.
+        if !entry_files.is_empty() {
+            entry_files.hash(&mut hasher);
+        }
+        cargo_toml.hash(&mut hasher);
+        rust_toolchain_toml.hash(&mut hasher);
+        // Likewise only scenarios with extra cargo arguments hash them.
+        let cargo_args = scenario.cargo_args();
+        if !cargo_args.is_empty() {
+            cargo_args.hash(&mut hasher);
+        }
+        self.facts.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
@@ -0,0 +1,89 @@
+//! `--first-frame`: starts the built payload and times how long it takes to
+//! finish its first frame. Dynamic linking and opt-levels move startup as much
+//! as build times, and both shape how iteration feels.
+
+use anyhow::{Context, Result};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use crate::capture;
+use crate::error::{BenchError, CapturedOutput};
+use crate::phase::{Phase, PhaseResult};
+use crate::runner::{self, CommandRunner, StreamEvent};
+use crate::tail::OutputTail;
+use crate::{PreparedScenario, Workspace, cargo_command};
+
+/// Prefix of the line the payload prints at the end of its first frame,
+/// followed by the milliseconds since its `main` started.
+pub(crate) const MARKER: &str = "PAYLOAD_FIRST_FRAME_MS=";
+
+/// How long the payload gets to open its window and draw.
+const TIMEOUT: Duration = Duration::from_secs(60);
+/// Start of the launch failure's reason when [`TIMEOUT`] ran out.
+pub(crate) const TIMED_OUT: &str = "timed out";
+
+/// The milliseconds a first-frame line reports, if `line` is one.
+pub(crate) fn parse(line: &str) -> Option<f64> {
+    capture::normalize(line)
+        .split_once(MARKER)?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Runs the payload with `cargo run` until its first frame. The phase lasts
+/// from starting cargo to the first-frame line, which includes loading any
+/// dynamic libraries; the payload's own count from `main` is stored as the
+/// `first_frame_ms` metric.
+pub(crate) fn measure(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<PhaseResult> {
+    println!("[bench] Starting the payload to time its first frame...");
+    let mut command = cargo_command(workspace, prepared, "run");
+    let started = Instant::now();
+    let mut process = runner
+        .spawn(&mut command, "cargo")
+        .context("failed to start the payload")?;
+    let rx = runner::stream_output(process.as_mut(), "payload")?;
+    let mut tail = OutputTail::default();
+    let failed = |reason: String, tail: OutputTail| -> anyhow::Error {
+        BenchError::PayloadLaunch {
+            reason,
+            output: CapturedOutput::new("cargo run", tail),
+        }
+        .into()
+    };
+    loop {
+        let remaining = (started + TIMEOUT).saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok(StreamEvent::Line(kind, line)) => {
+                runner::forward_stream_line("payload", kind, &line);
+                if let Some(milliseconds) = parse(&line) {
+                    let elapsed = started.elapsed();
+                    runner::shutdown_process(process.as_mut(), "payload")?;
+                    let mut result = PhaseResult::new(Phase::FirstFrame, elapsed, None);
+                    result
+                        .metrics
+                        .insert("first_frame_ms".to_string(), milliseconds);
+                    return Ok(result);
+                }
+                tail.push(kind, line);
+            }
+            Ok(StreamEvent::Closed(_) | StreamEvent::FrameTime(_)) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                runner::shutdown_process(process.as_mut(), "payload")?;
+                return Err(failed(
+                    format!("{TIMED_OUT} after {}s", TIMEOUT.as_secs()),
+                    tail,
+                ));
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = process.wait().context("failed to wait for the payload")?;
+                return Err(failed(format!("exited with status {status}"), tail));
+            }
+        }
+    }
+}
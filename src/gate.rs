@@ -0,0 +1,89 @@
+//! `bench gate`: fail CI when a measured phase exceeds its time budget.
+
+use anyhow::{Context, Result, bail};
+use std::time::Duration;
+
+use crate::cli::{GateArgs, PhaseBudgets};
+use crate::config::Config;
+use crate::format_duration;
+use crate::history;
+
+pub fn run(args: &GateArgs, config: &Config) -> Result<()> {
+    let record = history::read_run(&args.results)?;
+    let mut checked = 0usize;
+    let mut violations = 0usize;
+
+    for scenario in &record.scenarios {
+        if scenario.error.is_some() {
+            println!(
+                "[gate] SKIP {} (scenario failed, no timings)",
+                scenario.slug
+            );
+            continue;
+        }
+
+        // Per-slug config beats the command line, which beats config defaults.
+        let budgets = config
+            .gate
+            .slugs
+            .get(&scenario.slug)
+            .copied()
+            .unwrap_or_default()
+            .or(args.budgets)
+            .or(config.gate.budgets);
+
+        let timings = &scenario.timings;
+        let phases = [
+            ("clean", timings.phase("clean"), budgets.max_clean),
+            ("second", timings.phase("second"), budgets.max_second),
+            ("modified", timings.phase("modified"), budgets.max_modified),
+            ("hotpatch", timings.phase("hotpatch"), budgets.max_hotpatch),
+        ];
+        for (phase, measured, budget) in phases {
+            let (Some(measured), Some(budget)) = (measured, budget) else {
+                continue;
+            };
+            // The results file may be hand-edited or corrupt.
+            let measured = Duration::try_from_secs_f64(measured).with_context(|| {
+                format!(
+                    "{} records an invalid {phase} time of {measured}s in {}",
+                    scenario.slug,
+                    args.results.display()
+                )
+            })?;
+            checked += 1;
+            let verdict = if measured > budget {
+                violations += 1;
+                "FAIL"
+            } else {
+                "ok  "
+            };
+            println!(
+                "[gate] {verdict} {} {phase}: {} (budget {})",
+                scenario.slug,
+                format_duration(Some(measured)),
+                format_duration(Some(budget))
+            );
+        }
+    }
+
+    if checked == 0 && !has_any_budget(args.budgets, config) {
+        bail!("no budgets configured; pass --max-<phase> or add a [gate] section to the config");
+    }
+
+    println!("[gate] {checked} phase(s) checked, {violations} over budget.");
+    if violations > 0 {
+        bail!("{violations} phase(s) exceeded their budget");
+    }
+    Ok(())
+}
+
+fn has_any_budget(cli: PhaseBudgets, config: &Config) -> bool {
+    let any = |budgets: PhaseBudgets| {
+        budgets.max_clean.is_some()
+            || budgets.max_second.is_some()
+            || budgets.max_modified.is_some()
+            || budgets.max_hotpatch.is_some()
+    };
+    any(cli) || any(config.gate.budgets) || config.gate.slugs.values().copied().any(any)
+}
@@ -0,0 +1,308 @@
+//! Timings from previous runs in the results directory, used to estimate how
+//! long each scenario will take before it starts.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{PreparedScenario, RunRecord, ScenarioTimingRecord};
+use crate::{metadata, stats};
+
+#[derive(Debug, Default)]
+pub struct History {
+    estimates: HashMap<String, Duration>,
+    /// Environment hash of every successful scenario, mapped to the run log
+    /// that holds its result.
+    environments: HashMap<String, PathBuf>,
+    /// Per-phase median of each slug's past timings.
+    baselines: HashMap<String, ScenarioTimingRecord>,
+}
+
+impl History {
+    /// Reads every run log under `dir`.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut samples: HashMap<String, Vec<f64>> = HashMap::new();
+        let mut environments = HashMap::new();
+        let mut timings: HashMap<String, Vec<ScenarioTimingRecord>> = HashMap::new();
+        for (path, record) in read_runs(dir)? {
+            for scenario in record.scenarios {
+                if scenario.error.is_some() {
+                    continue;
+                }
+                if let Some(hash) = scenario.environment_hash {
+                    environments.insert(hash, path.clone());
+                }
+                if let Some(total) = total_seconds(&scenario.timings) {
+                    samples
+                        .entry(scenario.slug.clone())
+                        .or_default()
+                        .push(total);
+                }
+                timings
+                    .entry(scenario.slug)
+                    .or_default()
+                    .push(scenario.timings);
+            }
+        }
+
+        let estimates = samples
+            .into_iter()
+            .map(|(slug, values)| (slug, Duration::from_secs_f64(stats::median(&values))))
+            .collect();
+        let baselines = timings
+            .into_iter()
+            .map(|(slug, samples)| (slug, ScenarioTimingRecord::median(&samples)))
+            .collect();
+        Ok(Self {
+            estimates,
+            environments,
+            baselines,
+        })
+    }
+
+    /// Run log that already has a result for this exact environment.
+    pub fn cached_result(&self, environment_hash: &str) -> Option<&Path> {
+        self.environments
+            .get(environment_hash)
+            .map(PathBuf::as_path)
+    }
+
+    pub fn baseline(&self, slug: &str) -> Option<&ScenarioTimingRecord> {
+        self.baselines.get(slug)
+    }
+
+    pub fn estimate(&self, slug: &str) -> Option<Duration> {
+        self.estimates.get(slug).copied()
+    }
+
+    /// Orders scenarios cheapest-first so useful partial results arrive early.
+    /// Scenarios without history keep their relative order and run last, since
+    /// nothing suggests they are cheap.
+    pub fn schedule_fastest_first(&self, scenarios: &mut [PreparedScenario]) {
+        scenarios.sort_by(
+            |a, b| match (self.estimate(&a.slug), self.estimate(&b.slug)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            },
+        );
+    }
+
+    /// Estimated wall time for `scenarios`. Slugs without history are charged
+    /// the mean of the known estimates. Returns the total and how many of the
+    /// scenarios were actually covered by history.
+    pub fn forecast(&self, scenarios: &[PreparedScenario]) -> Option<(Duration, usize)> {
+        let known: Vec<Duration> = scenarios
+            .iter()
+            .filter_map(|scenario| self.estimate(&scenario.slug))
+            .collect();
+        if known.is_empty() {
+            return None;
+        }
+
+        let known_total: Duration = known.iter().sum();
+        let fallback = known_total / known.len() as u32;
+        let unknown = (scenarios.len() - known.len()) as u32;
+        Some((known_total + fallback * unknown, known.len()))
+    }
+
+    pub fn report_forecast(&self, scenarios: &[PreparedScenario], iterations: u32) {
+        match self.forecast(scenarios) {
+            Some((total, covered)) => println!(
+                "Estimated total run time: {} ({covered}/{} scenario(s) have history).",
+                format_eta(total * iterations),
+                scenarios.len()
+            ),
+            None => println!("No timing history found; run time cannot be estimated yet."),
+        }
+    }
+}
+
+/// Time left in a run, re-estimated after every scenario. History estimates
+/// only cover the phases, so they are scaled by how far off they were for the
+/// scenarios measured so far; scenarios without history are charged the mean
+/// wall time measured so far.
+#[derive(Debug)]
+pub struct RunForecast {
+    /// Forecast for the whole run before it started.
+    initial: Option<Duration>,
+    started: Instant,
+    /// Estimated and actual wall time of finished scenarios with history.
+    checked: Vec<(Duration, Duration)>,
+    /// Wall time of every finished scenario.
+    finished: Vec<Duration>,
+}
+
+impl RunForecast {
+    pub fn start(history: &History, scenarios: &[PreparedScenario], iterations: u32) -> Self {
+        Self {
+            initial: history
+                .forecast(scenarios)
+                .map(|(total, _)| total * iterations),
+            started: Instant::now(),
+            checked: Vec::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    /// Records that `slug` took `elapsed` for `iterations` iterations.
+    pub fn finish(&mut self, history: &History, slug: &str, iterations: u32, elapsed: Duration) {
+        if let Some(estimate) = history.estimate(slug) {
+            self.checked.push((estimate * iterations, elapsed));
+        }
+        self.finished.push(elapsed);
+    }
+
+    /// Actual over estimated wall time so far; 1 until something is known.
+    fn correction(&self) -> f64 {
+        let estimated: Duration = self.checked.iter().map(|(estimate, _)| *estimate).sum();
+        let actual: Duration = self.checked.iter().map(|(_, actual)| *actual).sum();
+        if estimated.is_zero() {
+            1.0
+        } else {
+            actual.as_secs_f64() / estimated.as_secs_f64()
+        }
+    }
+
+    pub fn remaining(
+        &self,
+        history: &History,
+        scenarios: &[PreparedScenario],
+        iterations: u32,
+    ) -> Option<Duration> {
+        let correction = self.correction();
+        let measured = (!self.finished.is_empty())
+            .then(|| self.finished.iter().sum::<Duration>() / self.finished.len() as u32);
+        let fallback = measured.or_else(|| {
+            history
+                .forecast(scenarios)
+                .map(|(total, _)| total.mul_f64(correction) * iterations / scenarios.len() as u32)
+        })?;
+        Some(
+            scenarios
+                .iter()
+                .map(|scenario| {
+                    history
+                        .estimate(&scenario.slug)
+                        .map(|estimate| (estimate * iterations).mul_f64(correction))
+                        .unwrap_or(fallback)
+                })
+                .sum(),
+        )
+    }
+
+    pub fn report_remaining(
+        &self,
+        history: &History,
+        scenarios: &[PreparedScenario],
+        iterations: u32,
+    ) {
+        if scenarios.is_empty() {
+            return;
+        }
+        if let Some(remaining) = self.remaining(history, scenarios, iterations) {
+            println!(
+                "[bench] Forecast: about {} left for {} scenario(s), {} elapsed",
+                format_eta(remaining),
+                scenarios.len(),
+                format_eta(self.started.elapsed())
+            );
+        }
+    }
+
+    /// How far the forecasts were off, for the end of the run.
+    pub fn report_error(&self) {
+        let elapsed = self.started.elapsed();
+        if let Some(initial) = self.initial
+            && !initial.is_zero()
+        {
+            println!(
+                "[bench] Forecast error: estimated {} before the run, took {} ({:+.1}%)",
+                format_eta(initial),
+                format_eta(elapsed),
+                (elapsed.as_secs_f64() / initial.as_secs_f64() - 1.0) * 100.0
+            );
+        }
+        let errors: Vec<f64> = self
+            .checked
+            .iter()
+            .filter(|(estimate, _)| !estimate.is_zero())
+            .map(|(estimate, actual)| {
+                (actual.as_secs_f64() / estimate.as_secs_f64() - 1.0).abs() * 100.0
+            })
+            .collect();
+        if !errors.is_empty() {
+            println!(
+                "[bench] Per-scenario estimates were off by {:.1}% on average ({} scenario(s) with history)",
+                errors.iter().sum::<f64>() / errors.len() as f64,
+                errors.len()
+            );
+        }
+    }
+}
+
+/// Every `*.ron` run log under `dir`, in file name order. Files that fail to
+/// parse are skipped with a warning so one truncated log cannot block a new
+/// run.
+pub fn read_runs(dir: &Path) -> Result<Vec<(PathBuf, RunRecord)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read results directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry
+            .context("failed to read results directory entry")?
+            .path();
+        if path.extension().is_some_and(|ext| ext == "ron") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut runs = Vec::with_capacity(paths.len());
+    for path in paths {
+        match read_run(&path) {
+            Ok(record) => runs.push((path, record)),
+            Err(err) => eprintln!(
+                "[bench][warn] Ignoring unreadable history {}: {err:#}",
+                path.display()
+            ),
+        }
+    }
+    Ok(runs)
+}
+
+pub fn read_run(path: &Path) -> Result<RunRecord> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let record: RunRecord =
+        ron::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    metadata::check_supported(&record, path)?;
+    Ok(record)
+}
+
+fn total_seconds(timings: &ScenarioTimingRecord) -> Option<f64> {
+    let measured: Vec<f64> = timings.phases().map(|(_, seconds)| seconds).collect();
+    if measured.is_empty() {
+        None
+    } else {
+        Some(measured.iter().sum())
+    }
+}
+
+pub fn format_eta(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
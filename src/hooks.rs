@@ -0,0 +1,94 @@
+//! User shell hooks run around scenarios and phases, configured under
+//! `[hooks]` in the config file.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::Workspace;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// After the workspace is created, before the first build.
+    pub before_scenario: Option<String>,
+    /// After each timed `cargo build`; `BENCH_PHASE` names the build.
+    pub after_build: Option<String>,
+    /// Before `dx serve` starts.
+    pub before_hotpatch: Option<String>,
+    /// After the last phase, before the workspace is removed.
+    pub after_scenario: Option<String>,
+}
+
+impl Hooks {
+    pub fn before_scenario(&self, workspace: &Workspace, slug: &str) -> Result<()> {
+        run_hook(
+            "before_scenario",
+            &self.before_scenario,
+            workspace,
+            slug,
+            None,
+        )
+    }
+
+    pub fn after_build(&self, workspace: &Workspace, slug: &str, phase: &str) -> Result<()> {
+        run_hook(
+            "after_build",
+            &self.after_build,
+            workspace,
+            slug,
+            Some(phase),
+        )
+    }
+
+    pub fn before_hotpatch(&self, workspace: &Workspace, slug: &str) -> Result<()> {
+        run_hook(
+            "before_hotpatch",
+            &self.before_hotpatch,
+            workspace,
+            slug,
+            None,
+        )
+    }
+
+    pub fn after_scenario(&self, workspace: &Workspace, slug: &str) -> Result<()> {
+        run_hook(
+            "after_scenario",
+            &self.after_scenario,
+            workspace,
+            slug,
+            None,
+        )
+    }
+}
+
+/// Runs `script` through the host's shell in the workspace directory with
+/// `BENCH_WORKSPACE`, `BENCH_SLUG`, and (for build hooks) `BENCH_PHASE` set.
+/// A failing hook fails the scenario.
+fn run_hook(
+    name: &str,
+    script: &Option<String>,
+    workspace: &Workspace,
+    slug: &str,
+    phase: Option<&str>,
+) -> Result<()> {
+    let Some(script) = script else {
+        return Ok(());
+    };
+    let mut env = workspace.env.clone();
+    env.push(("BENCH_WORKSPACE".to_string(), workspace.exec_dir.clone()));
+    env.push(("BENCH_SLUG".to_string(), slug.to_string()));
+    if let Some(phase) = phase {
+        env.push(("BENCH_PHASE".to_string(), phase.to_string()));
+    }
+
+    println!("[bench] Running {name} hook");
+    let status = workspace
+        .host
+        .shell(&workspace.exec_dir, &env, script)
+        .status()
+        .with_context(|| format!("failed to run {name} hook"))?;
+    if !status.success() {
+        bail!("{name} hook failed with status {status}");
+    }
+    Ok(())
+}
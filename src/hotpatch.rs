@@ -0,0 +1,572 @@
+//! Watches a `dx serve --hot-patch` session: waits for the payload's ready
+//! marker, triggers the source change, and times until the patched value
+//! appears.
+
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture;
+use crate::error::{BenchError, CapturedOutput};
+use crate::first_frame;
+use crate::protocol::{Server, Session};
+use crate::recording::Recording;
+use crate::runner::{self, Process, StreamEvent, StreamKind};
+use crate::tail::OutputTail;
+use crate::watchdog;
+
+/// How long `dx serve` gets to build and launch the payload.
+pub const READY_TIMEOUT: Duration = Duration::from_secs(180);
+/// Default of `--patch-timeout`: how long a patch gets to show up in the
+/// payload once the source changed.
+pub const PATCH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often the marker file is checked for new lines.
+const MARKER_POLL: Duration = Duration::from_millis(10);
+
+/// Where the payload reports its ready marker and payload value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReadyChannel {
+    /// The payload prints them, and dx forwards its output.
+    #[default]
+    Stdout,
+    /// The payload appends them to a file the harness follows, for dx
+    /// versions that swallow the app's output.
+    File,
+    /// The payload connects to the harness and reports them, and its frame
+    /// times, over the [harness protocol](crate::protocol).
+    Socket,
+}
+
+/// Where the payload reports besides the output of dx.
+#[derive(Debug)]
+pub enum MarkerSource {
+    /// A file the payload appends marker lines to.
+    File(PathBuf),
+    /// A listener the payload connects to with the harness protocol.
+    Socket(Server),
+}
+
+/// Outcome of the hotpatch phase.
+#[derive(Debug)]
+pub struct HotpatchOutcome {
+    pub elapsed: Duration,
+    /// Why the new payload value cannot be attributed to a hot patch, if it
+    /// cannot.
+    pub problem: Option<String>,
+    /// Frame statistics in milliseconds, as far as the payload reported
+    /// them: `first_frame_ms` from its `main` to the end of the first frame,
+    /// `frame_ms_median` over the session, and `patch_frame_ms_max`, the
+    /// longest frame while the patch was applied.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+/// Follows the output of a running `dx serve`. Once `ready_marker` appears,
+/// `trigger` changes the payload source and returns the line the patched
+/// payload prints; the time from then until that line, at most
+/// `patch_timeout`, is the hotpatch time.
+/// The process is shut down before returning, except when it already exited.
+/// Output, the patched line, and the exit code go to `recording` if given.
+///
+/// With `markers`, what the payload reports there is watched alongside the
+/// output of dx.
+pub fn watch(
+    process: &mut dyn Process,
+    ready_marker: &str,
+    ready_timeout: Duration,
+    patch_timeout: Duration,
+    markers: Option<&MarkerSource>,
+    trigger: impl FnOnce() -> Result<String>,
+    recording: Option<&mut Recording>,
+) -> Result<HotpatchOutcome> {
+    let mut session = DxSession::start(process, ready_marker, markers, recording)?;
+    session.wait_ready(ready_timeout)?;
+    let outcome = session.patch(patch_timeout, trigger)?;
+    session.finish()?;
+    Ok(outcome)
+}
+
+/// A running `dx serve --hot-patch` session, kept alive for any number of
+/// patches: [`DxSession::wait_ready`] once, then [`DxSession::patch`] for
+/// each source change, then [`DxSession::finish`]. Its output drives a
+/// [`DxMachine`]; a method that fails leaves the machine
+/// [`DxState::Failed`] and has already shut dx down, unless it exited on
+/// its own.
+pub struct DxSession<'a> {
+    process: &'a mut dyn Process,
+    rx: Receiver<StreamEvent>,
+    _follower: Option<Follower>,
+    connection: Option<Session>,
+    recording: Option<&'a mut Recording>,
+    started: Instant,
+    tail: OutputTail,
+    machine: DxMachine,
+    /// Output lines and frame times both count as signs of life.
+    last_activity: Instant,
+}
+
+impl<'a> DxSession<'a> {
+    /// Starts following `process`. With `markers`, what the payload reports
+    /// there is watched alongside the output of dx. Output, the first
+    /// patched line, and the exit code go to `recording` if given.
+    pub fn start(
+        process: &'a mut dyn Process,
+        ready_marker: &str,
+        markers: Option<&MarkerSource>,
+        recording: Option<&'a mut Recording>,
+    ) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let (follower, connection) = match markers {
+            Some(MarkerSource::File(path)) => {
+                (Some(Follower::start(path.clone(), tx.clone())), None)
+            }
+            Some(MarkerSource::Socket(server)) => (None, Some(server.serve(tx.clone())?)),
+            None => (None, None),
+        };
+        runner::stream_output_into(process, "dx serve", tx, None)?;
+        Ok(Self {
+            process,
+            rx,
+            _follower: follower,
+            connection,
+            recording,
+            started: Instant::now(),
+            tail: OutputTail::default(),
+            machine: DxMachine::new(ready_marker),
+            last_activity: Instant::now(),
+        })
+    }
+
+    pub fn state(&self) -> &DxState {
+        self.machine.state()
+    }
+
+    /// Waits up to `timeout` for the payload's ready marker.
+    pub fn wait_ready(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = self.started + timeout;
+        while *self.machine.state() == DxState::Starting {
+            if !self.advance()? && Instant::now() > deadline {
+                self.machine
+                    .fail(format!("no ready marker within {}s", timeout.as_secs()));
+                runner::shutdown_process(self.process, "dx serve")?;
+                return Err(BenchError::HotpatchTimeout {
+                    marker: self.machine.ready_marker.clone(),
+                    output: CapturedOutput::new("dx serve", std::mem::take(&mut self.tail)),
+                }
+                .into());
+            }
+        }
+        println!(
+            "[bench] Ready marker {} observed.",
+            self.machine.ready_marker
+        );
+        Ok(())
+    }
+
+    /// One source change: `trigger` writes it and returns the line the
+    /// patched payload prints, and the patch is timed until that line shows
+    /// up, for at most `timeout`. dx keeps running afterwards.
+    pub fn patch(
+        &mut self,
+        timeout: Duration,
+        trigger: impl FnOnce() -> Result<String>,
+    ) -> Result<HotpatchOutcome> {
+        if !self.machine.state().accepts_patch() {
+            bail!(
+                "cannot patch a dx session that is {}",
+                self.machine.state().label()
+            );
+        }
+        let expected = match trigger() {
+            Ok(expected) => expected,
+            Err(err) => {
+                self.machine.fail("the source change failed");
+                runner::shutdown_process(self.process, "dx serve")?;
+                return Err(err);
+            }
+        };
+        if let Some(recording) = self.recording.as_deref_mut() {
+            recording
+                .patched_line
+                .get_or_insert_with(|| expected.clone());
+        }
+        println!("[bench] Hotpatch triggered, waiting for {expected}.");
+        self.machine.begin_patch(expected.clone());
+        let started = Instant::now();
+        let deadline = started + timeout;
+        loop {
+            // Heartbeats and frame times keep arriving while the patch never
+            // lands, so the deadline is checked after every event.
+            self.advance()?;
+            if Instant::now() > deadline
+                && matches!(self.machine.state(), DxState::PatchPending { .. })
+            {
+                self.machine
+                    .fail(format!("no patched payload within {}s", timeout.as_secs()));
+                runner::shutdown_process(self.process, "dx serve")?;
+                return Err(BenchError::PatchTimeout {
+                    expected,
+                    output: CapturedOutput::new("dx serve", std::mem::take(&mut self.tail)),
+                }
+                .into());
+            }
+            if let DxState::Patched { problem, metrics } = self.machine.state() {
+                let elapsed = started.elapsed();
+                println!("[bench] Hotpatch payload observed.");
+                if let Some(problem) = problem {
+                    eprintln!("[bench][warn] {problem}");
+                }
+                return Ok(HotpatchOutcome {
+                    elapsed,
+                    problem: problem.clone(),
+                    metrics: metrics.clone(),
+                });
+            }
+        }
+    }
+
+    /// Shuts dx down, letting a connected payload exit first.
+    pub fn finish(mut self) -> Result<()> {
+        drop(self.connection.take());
+        runner::shutdown_process(self.process, "dx serve")
+    }
+
+    /// Feeds the next event to the machine. Returns `false` when nothing
+    /// arrived for a moment, and fails once dx exits or the watchdog gives
+    /// up on it.
+    fn advance(&mut self) -> Result<bool> {
+        let event = match self.rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => {
+                self.check_stall()?;
+                return Ok(false);
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = self.process.wait().context("failed to wait for dx serve")?;
+                return Err(self.exited(format!("output closed, status {status}"), status));
+            }
+        };
+        match &event {
+            StreamEvent::Line(kind, line) => {
+                self.last_activity = Instant::now();
+                runner::forward_stream_line("dx", *kind, line);
+                if let Some(recording) = self.recording.as_deref_mut() {
+                    recording.push(self.started.elapsed(), *kind, line);
+                }
+                self.tail.push(*kind, line.clone());
+            }
+            StreamEvent::FrameTime(_) => self.last_activity = Instant::now(),
+            StreamEvent::Closed(kind) => {
+                if let Some(status) = self
+                    .process
+                    .try_wait()
+                    .context("failed to poll dx serve status")?
+                {
+                    drain_output(
+                        &self.rx,
+                        &mut self.tail,
+                        self.started,
+                        self.recording.as_deref_mut(),
+                    );
+                    return Err(self.exited(format!("{kind:?} closed, status {status}"), status));
+                }
+            }
+        }
+        self.machine.on_event(&event);
+        Ok(true)
+    }
+
+    fn check_stall(&mut self) -> Result<()> {
+        let Some(watchdog) = watchdog::current() else {
+            return Ok(());
+        };
+        let silent = self.last_activity.elapsed();
+        if silent < watchdog.window {
+            return Ok(());
+        }
+        if !watchdog.stalled("dx serve", self.process, &self.tail, silent) {
+            self.last_activity = Instant::now();
+            return Ok(());
+        }
+        self.machine
+            .fail(format!("no output for {}s", silent.as_secs()));
+        runner::shutdown_process(self.process, "dx serve")?;
+        Err(BenchError::Stalled {
+            tool: "dx serve".to_string(),
+            silent_seconds: silent.as_secs(),
+            output: CapturedOutput::new("dx serve", std::mem::take(&mut self.tail)),
+        }
+        .into())
+    }
+
+    fn exited(&mut self, reason: String, status: ExitStatus) -> anyhow::Error {
+        self.machine.fail(reason.clone());
+        if let Some(recording) = self.recording.as_deref_mut() {
+            recording.exit_code = status.code();
+        }
+        BenchError::HotpatchExited {
+            reason,
+            output: CapturedOutput::new("dx serve", std::mem::take(&mut self.tail)),
+        }
+        .into()
+    }
+}
+
+/// Where a dx session stands.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DxState {
+    /// Waiting for the payload's ready marker.
+    Starting,
+    /// The payload runs and can be patched.
+    Ready,
+    /// A source change was written; waiting for the payload to print
+    /// `expected`.
+    PatchPending {
+        expected: String,
+        /// The ready marker appeared again, so the payload restarted.
+        restarted: bool,
+        /// dx reported applying a patch.
+        patch_logged: bool,
+    },
+    /// The last patch showed up and the payload can be patched again.
+    Patched {
+        /// Why the new value cannot be attributed to a hot patch, if it
+        /// cannot.
+        problem: Option<String>,
+        /// Frame statistics of the patch, see [`HotpatchOutcome::metrics`].
+        metrics: BTreeMap<String, f64>,
+    },
+    /// dx exited, timed out, or stalled; the session is over.
+    Failed(String),
+}
+
+impl DxState {
+    pub fn accepts_patch(&self) -> bool {
+        matches!(self, Self::Ready | Self::Patched { .. })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::Ready => "ready",
+            Self::PatchPending { .. } => "waiting for a patch",
+            Self::Patched { .. } => "patched",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+/// The transitions of a dx session, driven by one [`StreamEvent`] at a time
+/// and independent of any process, so they can be tested on their own.
+#[derive(Debug)]
+pub struct DxMachine {
+    state: DxState,
+    ready_marker: String,
+    first_frame_ms: Option<f64>,
+    /// Frame times since the session started or the last patch showed up.
+    frames: Vec<f64>,
+    /// Frame times since the pending patch was triggered.
+    patch_frames: Vec<f64>,
+    patches: usize,
+}
+
+impl DxMachine {
+    pub fn new(ready_marker: impl Into<String>) -> Self {
+        Self {
+            state: DxState::Starting,
+            ready_marker: ready_marker.into(),
+            first_frame_ms: None,
+            frames: Vec::new(),
+            patch_frames: Vec::new(),
+            patches: 0,
+        }
+    }
+
+    pub fn state(&self) -> &DxState {
+        &self.state
+    }
+
+    pub fn on_event(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::Line(_, line) => self.on_line(line),
+            StreamEvent::FrameTime(milliseconds) => {
+                self.frames.push(*milliseconds);
+                if matches!(self.state, DxState::PatchPending { .. }) {
+                    self.patch_frames.push(*milliseconds);
+                }
+            }
+            // Whether dx is gone depends on the process, not on its pipes.
+            StreamEvent::Closed(_) => {}
+        }
+    }
+
+    /// A source change was written; `expected` is what the patched payload
+    /// prints. Only valid when [`DxState::accepts_patch`].
+    pub fn begin_patch(&mut self, expected: String) {
+        debug_assert!(self.state.accepts_patch(), "patch in {:?}", self.state);
+        self.patch_frames.clear();
+        self.state = DxState::PatchPending {
+            expected,
+            restarted: false,
+            patch_logged: false,
+        };
+    }
+
+    pub fn fail(&mut self, reason: impl Into<String>) {
+        self.state = DxState::Failed(reason.into());
+    }
+
+    fn on_line(&mut self, line: &str) {
+        let line = &*capture::normalize(line);
+        if let Some(milliseconds) = first_frame::parse(line) {
+            self.first_frame_ms.get_or_insert(milliseconds);
+        }
+        match &mut self.state {
+            DxState::Starting if is_ready_line(line, &self.ready_marker) => {
+                self.state = DxState::Ready;
+            }
+            DxState::PatchPending {
+                expected,
+                restarted,
+                patch_logged,
+            } => {
+                *restarted |= is_ready_line(line, &self.ready_marker);
+                *patch_logged |= is_dx_patch_line(line);
+                if !line.contains(expected.as_str()) {
+                    return;
+                }
+                let problem = if *restarted {
+                    Some("hotpatch not verified: the payload restarted".to_string())
+                } else if !*patch_logged {
+                    Some("hotpatch not verified: dx did not report a patch".to_string())
+                } else {
+                    None
+                };
+                // Only the first patch follows the payload's first frame.
+                let first_frame_ms = self.first_frame_ms.filter(|_| self.patches == 0);
+                let metrics = frame_metrics(first_frame_ms, &self.frames, &self.patch_frames);
+                self.frames.clear();
+                self.patches += 1;
+                self.state = DxState::Patched { problem, metrics };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects what the other pipe still has after the process exited, so the
+/// error shows its last words. Bounded, since a payload launched by dx can
+/// keep the pipes open after dx itself is gone.
+fn drain_output(
+    rx: &Receiver<StreamEvent>,
+    tail: &mut OutputTail,
+    session_start: Instant,
+    mut recording: Option<&mut Recording>,
+) {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+        match rx.recv_timeout(timeout) {
+            Ok(StreamEvent::Line(kind, line)) => {
+                runner::forward_stream_line("dx", kind, &line);
+                if let Some(recording) = recording.as_deref_mut() {
+                    recording.push(session_start.elapsed(), kind, &line);
+                }
+                tail.push(kind, line);
+            }
+            Ok(StreamEvent::Closed(_) | StreamEvent::FrameTime(_)) => {}
+            Err(_) => break,
+        }
+    }
+}
+
+fn frame_metrics(
+    first_frame_ms: Option<f64>,
+    frames: &[f64],
+    patch_frames: &[f64],
+) -> BTreeMap<String, f64> {
+    let mut metrics = BTreeMap::new();
+    if let Some(milliseconds) = first_frame_ms {
+        metrics.insert("first_frame_ms".to_string(), milliseconds);
+    }
+    if !frames.is_empty() {
+        let mut sorted = frames.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        metrics.insert("frame_ms_median".to_string(), sorted[sorted.len() / 2]);
+    }
+    if let Some(longest) = patch_frames.iter().copied().reduce(f64::max) {
+        metrics.insert("patch_frame_ms_max".to_string(), longest);
+    }
+    metrics
+}
+
+/// Sends each complete line appended to a file as a marker event, until
+/// dropped. The file does not have to exist yet.
+struct Follower {
+    stop: Arc<AtomicBool>,
+}
+
+impl Follower {
+    fn start(path: PathBuf, tx: Sender<StreamEvent>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || {
+            let mut reader = None;
+            let mut pending = Vec::new();
+            while !stopped.load(Ordering::SeqCst) {
+                if reader.is_none() {
+                    reader = File::open(&path).ok().map(BufReader::new);
+                }
+                let Some(file) = reader.as_mut() else {
+                    thread::sleep(MARKER_POLL);
+                    continue;
+                };
+                // `read_until` returns partial lines at the end of the file;
+                // they stay pending until the payload finishes writing them.
+                match file.read_until(b'\n', &mut pending) {
+                    Ok(0) | Err(_) => thread::sleep(MARKER_POLL),
+                    Ok(_) if pending.ends_with(b"\n") => {
+                        let line = capture::sanitize(&pending).trim_end().to_string();
+                        pending.clear();
+                        if tx
+                            .send(StreamEvent::Line(StreamKind::Marker, line))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                }
+            }
+        });
+        Self { stop }
+    }
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Whether `line` is the payload announcing that it started: the ready
+/// marker as the last word, after whatever prefix dx gives app output. The
+/// heartbeat embeds the marker in a longer word, so it does not count.
+fn is_ready_line(line: &str, ready_marker: &str) -> bool {
+    line.split_whitespace().next_back() == Some(ready_marker)
+}
+
+/// Whether `line` is dx reporting that it applied a patch, as opposed to
+/// rebuilding and relaunching the app. dx logs `Hot-patching: <files> took
+/// <n>ms` for that; slugs and package names are lowercase, so the payload's
+/// own output never matches.
+fn is_dx_patch_line(line: &str) -> bool {
+    line.contains("Hot-patching: ") && !line.contains("failed")
+}
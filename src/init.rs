@@ -0,0 +1,114 @@
+//! `bench init`: a commented starter configuration for a team repository.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+use crate::cli::InitArgs;
+use crate::config::DEFAULT_CONFIG;
+
+const WORKFLOW: &str = ".github/workflows/bevy-build-bench.yml";
+
+const STARTER_CONFIG: &str = r#"# Configuration for the Bevy build benchmarks, read from `bench.toml` in the
+# current directory or the file passed with `--config`.
+#
+# Which scenarios run is chosen on the command line, so a team can keep a few
+# shell aliases as presets:
+#
+#   bench --linkers default,rust-lld                     # quick linker check
+#   bench --hotpatch --dynamics dynamic-linking          # dx hotpatch times
+#   bench --caches sccache --iterations 5                # cache comparison
+#   bench --design fractional --budget 1h                # broad overnight run
+#   bench list                                           # preview a selection
+#
+# Results are written to `results/run-<timestamp>.ron`; compare two runs with
+# `bench diff` and check one against the budgets below with `bench gate`.
+
+# Compile-time budgets for `bench gate`. Durations accept ms, s, m, and h
+# units; a bare number is seconds.
+[gate]
+max_clean = "15m"
+max_second = "1m"
+max_modified = "1m"
+# max_hotpatch = "10s"
+
+# Budgets for a single scenario take precedence over the ones above.
+# [gate.slugs."rust-lld-incremental-default-dynamic-no-hotpatch"]
+# max_second = "10s"
+
+# Extra axes crossed with the built-in matrix. Each value can set `env`,
+# `rustflags`, `profile` keys, Bevy `features`, and `tags` for --tags and
+# --skip-tags.
+# [[axis]]
+# name = "codegen-units"
+# values = [
+#   { name = "cgu-default" },
+#   { name = "cgu-256", rustflags = ["-Ccodegen-units=256"], profile = { codegen-units = 256 }, tags = ["slow"] },
+# ]
+
+# Shell commands run in each scenario workspace, outside the timed sections.
+# [hooks]
+# before_scenario = "echo starting $BENCH_SLUG"
+# after_build = "du -sh target"
+
+# Benchmark a local Bevy checkout instead of the published crates.
+# [patch.crates-io]
+# bevy = { path = "../bevy" }
+"#;
+
+const STARTER_WORKFLOW: &str = r#"# Runs the Bevy build benchmarks and fails when a phase exceeds the budgets
+# in bench.toml.
+name: Bevy build benchmarks
+
+on:
+  workflow_dispatch:
+  schedule:
+    - cron: "0 3 * * 1"
+
+jobs:
+  bench:
+    runs-on: ubuntu-latest
+    timeout-minutes: 180
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@nightly
+      - name: Install the harness
+        run: cargo install --locked --git https://github.com/lizelive/bevy-build-test sample
+      - name: Benchmark
+        run: sample --linkers default,rust-lld
+      - name: Check budgets
+        run: sample gate "$(ls -t results/run-*.ron | head -n 1)"
+      - uses: actions/upload-artifact@v4
+        if: always()
+        with:
+          name: bench-results
+          path: results/
+"#;
+
+pub fn run(args: &InitArgs) -> Result<()> {
+    write(Path::new(DEFAULT_CONFIG), STARTER_CONFIG, args.force)?;
+    if args.github_actions {
+        write(Path::new(WORKFLOW), STARTER_WORKFLOW, args.force)?;
+    }
+    println!("Edit the budgets in {DEFAULT_CONFIG}, then preview the matrix with `bench list`.");
+    Ok(())
+}
+
+fn write(path: &Path, contents: &str, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Bytes the spawned tools read from and wrote to storage. With incremental
+//! builds or full debuginfo a build on a slow disk can spend most of its time
+//! in I/O, which its wall time alone does not show.
+//!
+//! Linux reads the harness's own `/proc/self/io`, which includes every child
+//! the harness (or one of its children) has waited for. Windows adds up the
+//! accounting of each tool's Job Object as it closes. Elsewhere, and for
+//! builds on a remote host, nothing is recorded.
+
+use std::collections::BTreeMap;
+#[cfg(windows)]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(windows)]
+static READ_BYTES: AtomicU64 = AtomicU64::new(0);
+#[cfg(windows)]
+static WRITE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IoCounters {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+impl IoCounters {
+    /// Totals for the tools that finished so far, if the platform has them.
+    pub(crate) fn snapshot() -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let io = std::fs::read_to_string("/proc/self/io").ok()?;
+            let field = |name: &str| {
+                io.lines()
+                    .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                    .and_then(|value| value.trim().parse().ok())
+            };
+            Some(Self {
+                read_bytes: field("read_bytes")?,
+                write_bytes: field("write_bytes")?,
+            })
+        }
+        #[cfg(windows)]
+        {
+            Some(Self {
+                read_bytes: READ_BYTES.load(Ordering::SeqCst),
+                write_bytes: WRITE_BYTES.load(Ordering::SeqCst),
+            })
+        }
+        #[cfg(not(any(target_os = "linux", windows)))]
+        {
+            None
+        }
+    }
+
+    /// What happened between `earlier` and `self`, as phase metrics.
+    pub(crate) fn metrics_since(self, earlier: Self) -> BTreeMap<String, f64> {
+        BTreeMap::from([
+            (
+                "io_read_bytes".to_string(),
+                self.read_bytes.saturating_sub(earlier.read_bytes) as f64,
+            ),
+            (
+                "io_write_bytes".to_string(),
+                self.write_bytes.saturating_sub(earlier.write_bytes) as f64,
+            ),
+        ])
+    }
+}
+
+/// Adds a finished Job Object's transfer counts to the totals.
+#[cfg(windows)]
+pub(crate) fn record(read_bytes: u64, write_bytes: u64) {
+    READ_BYTES.fetch_add(read_bytes, Ordering::SeqCst);
+    WRITE_BYTES.fetch_add(write_bytes, Ordering::SeqCst);
+}
@@ -0,0 +1,4289 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+mod allocation;
+mod analysis;
+mod axes;
+mod bisect;
+mod bloat;
+mod builder;
+mod calibration;
+mod capture;
+pub mod cli;
+mod community;
+mod config;
+mod constraint;
+mod crate_timings;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod diagnostics;
+mod diff;
+mod disk;
+mod dry_run;
+pub mod error;
+mod examples;
+mod explain;
+mod feature_delta;
+mod filesystem;
+mod fingerprint;
+mod first_frame;
+mod gate;
+mod history;
+mod hooks;
+pub mod hotpatch;
+mod init;
+mod io_counters;
+pub mod link;
+mod list;
+mod llvm_lines;
+mod memory_limit;
+mod metadata;
+pub mod mutation;
+mod outliers;
+mod output;
+mod pareto;
+mod patch;
+mod phase;
+mod pin;
+mod port;
+mod process_tree;
+mod project;
+pub mod protocol;
+pub mod recording;
+mod redact;
+mod relocate;
+mod remote;
+mod report;
+pub mod runner;
+mod sccache;
+mod selection;
+mod slug;
+mod snapshot;
+mod stats;
+mod tail;
+mod timeline;
+mod wasm;
+pub mod watchdog;
+pub mod wrapper;
+
+use axes::{AxisChoice, CustomAxis};
+use bloat::Bloat;
+pub use builder::ScenarioBuilder;
+use calibration::Calibration;
+use cli::{Cli, Commands, RunArgs};
+use config::Config;
+use constraint::Constraint;
+use crate_timings::CrateTimings;
+use disk::DiskUsage;
+use error::BenchError;
+use filesystem::{DefenderPair, WorkspaceRoot};
+use fingerprint::Environment;
+use history::{History, RunForecast};
+use hooks::Hooks;
+use hotpatch::{DxSession, HotpatchOutcome, MarkerSource, ReadyChannel};
+use io_counters::IoCounters;
+use llvm_lines::LlvmLines;
+use metadata::RunMetadata;
+use mutation::{EditTarget, Mutation, PayloadEdit};
+use outliers::Outlier;
+use output::OutputDir;
+use patch::Patches;
+use phase::{Phase, PhaseRecord, PhaseResult};
+use pin::Pins;
+use recording::{Recording, Verdict};
+use remote::Host;
+use runner::{CommandRunner, SystemRunner};
+use sccache::{CacheGrowth, CompilerCache, SccacheServer, SccacheStats};
+use selection::Design;
+use slug::SlugScheme;
+use stats::PhaseStatistics;
+use timeline::PhaseMarker;
+use wasm::WasmSizes;
+use wrapper::RustcWrapper;
+
+const RESULTS_DIR: &str = "results";
+/// Address the `dx serve` dev server listens on; the port is chosen per
+/// session.
+const DX_ADDRESS: &str = "127.0.0.1";
+/// Sessions started before a port conflict fails the hotpatch phase.
+const DX_PORT_ATTEMPTS: usize = 3;
+/// When set, the payload appends its ready marker and value lines to this
+/// file instead of printing them (`--ready-channel file`).
+const MARKER_FILE_ENV: &str = "BENCH_MARKER_FILE";
+/// Published Bevy release the payload depends on.
+const BEVY_VERSION: &str = "0.17.2";
+/// Default features of [`BEVY_VERSION`] apart from the windowing backends, so
+/// a scenario can turn the defaults off and pick one backend.
+const BEVY_DEFAULT_FEATURES_WITHOUT_BACKENDS: &[&str] = &[
+    "std",
+    "async_executor",
+    "android-game-activity",
+    "android_shared_stdcxx",
+    "animation",
+    "bevy_asset",
+    "bevy_audio",
+    "bevy_color",
+    "bevy_core_pipeline",
+    "bevy_post_process",
+    "bevy_anti_alias",
+    "bevy_gilrs",
+    "bevy_gizmos",
+    "bevy_gltf",
+    "bevy_input_focus",
+    "bevy_log",
+    "bevy_mesh_picking_backend",
+    "bevy_pbr",
+    "bevy_picking",
+    "bevy_render",
+    "bevy_scene",
+    "bevy_image",
+    "bevy_mesh",
+    "bevy_camera",
+    "bevy_light",
+    "bevy_shader",
+    "bevy_sprite",
+    "bevy_sprite_picking_backend",
+    "bevy_sprite_render",
+    "bevy_state",
+    "bevy_text",
+    "bevy_ui",
+    "bevy_ui_picking_backend",
+    "bevy_ui_render",
+    "bevy_window",
+    "bevy_winit",
+    "custom_cursor",
+    "default_font",
+    "hdr",
+    "ktx2",
+    "multi_threaded",
+    "png",
+    "reflect_auto_register",
+    "smaa_luts",
+    "sysinfo_plugin",
+    "tonemapping_luts",
+    "vorbis",
+    "webgl2",
+    "debug",
+    "zstd_rust",
+];
+
+/// One combination of build settings to benchmark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Scenario {
+    pub linker: Option<Linker>,
+    pub cache: Option<Cache>,
+    pub dynamic: Option<Dynamic>,
+    pub hotpatching: Option<Hotpatching>,
+    /// Cross-compilation target; `None` builds for the host.
+    #[serde(default)]
+    pub target: Option<Target>,
+    #[serde(default)]
+    pub channel: Channel,
+    #[serde(default)]
+    pub build_std: Option<BuildStd>,
+    /// Bevy's tracing instrumentation compiled in.
+    #[serde(default)]
+    pub trace: Option<Trace>,
+    /// Linux windowing backend; `None` keeps Bevy's default of both.
+    #[serde(default)]
+    pub window_backend: Option<WindowBackend>,
+    /// crates.io index protocol, fetched into an empty `CARGO_HOME`; `None`
+    /// uses the shared `CARGO_HOME` and skips the fetch phase.
+    #[serde(default)]
+    pub registry: Option<Registry>,
+    /// Dependency resolver version; `None` is the edition's default.
+    #[serde(default)]
+    pub resolver: Option<Resolver>,
+    /// Cargo profile the payload builds with; `None` is `dev`.
+    #[serde(default)]
+    pub profile: Option<Profile>,
+    /// `debug-assertions` for the payload and its dependencies; `None` keeps
+    /// the profile's default.
+    #[serde(default)]
+    pub debug_assertions: Option<DebugAssertions>,
+    /// The compiler cache is filled by an untimed build of the same scenario
+    /// before the measured ones, instead of starting empty.
+    #[serde(default)]
+    pub warm_cache: bool,
+}
+
+/// Release channel of the toolchain the payload is built with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Channel {
+    #[default]
+    Nightly,
+    Stable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Linker {
+    RustLld,
+    /// Apple's previous linker, selected with `-ld_classic` (macOS only).
+    LdClassic,
+    /// LLVM's `ld64.lld`, selected with `-fuse-ld=lld` (macOS only).
+    MacLld,
+    /// `zig cc` as the linker, through `cargo zigbuild`.
+    Zig,
+    /// GNU gold, selected with `-fuse-ld=gold` (Linux only).
+    Gold,
+}
+
+impl Linker {
+    /// The linker flag that selects this linker, for linkers chosen through
+    /// the system linker driver rather than a `linker` setting.
+    fn link_arg(self) -> Option<&'static str> {
+        match self {
+            Self::RustLld | Self::Zig => None,
+            Self::LdClassic => Some("-Clink-arg=-ld_classic"),
+            Self::MacLld => Some("-Clink-arg=-fuse-ld=lld"),
+            Self::Gold => Some("-Clink-arg=-fuse-ld=gold"),
+        }
+    }
+
+    /// Whether this linker only exists on macOS hosts.
+    pub fn macos_only(self) -> bool {
+        matches!(self, Self::LdClassic | Self::MacLld)
+    }
+
+    /// Cargo subcommand that builds with this linker.
+    fn build_subcommand(linker: Option<Self>) -> &'static str {
+        match linker {
+            // cargo-zigbuild points cargo at its `zig cc` wrapper for the
+            // target and otherwise behaves like `cargo build`.
+            Some(Self::Zig) => "zigbuild",
+            _ => "build",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Cache {
+    DisableIncremental,
+    Sscache,
+    /// The `RUSTC_WRAPPER` from the config file's `[wrapper]`.
+    Wrapper,
+    /// cachepot, an sccache fork.
+    Cachepot,
+}
+
+impl Cache {
+    /// The compiler cache server this value runs builds through.
+    fn server(self) -> Option<CompilerCache> {
+        match self {
+            Self::Sscache => Some(CompilerCache::Sccache),
+            Self::Cachepot => Some(CompilerCache::Cachepot),
+            Self::DisableIncremental | Self::Wrapper => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Dynamic {
+    DynamicLinking,
+    ShareGenerics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Hotpatching {
+    Dx,
+}
+
+/// Rebuilds the standard library from source with `-Zbuild-std`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BuildStd {
+    Std,
+}
+
+/// Bevy's tracing instrumentation, enabled through its features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Trace {
+    /// The `trace` feature: spans for systems and schedules.
+    #[value(name = "trace")]
+    Trace,
+    /// The `trace_tracy` feature, which also builds the Tracy client.
+    #[value(name = "tracy")]
+    Tracy,
+}
+
+/// A single windowing backend for winit on Linux, instead of both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum WindowBackend {
+    #[value(name = "x11")]
+    X11,
+    #[value(name = "wayland")]
+    Wayland,
+}
+
+impl WindowBackend {
+    fn label(self) -> &'static str {
+        match self {
+            Self::X11 => "x11",
+            Self::Wayland => "wayland",
+        }
+    }
+}
+
+/// Protocol cargo uses to read the crates.io index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Registry {
+    /// Index files fetched over HTTP as needed.
+    #[value(name = "sparse")]
+    Sparse,
+    /// A clone of the whole index repository.
+    #[value(name = "git")]
+    Git,
+}
+
+impl Registry {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Sparse => "sparse",
+            Self::Git => "git",
+        }
+    }
+}
+
+/// What the `clean` phase starts from (`--clean-mode`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum CleanMode {
+    /// A fresh workspace with an empty target directory: a full cold build.
+    #[default]
+    #[value(name = "fresh")]
+    Fresh,
+    /// Dependencies built by an untimed build, then `cargo clean -p` of the
+    /// payload: a rebuild of the leaf crate only.
+    #[value(name = "payload")]
+    Payload,
+}
+
+/// A Cargo profile other than `dev`, defined in the payload's manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Profile {
+    /// `release` with ThinLTO and incremental compilation, whose cache lets
+    /// rustc reuse optimized and LTO'd modules that did not change.
+    #[value(name = "thin-lto")]
+    ThinLto,
+}
+
+impl Profile {
+    fn label(self) -> &'static str {
+        match self {
+            Self::ThinLto => "thin-lto",
+        }
+    }
+
+    /// Keys of the profile's section, before custom axes.
+    fn keys(self) -> BTreeMap<&'static str, toml::Value> {
+        match self {
+            Self::ThinLto => BTreeMap::from([
+                ("inherits", toml::Value::from("release")),
+                ("lto", toml::Value::from("thin")),
+                ("incremental", toml::Value::from(true)),
+            ]),
+        }
+    }
+}
+
+/// Where `debug-assertions` is enabled: in the payload crate, in its
+/// dependencies (Bevy's own assertions), or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum DebugAssertions {
+    #[value(name = "on")]
+    On,
+    #[value(name = "off")]
+    Off,
+    /// On in the payload, off in dependencies.
+    #[value(name = "payload-only")]
+    PayloadOnly,
+    /// Off in the payload, on in dependencies.
+    #[value(name = "deps-only")]
+    DepsOnly,
+}
+
+impl DebugAssertions {
+    fn label(self) -> &'static str {
+        match self {
+            Self::On => "on",
+            Self::Off => "off",
+            Self::PayloadOnly => "payload-only",
+            Self::DepsOnly => "deps-only",
+        }
+    }
+
+    /// The setting for the payload and for its dependencies.
+    fn settings(self) -> (bool, bool) {
+        match self {
+            Self::On => (true, true),
+            Self::Off => (false, false),
+            Self::PayloadOnly => (true, false),
+            Self::DepsOnly => (false, true),
+        }
+    }
+}
+
+/// Cargo's dependency resolver version (`package.resolver`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Resolver {
+    /// Unifies features across build scripts, proc macros, and targets.
+    #[value(name = "1")]
+    V1,
+    #[value(name = "2")]
+    V2,
+    /// Version 2 plus `rust-version`-aware version selection.
+    #[value(name = "3")]
+    V3,
+}
+
+impl Resolver {
+    fn label(self) -> &'static str {
+        match self {
+            Self::V1 => "1",
+            Self::V2 => "2",
+            Self::V3 => "3",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Target {
+    #[value(name = "aarch64-unknown-linux-gnu")]
+    Aarch64LinuxGnu,
+    /// Compared against musl to show the cost of static linking.
+    #[value(name = "x86_64-unknown-linux-gnu")]
+    X86_64LinuxGnu,
+    /// Statically linked against musl libc.
+    #[value(name = "x86_64-unknown-linux-musl")]
+    X86_64LinuxMusl,
+    /// Built from any host through `cargo xwin`.
+    #[value(name = "x86_64-pc-windows-msvc")]
+    X86_64WindowsMsvc,
+    /// The payload is built as a `cdylib` through `cargo ndk`.
+    #[value(name = "aarch64-linux-android")]
+    Aarch64Android,
+    /// Also measures artifact size before and after `wasm-opt`.
+    #[value(name = "wasm32-unknown-unknown")]
+    Wasm32,
+}
+
+/// Values each axis takes in the cross-product.
+#[derive(Debug, Clone)]
+struct Matrix {
+    linkers: Vec<Option<Linker>>,
+    caches: Vec<Option<Cache>>,
+    dynamics: Vec<Option<Dynamic>>,
+    hotpatches: Vec<Option<Hotpatching>>,
+    targets: Vec<Option<Target>>,
+    build_stds: Vec<Option<BuildStd>>,
+    traces: Vec<Option<Trace>>,
+    window_backends: Vec<Option<WindowBackend>>,
+    registries: Vec<Option<Registry>>,
+    resolvers: Vec<Option<Resolver>>,
+    profiles: Vec<Option<Profile>>,
+    debug_assertions: Vec<Option<DebugAssertions>>,
+    /// `[false]`, or `[false, true]` with `--warm-cache`.
+    warm_caches: Vec<bool>,
+    /// Toolchains from `--toolchains`; `None` builds with the channel.
+    toolchains: Vec<Option<String>>,
+    /// Axes declared in the config file, crossed with the built-in ones.
+    custom: Vec<CustomAxis>,
+    /// `constraints` from the config file; a scenario must satisfy all.
+    constraints: Vec<Constraint>,
+    /// Directories workspaces are created in; `None` is the system temp dir.
+    roots: Vec<Option<WorkspaceRoot>>,
+    /// Dependency overrides from the config file, the same for every scenario.
+    patches: Patches,
+    /// `[cargo_config]` from the config file, under every generated
+    /// `.cargo/config.toml`.
+    cargo_config: toml::Table,
+    /// The payload edit of every scenario (`--mutation`).
+    mutation: Mutation,
+    edit_target: EditTarget,
+    /// Keep only scenarios with one of these tags (`--tags`); empty keeps all.
+    tags: Vec<String>,
+    /// Drop scenarios with any of these tags (`--skip-tags`).
+    skip_tags: Vec<String>,
+    slug_scheme: SlugScheme,
+}
+
+#[derive(Debug, Clone)]
+struct Code {
+    pub cargo_config_toml: String,
+    /// Path of the payload source relative to the workspace root.
+    pub payload_file: &'static str,
+    pub src_main_rs: String,
+    /// Sources that edits leave alone, by path, when the payload source is
+    /// not the crate root (`--edit-in`).
+    pub entry_files: Vec<(&'static str, String)>,
+    pub cargo_toml: String,
+    pub rust_toolchain_toml: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ScenarioTimings {
+    /// Measured phases in the order they ran.
+    phases: Vec<PhaseResult>,
+    /// Whether the hotpatch phase was confirmed to be a real patch rather
+    /// than a restart of the payload.
+    hotpatch_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+struct PreparedScenario {
+    scenario: Scenario,
+    /// Toolchain from `--toolchains`; `None` uses the scenario's channel.
+    toolchain: Option<String>,
+    /// Values picked for the config file's custom axes.
+    custom: Vec<AxisChoice>,
+    /// Where the workspace is created; `None` for the system temp dir.
+    root: Option<WorkspaceRoot>,
+    slug: String,
+    /// The slug under [`SlugScheme::Full`], when it differs from `slug`.
+    full_slug: Option<String>,
+    ready_marker: String,
+    payload_value: u64,
+    /// How the payload is edited between builds (`--mutation`).
+    mutation: Mutation,
+    /// Which file of the payload the edit lands in (`--edit-in`).
+    edit_target: EditTarget,
+    code: Code,
+    /// Set once the environment has been probed; see `fingerprint`.
+    environment_hash: Option<String>,
+}
+
+#[derive(Debug)]
+struct ScenarioResult {
+    slug: String,
+    timings: ScenarioTimings,
+    lockfile_hash: String,
+    invalid: Option<String>,
+    sccache: Option<SccacheStats>,
+    wasm: Option<WasmSizes>,
+    disk: Option<DiskUsage>,
+    /// System load average when the iteration started, where available.
+    load_average: Option<f64>,
+    /// The clean build reused dependency artifacts from an earlier scenario.
+    warm_deps: bool,
+    /// The clean build was skipped for a `--restore-target` snapshot.
+    restored_target: bool,
+    clean_mode: CleanMode,
+    nice: Option<i32>,
+    /// Filesystem of the workspace, for local runs.
+    filesystem: Option<String>,
+    /// Whether Windows Defender real-time scanning covered the workspace.
+    defender_scanned: Option<bool>,
+    bloat: Option<Bloat>,
+    llvm_lines: Option<LlvmLines>,
+    crate_timings: Option<CrateTimings>,
+}
+
+/// All iterations of one scenario plus the outliers found among them.
+#[derive(Debug)]
+struct ScenarioRun {
+    results: Vec<ScenarioResult>,
+    outliers: Vec<Outlier>,
+}
+
+impl ScenarioRun {
+    /// Median seconds of `phase` across the iterations, if it was measured.
+    fn phase_seconds(&self, phase: &str) -> Option<f64> {
+        let values: Vec<f64> = self
+            .results
+            .iter()
+            .filter_map(|result| ScenarioTimingRecord::from_timings(&result.timings).phase(phase))
+            .collect();
+        (!values.is_empty()).then(|| stats::median(&values))
+    }
+
+    /// Largest coefficient of variation of any phase across the iterations;
+    /// zero with a single iteration.
+    fn spread(&self) -> f64 {
+        let samples: Vec<ScenarioTimingRecord> = self
+            .results
+            .iter()
+            .map(|result| ScenarioTimingRecord::from_timings(&result.timings))
+            .collect();
+        let Some(first) = self.results.first() else {
+            return 0.0;
+        };
+        first
+            .timings
+            .phases
+            .iter()
+            .filter_map(|phase| {
+                let values: Vec<f64> = samples
+                    .iter()
+                    .filter_map(|sample| sample.phase(phase.phase.name()))
+                    .collect();
+                stats::coefficient_of_variation(&values)
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Hotpatch rounds of a scenario's `dx serve` session: how many a new
+/// session measures, and those it measured ahead for later iterations.
+#[derive(Debug, Default)]
+struct DxRounds {
+    wanted: u32,
+    measured: VecDeque<HotpatchOutcome>,
+}
+
+/// Settings shared by every scenario of a run.
+#[derive(Debug)]
+struct RunContext {
+    host: Host,
+    runner: Box<dyn CommandRunner>,
+    hooks: Hooks,
+    /// Reuse target directories across scenarios with identical compile
+    /// settings (`--share-deps`).
+    share_deps: bool,
+    /// Number of instantiations to keep from `cargo llvm-lines`, if enabled.
+    llvm_lines: Option<usize>,
+    /// Number of crates and functions to keep from `cargo bloat`, if enabled.
+    bloat: Option<usize>,
+    /// Bevy crates to report from the clean build's `--timings`, if enabled.
+    crate_timings: Option<usize>,
+    /// Directory to save `dx serve` recordings in (`--record-dx`).
+    record_dx: Option<PathBuf>,
+    ready_channel: ReadyChannel,
+    /// How long each hotpatch gets to show up (`--patch-timeout`).
+    patch_timeout: Duration,
+    /// Time the payload's first frame after the builds (`--first-frame`).
+    first_frame: bool,
+    /// Time a rebuild in a relocated copy of the workspace (`--relocate`).
+    relocate: bool,
+    /// Time the linker invocations of every build (`--link-time`).
+    link_time: bool,
+    /// `bench run <slug> --verbose`: see [`Workspace::verbose`].
+    verbose: bool,
+    /// `[wrapper]` from the config file, for the `wrapper` cache value.
+    wrapper: Option<RustcWrapper>,
+    clean_mode: CleanMode,
+    /// Niceness the tools were started with (`--nice`).
+    nice: Option<i32>,
+    /// Where scenarios put their logs and reports; `None` for commands
+    /// without a run log.
+    output: Option<OutputDir>,
+    /// Versions set in every `Cargo.lock` (`--pin-deps`, `[pin]`).
+    pins: Pins,
+    /// Where clean builds' target directories are saved (`--save-target`).
+    save_target: Option<PathBuf>,
+    /// Where target directories are restored from (`--restore-target`).
+    restore_target: Option<PathBuf>,
+}
+
+impl RunContext {
+    fn new(args: &RunArgs, host: &Host, config: &Config, verbose: bool) -> Self {
+        // Lowering the priority of the ssh client would not help the remote
+        // machine.
+        let nice = args.nice.filter(|_| matches!(host, Host::Local));
+        if let Some(window) = args.stall_timeout {
+            watchdog::install(window, args.stall_action);
+        }
+        Self {
+            host: host.clone(),
+            runner: Box::new(nice.map(SystemRunner::niced).unwrap_or_default()),
+            hooks: config.hooks.clone(),
+            share_deps: args.share_deps,
+            llvm_lines: args.llvm_lines,
+            bloat: args.bloat,
+            crate_timings: args.crate_timings,
+            record_dx: args.record_dx.clone(),
+            ready_channel: args.ready_channel,
+            patch_timeout: args.patch_timeout,
+            first_frame: args.first_frame,
+            relocate: args.relocate,
+            link_time: args.link_time,
+            verbose,
+            wrapper: config.wrapper.clone(),
+            clean_mode: args.clean_mode,
+            nice,
+            output: None,
+            pins: Pins::new(args.pin_deps, &config.pin),
+            save_target: args.save_target.clone(),
+            restore_target: args.restore_target.clone(),
+        }
+    }
+}
+
+/// Remembers the `Cargo.lock` produced before the first build so every later
+/// phase can prove it compiled the exact same dependency graph.
+#[derive(Debug)]
+struct LockfileGuard {
+    hash: String,
+    changed_in: Option<String>,
+}
+
+#[derive(Debug)]
+struct Workspace {
+    dir: TempDir,
+    payload_file: &'static str,
+    host: Host,
+    /// Directory commands run in: the temp dir itself, or its mirror on a
+    /// remote host.
+    exec_dir: String,
+    /// Cargo target directory: inside the workspace, or a shared directory
+    /// with `--share-deps`.
+    target_dir: String,
+    env: Vec<(String, String)>,
+    /// Environment of the harness's own cargo invocations only, not of
+    /// tools such as dx that run cargo themselves.
+    cargo_env: Vec<(String, String)>,
+    /// Builds print cargo's full output and the workspace is kept after the
+    /// scenario instead of being removed.
+    verbose: bool,
+    /// The scenario's directory in the run's [`OutputDir`], if any.
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct RunWriter {
+    path: PathBuf,
+    record: RunRecord,
+    /// `results/<run-id>/`, next to the log.
+    output: OutputDir,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RunRecord {
+    #[serde(default = "metadata::legacy_schema_version")]
+    schema_version: u32,
+    run_id: String,
+    started_at: DateTime<Utc>,
+    #[serde(default)]
+    failed: bool,
+    /// Build time of the reference crate on this machine, if calibrated.
+    #[serde(default)]
+    calibration: Option<Calibration>,
+    #[serde(default)]
+    metadata: Option<RunMetadata>,
+    scenarios: Vec<ScenarioRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScenarioRecord {
+    slug: String,
+    /// Full slug when `--slug-scheme` shortened `slug`; `scenario` and
+    /// `custom_axes` hold the complete configuration either way.
+    #[serde(default)]
+    full_slug: Option<String>,
+    ready_marker: String,
+    payload_value: u64,
+    scenario: Scenario,
+    /// Custom axis name and value name pairs.
+    #[serde(default)]
+    custom_axes: Vec<(String, String)>,
+    timings: ScenarioTimingRecord,
+    #[serde(default)]
+    lockfile_hash: Option<String>,
+    #[serde(default)]
+    invalid: Option<String>,
+    #[serde(default)]
+    sccache: Option<SccacheStats>,
+    #[serde(default)]
+    wasm: Option<WasmSizes>,
+    /// Per-iteration timings; `timings` holds their per-phase medians.
+    #[serde(default)]
+    iterations: Vec<ScenarioTimingRecord>,
+    #[serde(default)]
+    statistics: Vec<PhaseStatistics>,
+    #[serde(default)]
+    outliers: Vec<Outlier>,
+    #[serde(default)]
+    environment_hash: Option<String>,
+    #[serde(default)]
+    disk: Option<DiskUsage>,
+    /// At least one iteration's clean build started with warm dependencies
+    /// from `--share-deps`, so it is not a true clean build.
+    #[serde(default)]
+    warm_deps: bool,
+    /// At least one iteration restored its target directory from a
+    /// `--restore-target` snapshot instead of running the clean build.
+    #[serde(default)]
+    restored_target: bool,
+    /// What the `clean` phase rebuilt (`--clean-mode`).
+    #[serde(default)]
+    clean_mode: CleanMode,
+    /// The payload edit the incremental and hotpatch phases made
+    /// (`--mutation`).
+    #[serde(default)]
+    mutation: Mutation,
+    /// Where in the payload the edit landed (`--edit-in`).
+    #[serde(default)]
+    edit_target: EditTarget,
+    /// Niceness the builds ran with (`--nice`); timings at a lower priority
+    /// are not comparable with normal ones on a busy machine.
+    #[serde(default)]
+    nice: Option<i32>,
+    /// `--memory-limit` the builds ran under.
+    #[serde(default)]
+    memory_limit: Option<u64>,
+    /// Name of the `--workspace-root` the scenario ran under.
+    #[serde(default)]
+    workspace_root: Option<String>,
+    /// Toolchain from `--toolchains` the scenario was built with.
+    #[serde(default)]
+    toolchain: Option<String>,
+    #[serde(default)]
+    filesystem: Option<String>,
+    #[serde(default)]
+    defender_scanned: Option<bool>,
+    #[serde(default)]
+    bloat: Option<Bloat>,
+    #[serde(default)]
+    llvm_lines: Option<LlvmLines>,
+    /// Per-crate breakdown of the first clean build (`--crate-timings`).
+    #[serde(default)]
+    crate_timings: Option<CrateTimings>,
+    /// Compiler cache size and hits summed over this run's scenarios so far,
+    /// for scenarios with a compiler cache.
+    #[serde(default)]
+    cache_growth: Option<CacheGrowth>,
+    error: Option<String>,
+    #[serde(default)]
+    failure: Option<FailureKind>,
+}
+
+/// Coarse category of a failed phase, derived from the error chain and the
+/// captured tool output so failures can be triaged without reading every log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+enum FailureKind {
+    ToolchainMissing,
+    LinkerNotFound,
+    OutOfMemory,
+    Timeout,
+    DxProtocolMismatch,
+    CompileError,
+    /// Stopped by SIGINT or SIGTERM rather than failing on its own.
+    Interrupted,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "TimingRecordFile")]
+struct ScenarioTimingRecord {
+    /// Measured phases in the order they ran.
+    phases: Vec<PhaseRecord>,
+    hotpatch_verified: Option<bool>,
+}
+
+/// Timings as found in results logs: named phases, or the fixed per-phase
+/// fields of logs written before phases were named.
+#[derive(Deserialize)]
+struct TimingRecordFile {
+    #[serde(default)]
+    phases: Vec<PhaseRecord>,
+    #[serde(default)]
+    first_seconds: Option<f64>,
+    #[serde(default)]
+    second_seconds: Option<f64>,
+    #[serde(default)]
+    modified_seconds: Option<f64>,
+    #[serde(default)]
+    hotpatch_seconds: Option<f64>,
+    #[serde(default)]
+    wasm_opt_seconds: Option<f64>,
+    #[serde(default)]
+    hotpatch_verified: Option<bool>,
+}
+
+impl From<TimingRecordFile> for ScenarioTimingRecord {
+    fn from(file: TimingRecordFile) -> Self {
+        let mut phases = file.phases;
+        if phases.is_empty() {
+            let legacy = [
+                (Phase::Clean, file.first_seconds),
+                (Phase::Second, file.second_seconds),
+                (Phase::WasmOpt, file.wasm_opt_seconds),
+                (Phase::Modified, file.modified_seconds),
+                (Phase::Hotpatch, file.hotpatch_seconds),
+            ];
+            phases = legacy
+                .into_iter()
+                .filter_map(|(phase, seconds)| Some(PhaseRecord::legacy(phase.name(), seconds?)))
+                .collect();
+        }
+        Self {
+            phases,
+            hotpatch_verified: file.hotpatch_verified,
+        }
+    }
+}
+
+/// Runs the command selected on the command line.
+pub fn dispatch(cli: &Cli) -> Result<()> {
+    timeline::start();
+    let config = Config::load(cli.config.as_deref())?;
+    redact::install(
+        &config.redact,
+        config.wrapper.iter().flat_map(|wrapper| &wrapper.env),
+    );
+    match &cli.command {
+        None => run(&cli.run, &config, None, false),
+        Some(Commands::Run(command)) => match &command.explain {
+            Some(slug) => explain::run(&command.args, &config, slug),
+            None => run(
+                &command.args,
+                &config,
+                command.slug.as_deref(),
+                command.verbose,
+            ),
+        },
+        Some(Commands::Gate(args)) => gate::run(args, &config),
+        Some(Commands::Diff(args)) => diff::run(args),
+        Some(Commands::Replay(args)) => recording::run(args),
+        Some(Commands::CompareCommunity(args)) => community::compare(args),
+        #[cfg(feature = "dashboard")]
+        Some(Commands::Dashboard(args)) => dashboard::run(args),
+        Some(Commands::List(args)) => list::run(args, &config),
+        Some(Commands::Completions(args)) => {
+            cli::print_completions(args.shell);
+            Ok(())
+        }
+        Some(Commands::Examples(args)) => examples::run(args, &config),
+        Some(Commands::Project(args)) => project::run(args, &config),
+        Some(Commands::FeatureDelta(args)) => feature_delta::run(args, &config),
+        Some(Commands::BisectBevy(args)) => bisect::bisect_bevy(args, &config),
+        Some(Commands::BisectToolchain(args)) => bisect::bisect_toolchain(args, &config),
+        Some(Commands::Init(args)) => init::run(args),
+    }
+}
+
+/// Runs the matrix selected by `args`, or only the scenario named `slug`.
+fn run(args: &RunArgs, config: &Config, slug: Option<&str>, verbose: bool) -> Result<()> {
+    let host = Host::from_remote(args.remote.as_deref(), &args.remote_dir);
+    if args.halving.is_some() && args.iterations < 2 {
+        bail!("--halving needs --iterations of at least 2 to give the finalists more runs");
+    }
+    if !matches!(host, Host::Local) && !args.workspace_roots.is_empty() {
+        bail!("--workspace-root is only supported for local runs");
+    }
+    if !matches!(host, Host::Local) && args.first_frame {
+        bail!("--first-frame is only supported for local runs");
+    }
+    if !matches!(host, Host::Local) && args.ready_channel != ReadyChannel::Stdout {
+        bail!("--ready-channel file and socket are only supported for local runs");
+    }
+    if args.memory_limit.is_some() && !matches!(host, Host::Local) {
+        bail!("--memory-limit is only supported for local runs");
+    }
+    if (args.save_target.is_some() || args.restore_target.is_some()) && !matches!(host, Host::Local)
+    {
+        bail!("--save-target and --restore-target are only supported for local runs");
+    }
+    // A dry run changes nothing on the machine.
+    if let Some(bytes) = args.memory_limit.filter(|_| !args.dry_run) {
+        memory_limit::install(bytes)?;
+        println!(
+            "[bench] Capping the memory of every build at {}",
+            format_bytes(bytes)
+        );
+    }
+    // Held for the whole run; dropping it removes the Defender exclusion.
+    let defender_pair = if args.defender_pair {
+        if !matches!(host, Host::Local) || !args.workspace_roots.is_empty() {
+            bail!("--defender-pair cannot be combined with --remote or --workspace-root");
+        }
+        (!args.dry_run).then(DefenderPair::create).transpose()?
+    } else {
+        None
+    };
+    let history = History::load(Path::new(RESULTS_DIR))?;
+    if args.nice.is_some() && !matches!(host, Host::Local) {
+        eprintln!("[bench][warn] --nice only lowers the priority of local builds");
+    }
+    // Probing starts the tools, which a dry run must not; without the
+    // hashes no cached result is skipped either.
+    let pins = Pins::new(args.pin_deps, &config.pin);
+    let environment =
+        (!args.dry_run).then(|| Environment::probe(&host, &config.patch, &pins, args));
+    if let Some(environment) = &environment {
+        println!("Environment: {}", environment.describe());
+    }
+    let mut matrix = Matrix::from_args(args, config);
+    if let Some(pair) = &defender_pair {
+        matrix.roots = pair.roots.iter().cloned().map(Some).collect();
+    }
+    let mut prepared = prepare_scenarios(&matrix)?;
+    if let Some(slug) = slug {
+        prepared.retain(|scenario| scenario.slug == slug);
+        if prepared.is_empty() {
+            bail!("no scenario named {slug} in the selected matrix; see `bench list`");
+        }
+    }
+    if args.design == Design::Fractional {
+        let total = prepared.len();
+        let axes = selection::fractional(&mut prepared);
+        println!(
+            "Fractional design: {} of {total} scenario(s), balanced over {axes} axes.",
+            prepared.len()
+        );
+    }
+    for scenario in &mut prepared {
+        scenario.environment_hash = environment
+            .as_ref()
+            .map(|environment| environment.scenario_hash(scenario));
+    }
+    if !args.force && slug.is_none() {
+        prepared.retain(|scenario| {
+            let hash = scenario.environment_hash.as_deref().unwrap_or_default();
+            match history.cached_result(hash) {
+                Some(path) => {
+                    println!(
+                        "Skipping {}: unchanged since {} (use --force to re-run).",
+                        scenario.slug,
+                        path.display()
+                    );
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    if let Some(count) = args.sample {
+        let total = prepared.len();
+        selection::sample(&mut prepared, count, args.seed);
+        println!(
+            "Sampled {} of {total} scenario(s) with seed {}.",
+            prepared.len(),
+            args.seed
+        );
+    }
+    history.schedule_fastest_first(&mut prepared);
+    if args.budget.is_some() {
+        selection::order_for_coverage(&mut prepared);
+    }
+    if args.dry_run {
+        return dry_run::report(&prepared, &history, args, config);
+    }
+    println!(
+        "Benchmarking {} scenario(s) on {}...",
+        prepared.len(),
+        host.describe()
+    );
+    history.report_forecast(&prepared, args.iterations);
+    let mut writer = RunWriter::create(RunMetadata::collect(args, config))?;
+    println!("Writing incremental results to {}", writer.path().display());
+    println!(
+        "Writing logs and reports to {}",
+        writer.output.path().display()
+    );
+    if !args.no_calibration {
+        println!("Calibrating machine with a reference build...");
+        match calibration::run(&host) {
+            Ok(calibration) => {
+                println!(
+                    "[bench] Calibration: {:.3}s (rust {})",
+                    calibration.seconds, calibration.toolchain
+                );
+                writer.set_calibration(calibration)?;
+            }
+            Err(err) => eprintln!("[bench][warn] Calibration failed: {err:#}"),
+        }
+    }
+    process_tree::forward_interrupts().context("failed to install signal handlers")?;
+    let mut failures: Vec<(String, FailureKind)> = Vec::new();
+    let context = RunContext {
+        output: Some(writer.output.clone()),
+        ..RunContext::new(args, &host, config, verbose)
+    };
+
+    // With `--halving`, every scenario first gets a single screening
+    // iteration, and with `--allocate` a pilot pass.
+    let first_round = if args.halving.is_some() {
+        1
+    } else if args.allocate {
+        allocation::PILOT_ITERATIONS
+    } else {
+        args.iterations
+    };
+    let mut screened: Vec<(&PreparedScenario, ScenarioRun, Duration)> = Vec::new();
+    let started = Instant::now();
+    let mut forecast = RunForecast::start(&history, &prepared, args.iterations);
+    for (index, scenario) in prepared.iter().enumerate() {
+        if let Some(budget) = args.budget {
+            let elapsed = started.elapsed();
+            if elapsed >= budget {
+                println!(
+                    "\n[bench] Time budget of {} used up; skipping the remaining {} scenario(s).",
+                    history::format_eta(budget),
+                    prepared.len() - index
+                );
+                break;
+            }
+            if let Some(estimate) = history.estimate(&scenario.slug)
+                && elapsed + estimate * first_round > budget
+            {
+                println!(
+                    "\n[bench] Skipping {}: its estimated {} does not fit the remaining budget.",
+                    scenario.slug,
+                    history::format_eta(estimate * first_round)
+                );
+                continue;
+            }
+        }
+        println!("\n=== Scenario: {} ===", scenario.slug);
+        println!("{}", scenario.describe());
+        let scenario_started = Instant::now();
+        match run_iterations(
+            scenario,
+            &context,
+            Vec::new(),
+            first_round,
+            args.rerun_outliers,
+        )
+        .with_context(|| format!("benchmark failed for {}", scenario.slug))
+        {
+            Ok(run) => {
+                let record = writer
+                    .push_success(scenario, &run)
+                    .with_context(|| format!("failed to record results for {}", scenario.slug))?;
+                report_statistics(record);
+                report_outliers(record);
+                report_cache_growth(record);
+                if args.halving.is_some() || args.allocate {
+                    let per_iteration = scenario_started.elapsed() / run.results.len() as u32;
+                    screened.push((scenario, run, per_iteration));
+                }
+            }
+            Err(err) => {
+                let kind = FailureKind::classify(&err);
+                eprintln!("[bench][error][{}] {}", kind.label(), err);
+                writer
+                    .push_failure(scenario, &err, kind)
+                    .with_context(|| format!("failed to log failure for {}", scenario.slug))?;
+                failures.push((scenario.slug.clone(), kind));
+                if kind == FailureKind::Interrupted {
+                    break;
+                }
+            }
+        }
+        forecast.finish(
+            &history,
+            &scenario.slug,
+            first_round,
+            scenario_started.elapsed(),
+        );
+        forecast.report_remaining(&history, &prepared[index + 1..], first_round);
+    }
+
+    if let Some(finalists) = args.halving
+        && !process_tree::interrupted()
+    {
+        run_finalists(
+            screened,
+            finalists as usize,
+            args,
+            &context,
+            &mut writer,
+            &mut failures,
+        )?;
+    } else if let Some(budget) = args.budget
+        && args.allocate
+        && !process_tree::interrupted()
+    {
+        run_allocated(
+            screened,
+            budget.saturating_sub(started.elapsed()),
+            args,
+            &context,
+            &mut writer,
+            &mut failures,
+        )?;
+    }
+
+    report::print_table(
+        writer.records(),
+        &history,
+        args.reference.as_deref(),
+        report::use_color(args.no_color),
+    );
+    analysis::summarize(writer.records());
+    analysis::report(writer.records());
+    pareto::report(writer.records());
+    bloat::report(writer.records());
+    llvm_lines::report(writer.records());
+    if let Some(top) = args.crate_timings {
+        crate_timings::report(writer.records(), top);
+    }
+    memory_limit::release();
+    if process_tree::interrupted() {
+        bail!(
+            "run interrupted; partial results are in {}",
+            writer.path().display()
+        );
+    }
+    if let Some(endpoint) = &args.submit
+        && let Err(err) = community::submit(endpoint, &writer.record, &host)
+    {
+        eprintln!("[bench][warn] Could not submit results: {err:#}");
+    }
+    println!("\nAll scenarios completed. Failures: {}.", failures.len());
+    forecast.report_error();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        report_failures(&failures);
+        bail!(
+            "{} scenario(s) failed. See results log for details.",
+            failures.len()
+        )
+    }
+}
+
+/// Second round of `--halving`: ranks the screened scenarios by one
+/// iteration of `--halving-phase` and runs the remaining iterations for the
+/// fastest `finalists`. Their records are replaced by the full runs; the
+/// others keep their single screening iteration.
+fn run_finalists(
+    mut screened: Vec<(&PreparedScenario, ScenarioRun, Duration)>,
+    finalists: usize,
+    args: &RunArgs,
+    context: &RunContext,
+    writer: &mut RunWriter,
+    failures: &mut Vec<(String, FailureKind)>,
+) -> Result<()> {
+    let phase = args.halving_phase.as_str();
+    let screened_count = screened.len();
+    // Scenarios without the phase (e.g. hotpatch without dx) cannot be ranked.
+    screened.retain(|(_, run, _)| run.phase_seconds(phase).is_some());
+    screened.sort_by(|(_, a, _), (_, b, _)| {
+        let seconds = |run: &ScenarioRun| run.phase_seconds(phase).unwrap_or(f64::INFINITY);
+        seconds(a).total_cmp(&seconds(b))
+    });
+    screened.truncate(finalists);
+    println!(
+        "\n[bench] Screened {screened_count} scenario(s); running {} more iteration(s) of the {} fastest by {phase}.",
+        args.iterations - 1,
+        screened.len()
+    );
+
+    for (scenario, run, _) in screened {
+        println!("\n=== Finalist: {} ===", scenario.slug);
+        if !continue_run(
+            scenario,
+            run,
+            args.iterations,
+            args,
+            context,
+            writer,
+            failures,
+        )? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `--allocate`: gives the scenarios of the pilot pass the iterations that
+/// `remaining` affords, by [`allocation::allocate`].
+fn run_allocated(
+    screened: Vec<(&PreparedScenario, ScenarioRun, Duration)>,
+    remaining: Duration,
+    args: &RunArgs,
+    context: &RunContext,
+    writer: &mut RunWriter,
+    failures: &mut Vec<(String, FailureKind)>,
+) -> Result<()> {
+    let pilots: Vec<allocation::Pilot> = screened
+        .iter()
+        .map(|(_, run, cost)| allocation::Pilot {
+            iterations: run.results.len() as u32,
+            spread: run.spread(),
+            cost: *cost,
+        })
+        .collect();
+    let plan = allocation::allocate(&pilots, remaining);
+    println!(
+        "\n[bench] Allocating the remaining {} of the budget:",
+        history::format_eta(remaining)
+    );
+    for ((scenario, _, _), (pilot, iterations)) in screened.iter().zip(pilots.iter().zip(&plan)) {
+        println!(
+            "    {}: {iterations} iteration(s) (spread {:.1}%, {} each)",
+            scenario.slug,
+            pilot.spread * 100.0,
+            history::format_eta(pilot.cost)
+        );
+    }
+
+    let started = Instant::now();
+    for ((scenario, run, cost), iterations) in screened.into_iter().zip(plan) {
+        let missing = iterations.saturating_sub(run.results.len() as u32);
+        if missing == 0 {
+            continue;
+        }
+        if started.elapsed() + cost * missing > remaining {
+            println!(
+                "\n[bench] Budget used up; {} keeps {} iteration(s).",
+                scenario.slug,
+                run.results.len()
+            );
+            continue;
+        }
+        println!("\n=== Allocated: {} ===", scenario.slug);
+        if !continue_run(scenario, run, iterations, args, context, writer, failures)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Runs `scenario` on from `run` to `iterations` in total and replaces its
+/// record. Returns `false` when the run was interrupted.
+fn continue_run(
+    scenario: &PreparedScenario,
+    run: ScenarioRun,
+    iterations: u32,
+    args: &RunArgs,
+    context: &RunContext,
+    writer: &mut RunWriter,
+    failures: &mut Vec<(String, FailureKind)>,
+) -> Result<bool> {
+    writer.retract(&scenario.slug);
+    match run_iterations(
+        scenario,
+        context,
+        run.results,
+        iterations,
+        args.rerun_outliers,
+    )
+    .with_context(|| format!("benchmark failed for {}", scenario.slug))
+    {
+        Ok(run) => {
+            let record = writer
+                .push_success(scenario, &run)
+                .with_context(|| format!("failed to record results for {}", scenario.slug))?;
+            report_statistics(record);
+            report_outliers(record);
+            report_cache_growth(record);
+        }
+        Err(err) => {
+            let kind = FailureKind::classify(&err);
+            eprintln!("[bench][error][{}] {}", kind.label(), err);
+            writer
+                .push_failure(scenario, &err, kind)
+                .with_context(|| format!("failed to log failure for {}", scenario.slug))?;
+            failures.push((scenario.slug.clone(), kind));
+            return Ok(kind != FailureKind::Interrupted);
+        }
+    }
+    Ok(true)
+}
+
+/// Runs the scenario until it has `iterations` results, continuing after any
+/// `previous` ones. Each iteration runs in a fresh workspace so it measures a
+/// genuinely clean first build, except that one `dx serve` session measures
+/// the hotpatches of all remaining iterations.
+///
+/// With `rerun_outliers`, iterations flagged as outliers are run once more and
+/// their measurements replaced; the original outliers stay in the record.
+fn run_iterations(
+    prepared: &PreparedScenario,
+    context: &RunContext,
+    previous: Vec<ScenarioResult>,
+    iterations: u32,
+    rerun_outliers: bool,
+) -> Result<ScenarioRun> {
+    let mut results = previous;
+    let mut dx = DxRounds::default();
+    for iteration in results.len() as u32 + 1..=iterations {
+        if iterations > 1 {
+            println!("--- Iteration {iteration}/{iterations} ---");
+        }
+        dx.wanted = iterations - iteration + 1;
+        results.push(run_iteration(
+            prepared, context, iteration, iterations, &mut dx,
+        )?);
+    }
+
+    let mut outliers = outliers::detect(&results);
+    if rerun_outliers && !outliers.is_empty() {
+        let mut rerun: Vec<usize> = outliers.iter().map(|outlier| outlier.iteration).collect();
+        rerun.sort_unstable();
+        rerun.dedup();
+        for iteration in rerun {
+            println!("--- Re-measuring outlier iteration {iteration}/{iterations} ---");
+            dx.wanted = 1;
+            results[iteration - 1] =
+                run_iteration(prepared, context, iteration as u32, iterations, &mut dx)?;
+        }
+        for outlier in &mut outliers {
+            outlier.remeasured = true;
+        }
+    }
+
+    Ok(ScenarioRun { results, outliers })
+}
+
+fn run_iteration(
+    prepared: &PreparedScenario,
+    context: &RunContext,
+    iteration: u32,
+    iterations: u32,
+    dx: &mut DxRounds,
+) -> Result<ScenarioResult> {
+    let oom_kills = memory_limit::oom_kills();
+    let result = run_scenario(prepared, context, dx)
+        .map_err(|err| memory_limit::explain(err, oom_kills))
+        .with_context(|| format!("iteration {iteration}/{iterations} failed"))?;
+    report_timings(&result);
+    report_invalid(&result);
+    Ok(result)
+}
+
+fn run_scenario(
+    prepared: &PreparedScenario,
+    context: &RunContext,
+    dx: &mut DxRounds,
+) -> Result<ScenarioResult> {
+    let (host, hooks, slug) = (&context.host, &context.hooks, prepared.slug.as_str());
+    let load_average = host.load_average();
+    let mut workspace = Workspace::create(prepared, host, context.verbose)?;
+    if let Some(output) = &context.output {
+        let dir = output.scenario(slug)?;
+        output::write_cargo_config(&dir, &prepared.code.cargo_config_toml)?;
+        workspace.output = Some(dir);
+    }
+    let (filesystem, defender_scanned) = match host {
+        Host::Local => (
+            filesystem::filesystem_type(workspace.path()),
+            filesystem::defender_scans(workspace.path()),
+        ),
+        Host::Ssh(_) => (None, None),
+    };
+    if defender_scanned == Some(true) {
+        eprintln!(
+            "[bench][warn] Windows Defender real-time scanning covers {}",
+            workspace.path().display()
+        );
+    }
+    let warm_deps = if context.share_deps {
+        let dir = host.shared_dir(&format!("target-{}", prepared.compile_settings_hash()))?;
+        let warm = host.dir_exists(&dir)?;
+        if warm {
+            println!(
+                "[bench] Reusing shared target directory {dir}; the clean build has warm dependencies."
+            );
+        }
+        workspace.use_shared_target_dir(dir);
+        warm
+    } else {
+        false
+    };
+    let sccache = match prepared.scenario.cache.and_then(Cache::server) {
+        Some(tool) => {
+            let server = SccacheServer::start(host, &workspace.exec_dir, tool)?;
+            workspace.env.extend(server.env());
+            Some(server)
+        }
+        None => None,
+    };
+    let mut wrapper_log = None;
+    if prepared.scenario.cache == Some(Cache::Wrapper) {
+        let wrapper = context
+            .wrapper
+            .as_ref()
+            .context("the wrapper cache needs a [wrapper] command in the config file")?;
+        wrapper_log = wrapper
+            .configure(&mut workspace)?
+            .map(|log| (log, wrapper.command.as_str()));
+    }
+    let link_log = if context.link_time {
+        link::configure(&mut workspace, prepared)?
+    } else {
+        None
+    };
+    // Remote builders are expected to have their own NDK environment.
+    if prepared.scenario.target == Some(Target::Aarch64Android) && matches!(host, Host::Local) {
+        let ndk = detect_android_ndk()?;
+        println!("[bench] Using Android NDK at {}", ndk.display());
+        workspace
+            .env
+            .push(("ANDROID_NDK_HOME".to_string(), ndk.display().to_string()));
+    }
+    if workspace.verbose {
+        diagnostics::print(&workspace, prepared);
+    }
+    hooks.before_scenario(&workspace, slug)?;
+    let mut lockfile = LockfileGuard::generate(&workspace, &context.pins)?;
+    if prepared.scenario.warm_cache
+        && let Some(server) = &sccache
+    {
+        prewarm_cache(&workspace, prepared, server, context)?;
+    }
+    let restored_target = match &context.restore_target {
+        Some(dir) => snapshot::restore(&workspace, dir, slug)?,
+        None => false,
+    };
+    if context.clean_mode == CleanMode::Payload && !restored_target {
+        build_dependencies(&workspace, prepared, context.runner.as_ref())?;
+    }
+    // Resolved with the shared `CARGO_HOME` above; from here on the registry
+    // axis starts from an empty one, so the fetch phase downloads the index
+    // and every crate.
+    if prepared.scenario.registry.is_some() {
+        let cargo_home = host.join(&workspace.exec_dir, "cargo-home");
+        workspace.env.push(("CARGO_HOME".to_string(), cargo_home));
+    }
+    // Links of the untimed builds above belong to no phase.
+    if let Some(log) = &link_log {
+        link::take(log);
+    }
+    let runner = context.runner.as_ref();
+    let mut phases = Vec::new();
+    let (mut wasm, mut hotpatch, mut crate_timings) = (None, None, None);
+    for phase in Phase::for_scenario(&prepared.scenario, context.first_frame, context.relocate) {
+        if phase == Phase::Clean && restored_target {
+            println!("[bench] Skipping the clean build: the target directory was restored.");
+            continue;
+        }
+        // Local counters say nothing about a build that ran over ssh.
+        let io_before = match host {
+            Host::Local => IoCounters::snapshot(),
+            Host::Ssh(_) => None,
+        };
+        let marker = PhaseMarker::begin(phase, workspace.log_file("phases"));
+        let mut result = match phase {
+            Phase::Fetch => {
+                let elapsed = run_cargo_fetch(&workspace, &prepared.scenario, runner)?;
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+            Phase::Clean if context.crate_timings.is_some() => {
+                let (elapsed, timings) =
+                    crate_timings::run_clean_build(&workspace, prepared, runner)?;
+                crate_timings = timings;
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+            Phase::Clean | Phase::Second => {
+                let elapsed = run_cargo_build(&workspace, prepared, phase.name(), runner)?;
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+            Phase::Relocated => relocate::measure(&workspace, prepared, runner)?,
+            Phase::WasmOpt => {
+                let (elapsed, sizes) = wasm::run_wasm_opt(&workspace, prepared)?;
+                wasm = Some(sizes);
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+            Phase::Modified => {
+                let elapsed = run_modified_build(&workspace, prepared, runner)?;
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+            Phase::FirstFrame => first_frame::measure(&workspace, prepared, runner)?,
+            Phase::Hotpatch => {
+                let outcome = match dx.measured.pop_front() {
+                    Some(outcome) => {
+                        println!(
+                            "[bench] Using the hotpatch measured for this iteration in the kept dx session."
+                        );
+                        outcome
+                    }
+                    None => {
+                        hooks.before_hotpatch(&workspace, slug)?;
+                        let mut outcomes =
+                            run_dx_hotpatch(&workspace, prepared, context, dx.wanted)?;
+                        lockfile.verify(&workspace, phase.name())?;
+                        let first = outcomes.remove(0);
+                        dx.measured.extend(outcomes);
+                        first
+                    }
+                };
+                let mut result = PhaseResult::new(phase, outcome.elapsed, None);
+                result.metrics = outcome.metrics.clone();
+                hotpatch = Some(outcome);
+                result
+            }
+            Phase::HotpatchRebuild => {
+                let elapsed = run_hotpatch_rebuild(&workspace, prepared, runner)?;
+                PhaseResult::new(phase, elapsed, Some(0))
+            }
+        };
+        if let Some(before) = io_before
+            && let Some(after) = IoCounters::snapshot()
+        {
+            result.metrics.extend(after.metrics_since(before));
+        }
+        if let Some(log) = &link_log {
+            let invocations = link::take(log);
+            if !invocations.is_empty() {
+                let metrics = link::metrics(&invocations);
+                println!(
+                    "[bench] {} spent {:.3}s of {:.3}s in {} linker invocation(s).",
+                    phase.name(),
+                    metrics["link_time"],
+                    result.duration.as_secs_f64(),
+                    invocations.len()
+                );
+                if let Some(path) = workspace.log_file("link")
+                    && let Err(err) = link::write_log(&path, phase.name(), &invocations)
+                {
+                    eprintln!("[bench][warn] Could not write the link log: {err:#}");
+                }
+                result.metrics.extend(metrics);
+            }
+        }
+        if phase == Phase::Clean
+            && let Some((log, command)) = &wrapper_log
+        {
+            let compiled = wrapper::verify(&workspace, log, command)?;
+            result
+                .metrics
+                .insert("wrapper_compilations".to_string(), compiled as f64);
+        }
+        if phase.is_cargo_build() {
+            lockfile.verify(&workspace, phase.name())?;
+            hooks.after_build(&workspace, slug, phase.name())?;
+        }
+        marker.end();
+        phases.push(result);
+        if phase == Phase::Clean
+            && let Some(dir) = &context.save_target
+        {
+            snapshot::save(&workspace, dir, slug)?;
+        }
+    }
+    let unverified_hotpatch = hotpatch
+        .as_ref()
+        .and_then(|hotpatch| hotpatch.problem.clone());
+    let sccache = sccache.map(|server| server.stats()).transpose()?;
+    // Size measurements are informational; a failure must not discard timings.
+    let disk = match disk::measure(&workspace, prepared) {
+        Ok(disk) => Some(disk),
+        Err(err) => {
+            eprintln!("[bench][warn] Could not measure disk usage: {err:#}");
+            None
+        }
+    };
+    // Before llvm-lines, which rebuilds the payload crate with other flags.
+    let bloat = context.bloat.and_then(|top| {
+        bloat::measure(&workspace, prepared, top)
+            .inspect_err(|err| eprintln!("[bench][warn] Could not run cargo bloat: {err:#}"))
+            .ok()
+    });
+    let llvm_lines = context.llvm_lines.and_then(|top| {
+        llvm_lines::measure(&workspace, prepared, top)
+            .inspect_err(|err| eprintln!("[bench][warn] Could not run cargo llvm-lines: {err:#}"))
+            .ok()
+    });
+    hooks.after_scenario(&workspace, slug)?;
+
+    Ok(ScenarioResult {
+        slug: prepared.slug.clone(),
+        timings: ScenarioTimings {
+            phases,
+            hotpatch_verified: hotpatch.as_ref().map(|hotpatch| hotpatch.problem.is_none()),
+        },
+        lockfile_hash: lockfile.hash,
+        invalid: lockfile
+            .changed_in
+            .map(|phase| format!("Cargo.lock changed during {phase} phase"))
+            .or(unverified_hotpatch),
+        sccache,
+        wasm,
+        disk,
+        load_average,
+        warm_deps,
+        restored_target,
+        clean_mode: context.clean_mode,
+        nice: context.nice,
+        filesystem,
+        defender_scanned,
+        bloat,
+        llvm_lines,
+        crate_timings,
+    })
+}
+
+fn run_modified_build(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<Duration> {
+    println!("[bench] Mutating source to trigger partial rebuild...");
+    apply_modified_source(workspace, prepared)?;
+    let result = run_cargo_build(workspace, prepared, "modified", runner);
+    workspace
+        .restore_original_source(&prepared.code)
+        .context("failed to restore original source after modified build")?;
+    result
+}
+
+/// Times the `cargo build` a hotpatch replaces: the same source change,
+/// built in a copy of the workspace as `dx serve` left it.
+fn run_hotpatch_rebuild(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<Duration> {
+    println!("[bench] Copying the workspace to time the equivalent rebuild...");
+    let copy = workspace.duplicate(prepared)?;
+    // Cargo may not trust the copied artifacts at their new path, so bring
+    // them up to date with the unchanged source before timing the change.
+    copy.restore_original_source(&prepared.code)?;
+    run_cargo_build(&copy, prepared, "warm-up", runner)?;
+    apply_modified_source(&copy, prepared)?;
+    run_cargo_build(&copy, prepared, Phase::HotpatchRebuild.name(), runner)
+}
+
+fn run_cargo_build(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    label: &str,
+    runner: &dyn CommandRunner,
+) -> Result<Duration> {
+    println!(
+        "[bench] Running {label} cargo build in {}",
+        workspace.exec_dir
+    );
+    let mut command = cargo_command(workspace, prepared, "build");
+    runner::run_build_logged(
+        runner,
+        &mut command,
+        label,
+        workspace.log_file(label).as_deref(),
+    )
+}
+
+/// Fills the scenario's compiler cache with an untimed build, then removes
+/// the target directory and resets the statistics, so the timed clean build
+/// starts from an empty target directory and a warm cache.
+fn prewarm_cache(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    server: &SccacheServer,
+    context: &RunContext,
+) -> Result<()> {
+    if context.share_deps {
+        bail!("--warm-cache scenarios remove their target directory and cannot use --share-deps");
+    }
+    run_cargo_build(
+        workspace,
+        prepared,
+        "cache pre-warming",
+        context.runner.as_ref(),
+    )?;
+    workspace
+        .host
+        .remove(&workspace.target_dir)
+        .context("failed to remove the pre-warming build's target directory")?;
+    server.zero_stats()
+}
+
+/// For `--clean-mode payload`: builds everything untimed, then removes only
+/// the payload's artifacts, so the clean phase rebuilds the leaf crate.
+fn build_dependencies(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<()> {
+    run_cargo_build(workspace, prepared, "dependency", runner)?;
+    let packages = prepared.packages();
+    let package = packages.join(" -p ");
+    println!("[bench] Running cargo clean -p {package}");
+    let mut command = workspace.command("cargo");
+    command.args(["clean", "--quiet", "--locked"]);
+    for package in &packages {
+        command.args(["-p", package]);
+    }
+    if let Some(profile) = prepared.scenario.profile {
+        command.arg("--profile").arg(profile.label());
+    }
+    if let Some(target) = prepared.scenario.target {
+        command.arg("--target").arg(target.triple());
+    }
+    let status = command
+        .status()
+        .map_err(runner::spawn_error("cargo"))
+        .context("failed to run cargo clean")?;
+    if !status.success() {
+        bail!("cargo clean -p {package} failed with status {status}");
+    }
+    Ok(())
+}
+
+/// `cargo fetch` of the locked dependencies for the scenario's target.
+/// Cargo plugins for cross targets only wrap builds, so plain cargo runs it.
+fn run_cargo_fetch(
+    workspace: &Workspace,
+    scenario: &Scenario,
+    runner: &dyn CommandRunner,
+) -> Result<Duration> {
+    println!("[bench] Running cargo fetch in {}", workspace.exec_dir);
+    let mut command = workspace.command("cargo");
+    command.args(["fetch", "--quiet", "--locked"]);
+    if let Some(target) = scenario.target {
+        command.arg("--target").arg(target.triple());
+    }
+    runner::run_build_logged(
+        runner,
+        &mut command,
+        "fetch",
+        workspace.log_file("fetch").as_deref(),
+    )
+}
+
+/// `cargo <subcommand>` in the workspace with the scenario's target flags.
+pub(crate) fn cargo_command(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    subcommand: &str,
+) -> Command {
+    let mut command = workspace.command("cargo");
+    command.envs(workspace.cargo_env.iter().cloned());
+    command.args(cargo_args(
+        &prepared.scenario,
+        subcommand,
+        workspace.verbose,
+    ));
+    command.args(prepared.cargo_args());
+    command
+}
+
+/// Arguments of `cargo <subcommand>` for `scenario`: the cross target's
+/// plugin, the linker's build subcommand, the profile, and the target.
+fn cargo_args<'a>(scenario: &Scenario, subcommand: &'a str, verbose: bool) -> Vec<&'a str> {
+    let mut args = Vec::new();
+    if let Some(target) = scenario.target {
+        args.extend(target.cargo_plugin());
+    }
+    args.push(match subcommand {
+        "build" => Linker::build_subcommand(scenario.linker),
+        other => other,
+    });
+    args.push(if verbose { "--verbose" } else { "--quiet" });
+    args.push("--locked");
+    if let Some(profile) = scenario.profile {
+        args.extend(["--profile", profile.label()]);
+    }
+    if let Some(target) = scenario.target
+        && target.needs_target_flag()
+    {
+        args.extend(["--target", target.triple()]);
+    }
+    args
+}
+
+/// Arguments of the `dx serve` hotpatch session listening on `port`.
+fn dx_serve_args(port: &str) -> [&str; 8] {
+    [
+        "serve",
+        "--hot-patch",
+        "--addr",
+        DX_ADDRESS,
+        "--port",
+        port,
+        "--features",
+        "bevy/hotpatching",
+    ]
+}
+
+/// Measures `rounds` hotpatches in one `dx serve` session, changing the
+/// payload constant again for each. A failing later round ends the session
+/// with the rounds measured so far; only a failing first round is an error.
+fn run_dx_hotpatch(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    context: &RunContext,
+    rounds: u32,
+) -> Result<Vec<HotpatchOutcome>> {
+    let mut recording = context.record_dx.as_ref().map(|_| {
+        Recording::new(
+            &prepared.ready_marker,
+            context.host.probe("dx", &["--version"]),
+        )
+    });
+    let mut attempt = 1;
+    let result = loop {
+        let port = port::free("dx serve")?;
+        let endpoint = format!("{DX_ADDRESS}:{port}");
+        println!("[bench] Starting dx serve hotpatch session on {endpoint}...");
+        let mut command = workspace.command("dx");
+        command.args(dx_serve_args(&port.to_string()));
+        let (markers, _marker_file) = match context.ready_channel {
+            ReadyChannel::Stdout => (None, None),
+            ReadyChannel::File => {
+                // Outside the workspace, so dx does not see the writes as
+                // changes.
+                let file = tempfile::Builder::new()
+                    .prefix(&format!("bench-{}-markers-", prepared.slug))
+                    .suffix(".log")
+                    .tempfile()
+                    .context("failed to create marker file")?;
+                println!(
+                    "[bench] Following payload markers in {}",
+                    file.path().display()
+                );
+                command.env(MARKER_FILE_ENV, file.path());
+                (
+                    Some(MarkerSource::File(file.path().to_path_buf())),
+                    Some(file),
+                )
+            }
+            ReadyChannel::Socket => {
+                let server = protocol::Server::bind()?;
+                println!("[bench] Listening for the payload on {}", server.address());
+                command.env(protocol::ADDRESS_ENV, server.address().to_string());
+                (Some(MarkerSource::Socket(server)), None)
+            }
+        };
+        let mut process = context
+            .runner
+            .spawn(&mut command, "dx")
+            .context("failed to spawn dx serve")?;
+        let result = hotpatch_rounds(
+            process.as_mut(),
+            markers.as_ref(),
+            workspace,
+            prepared,
+            rounds,
+            context.patch_timeout,
+            recording.as_mut(),
+        );
+        // Another process can take the port between choosing and binding it.
+        match result {
+            Err(err) if port::is_conflict(&format!("{err:#}")) => {
+                let err = err.context(format!("dx serve could not listen on {endpoint}"));
+                if attempt == DX_PORT_ATTEMPTS {
+                    break Err(err);
+                }
+                eprintln!("[bench][warn] {endpoint} is already in use; retrying on another port.");
+                // Only the session that got its port is worth replaying.
+                if let Some(recording) = recording.as_mut() {
+                    *recording =
+                        Recording::new(&prepared.ready_marker, recording.dx_version.take());
+                }
+                attempt += 1;
+            }
+            result => break result,
+        }
+    };
+
+    if let (Some(dir), Some(mut recording)) = (&context.record_dx, recording) {
+        recording.verdict = Some(match &result {
+            Ok(outcomes) => Verdict::of_outcome(&outcomes[0]),
+            Err(err) => Verdict::of_error(err),
+        });
+        let path = dir.join(format!("{}.ron", prepared.slug));
+        match fs::create_dir_all(dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| recording.save(&path))
+        {
+            Ok(()) => println!("[bench] Recorded dx session to {}", path.display()),
+            Err(err) => eprintln!("[bench][warn] Could not save dx recording: {err:#}"),
+        }
+    }
+    result
+}
+
+fn hotpatch_rounds(
+    process: &mut dyn runner::Process,
+    markers: Option<&MarkerSource>,
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    rounds: u32,
+    patch_timeout: Duration,
+    recording: Option<&mut Recording>,
+) -> Result<Vec<HotpatchOutcome>> {
+    let mut session = DxSession::start(process, &prepared.ready_marker, markers, recording)?;
+    session.wait_ready(hotpatch::READY_TIMEOUT)?;
+    let mut outcomes = Vec::new();
+    for round in 1..=rounds {
+        if round > 1 {
+            println!("[bench] Hotpatch round {round}/{rounds} in the same dx session...");
+        }
+        let value = hotpatch_payload_value(prepared.payload_value, round);
+        match session.patch(patch_timeout, || mutate_payload(workspace, prepared, value)) {
+            Ok(outcome) => outcomes.push(outcome),
+            // The session is gone; later iterations start their own.
+            Err(err) if !outcomes.is_empty() => {
+                eprintln!("[bench][warn] Hotpatch round {round}/{rounds} failed: {err:#}");
+                return Ok(outcomes);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    session.finish()?;
+    Ok(outcomes)
+}
+
+/// Edits the payload to report `new_value` and returns the line the patched
+/// payload will print.
+fn mutate_payload(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    new_value: u64,
+) -> Result<String> {
+    let new_source = prepared.mutated_source(new_value);
+    workspace
+        .write_main_source(&new_source)
+        .context("failed to update payload source for hotpatch")?;
+    Ok(format!("PAYLOAD_RANDOM_VALUE={new_value}"))
+}
+
+fn apply_modified_source(workspace: &Workspace, prepared: &PreparedScenario) -> Result<()> {
+    let modified_value = next_payload_value(prepared.payload_value);
+    let modified_source = prepared.mutated_source(modified_value);
+    workspace
+        .write_main_source(&modified_source)
+        .context("failed to write modified payload source")
+}
+
+/// Finds the NDK `cargo ndk` should use: `ANDROID_NDK_HOME`/`ANDROID_NDK_ROOT`
+/// if set, otherwise the newest side-by-side NDK under the Android SDK.
+fn detect_android_ndk() -> Result<PathBuf> {
+    for var in ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT"] {
+        if let Some(path) = std::env::var_os(var).map(PathBuf::from)
+            && path.is_dir()
+        {
+            return Ok(path);
+        }
+    }
+
+    for var in ["ANDROID_HOME", "ANDROID_SDK_ROOT"] {
+        let Some(sdk) = std::env::var_os(var).map(PathBuf::from) else {
+            continue;
+        };
+        let Ok(entries) = fs::read_dir(sdk.join("ndk")) else {
+            continue;
+        };
+        // Side-by-side NDK directories are named after their version.
+        let newest = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .max_by_key(|path| ndk_version(path));
+        if let Some(newest) = newest {
+            return Ok(newest);
+        }
+    }
+
+    Err(BenchError::ToolMissing {
+        tool: "Android NDK".to_string(),
+    })
+    .context("set ANDROID_NDK_HOME or install an NDK through the Android SDK")
+}
+
+fn ndk_version(path: &Path) -> Vec<u64> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// The constant of hotpatch round `round`, counting from 1. Every round gets
+/// a value neither the original payload nor an earlier round had, so each
+/// patch is a change dx has not compiled before.
+fn hotpatch_payload_value(original: u64, round: u32) -> u64 {
+    // The XOR moves the value more than 2^62 away from `original`, far beyond
+    // what a round count adds.
+    next_payload_value(original).wrapping_add(u64::from(round - 1))
+}
+
+fn next_payload_value(previous: u64) -> u64 {
+    let candidate = previous ^ 0xa076_1d64_78bd_642f;
+    if candidate != previous {
+        candidate
+    } else {
+        previous.wrapping_add(0x9e37)
+    }
+}
+
+fn report_timings(result: &ScenarioResult) {
+    let phases: Vec<String> = result
+        .timings
+        .phases
+        .iter()
+        .map(|phase| {
+            format!(
+                "{}={}",
+                phase.phase.name(),
+                format_duration(Some(phase.duration))
+            )
+        })
+        .collect();
+    println!(
+        "[bench] Results for {} -> {}",
+        result.slug,
+        phases.join(", ")
+    );
+    let duration = |phase| {
+        result
+            .timings
+            .phases
+            .iter()
+            .find(|result| result.phase == phase)
+            .map(|result| result.duration.as_secs_f64())
+    };
+    if let (Some(hotpatch), Some(rebuild)) =
+        (duration(Phase::Hotpatch), duration(Phase::HotpatchRebuild))
+    {
+        if rebuild > hotpatch {
+            println!(
+                "[bench] Hotpatch saved you {:.1}s per change ({hotpatch:.1}s vs a {rebuild:.1}s rebuild)",
+                rebuild - hotpatch
+            );
+        } else {
+            println!(
+                "[bench] Hotpatch was {:.1}s slower than a rebuild ({hotpatch:.1}s vs {rebuild:.1}s)",
+                hotpatch - rebuild
+            );
+        }
+    }
+    if let Some(hotpatch) = result
+        .timings
+        .phases
+        .iter()
+        .find(|result| result.phase == Phase::Hotpatch)
+        && let Some(median) = hotpatch.metrics.get("frame_ms_median")
+    {
+        let longest = hotpatch
+            .metrics
+            .get("patch_frame_ms_max")
+            .map(|longest| format!(", longest while patching {longest:.1}ms"))
+            .unwrap_or_default();
+        println!("[bench] frames: median {median:.1}ms{longest}");
+    }
+    let io: Vec<String> = result
+        .timings
+        .phases
+        .iter()
+        .filter_map(|phase| {
+            let read = phase.metrics.get("io_read_bytes")?;
+            let written = phase.metrics.get("io_write_bytes")?;
+            Some(format!(
+                "{} read {} wrote {}",
+                phase.phase.name(),
+                format_bytes(*read as u64),
+                format_bytes(*written as u64)
+            ))
+        })
+        .collect();
+    if !io.is_empty() {
+        println!("[bench] disk I/O: {}", io.join(", "));
+    }
+    if let Some(wasm) = result.wasm {
+        println!(
+            "[bench] wasm: raw={} -> {} after wasm-opt",
+            format_bytes(wasm.raw_bytes),
+            format_bytes(wasm.optimized_bytes)
+        );
+    }
+    if let Some(disk) = result.disk {
+        println!(
+            "[bench] disk: workspace={}, binary={}",
+            format_bytes(disk.workspace_bytes),
+            format_bytes(disk.binary_bytes)
+        );
+    }
+    if let Some(stats) = result.sccache {
+        println!(
+            "[bench] compiler cache: {} hit(s), {} miss(es), {} non-cacheable, hit rate {}, size {}",
+            stats.hits,
+            stats.misses,
+            stats.non_cacheable,
+            stats
+                .hit_rate()
+                .map(|rate| format!("{:.1}%", rate * 100.0))
+                .unwrap_or_else(|| "n/a".to_string()),
+            stats
+                .cache_bytes
+                .map(format_bytes)
+                .unwrap_or_else(|| "n/a".to_string())
+        );
+    }
+}
+
+fn report_statistics(record: &ScenarioRecord) {
+    for stats in &record.statistics {
+        println!(
+            "[bench] {} {}: p50={:.3}s p95={:.3}s 95% CI [{:.3}s, {:.3}s] (n={})",
+            record.slug,
+            stats.phase,
+            stats.p50,
+            stats.p95,
+            stats.ci_low,
+            stats.ci_high,
+            stats.samples
+        );
+    }
+}
+
+fn report_outliers(record: &ScenarioRecord) {
+    for outlier in &record.outliers {
+        eprintln!(
+            "[bench][warn] {} iteration {} {}={:.3}s is an outlier (median {:.3}s){}: suspected {}",
+            record.slug,
+            outlier.iteration,
+            outlier.phase,
+            outlier.seconds,
+            outlier.median,
+            if outlier.remeasured {
+                ", re-measured"
+            } else {
+                ""
+            },
+            outlier.suspected_cause
+        );
+    }
+}
+
+fn report_cache_growth(record: &ScenarioRecord) {
+    if let Some(growth) = record.cache_growth {
+        println!(
+            "[bench] Compiler caches after {} scenario(s): {} in total, cumulative hit rate {}, {} non-cacheable",
+            growth.scenarios,
+            format_bytes(growth.cache_bytes),
+            growth
+                .hit_rate()
+                .map(|rate| format!("{:.1}%", rate * 100.0))
+                .unwrap_or_else(|| "n/a".to_string()),
+            growth.non_cacheable
+        );
+    }
+}
+
+fn report_invalid(result: &ScenarioResult) {
+    if let Some(reason) = &result.invalid {
+        eprintln!(
+            "[bench][warn] {} is marked invalid: {reason}; its timings are not comparable.",
+            result.slug
+        );
+    }
+}
+
+fn report_failures(failures: &[(String, FailureKind)]) {
+    let mut by_kind: Vec<(FailureKind, Vec<&str>)> = Vec::new();
+    for (slug, kind) in failures {
+        match by_kind.iter_mut().find(|(existing, _)| existing == kind) {
+            Some((_, slugs)) => slugs.push(slug),
+            None => by_kind.push((*kind, vec![slug])),
+        }
+    }
+    by_kind.sort_by_key(|(kind, _)| *kind);
+
+    println!("[bench] Failures by category:");
+    for (kind, slugs) in by_kind {
+        println!("  {} ({}):", kind.label(), slugs.len());
+        for slug in slugs {
+            println!("    {slug}");
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    format!("{:.2} MiB", bytes as f64 / MIB)
+}
+
+fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(value) => format!("{:.3}s", value.as_secs_f64()),
+        None => "n/a".to_string(),
+    }
+}
+
+impl Workspace {
+    fn create(prepared: &PreparedScenario, host: &Host, verbose: bool) -> Result<Self> {
+        let prefix = format!("bench-{}-", prepared.slug);
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(&prefix).disable_cleanup(verbose);
+        let dir = match &prepared.root {
+            Some(root) => builder.tempdir_in(&root.path).with_context(|| {
+                format!("failed to create workspace under {}", root.path.display())
+            })?,
+            None => builder
+                .tempdir()
+                .context("failed to create temporary workspace")?,
+        };
+        write_workspace_files(dir.path(), &prepared.code)?;
+        let exec_dir = host.workspace_dir(dir.path());
+        host.push(dir.path(), &exec_dir)?;
+        let target_dir = host.join(&exec_dir, &format!("target/{}", prepared.slug));
+        Ok(Self {
+            dir,
+            payload_file: prepared.code.payload_file,
+            host: host.clone(),
+            exec_dir,
+            target_dir,
+            env: Vec::new(),
+            cargo_env: Vec::new(),
+            verbose,
+            output: None,
+        })
+    }
+
+    /// A copy of the workspace, build artifacts included, in a sibling
+    /// temporary directory that is removed again on drop.
+    fn duplicate(&self, prepared: &PreparedScenario) -> Result<Self> {
+        let parent = self
+            .path()
+            .parent()
+            .context("workspace has no parent directory")?;
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("bench-{}-copy-", prepared.slug))
+            .tempdir_in(parent)
+            .context("failed to create workspace copy")?;
+        let exec_dir = self.host.workspace_dir(dir.path());
+        if !matches!(self.host, Host::Local) {
+            // Later pushes mirror the local sources, so copy those as well.
+            Host::Local.copy_dir(
+                &self.path().display().to_string(),
+                &dir.path().display().to_string(),
+            )?;
+        }
+        self.host.copy_dir(&self.exec_dir, &exec_dir)?;
+        let target_dir = self
+            .host
+            .join(&exec_dir, &format!("target/{}", prepared.slug));
+        let mut env = self.env.clone();
+        // Builds in the copy must not touch a shared target directory.
+        if env.iter().any(|(key, _)| key == "CARGO_TARGET_DIR") {
+            self.host.copy_dir(&self.target_dir, &target_dir)?;
+            env.retain(|(key, _)| key != "CARGO_TARGET_DIR");
+        }
+        Ok(Self {
+            dir,
+            payload_file: self.payload_file,
+            host: self.host.clone(),
+            exec_dir,
+            target_dir,
+            env,
+            cargo_env: self.cargo_env.clone(),
+            verbose: false,
+            output: self.output.clone(),
+        })
+    }
+
+    /// Points cargo at a target directory outside the workspace, which
+    /// survives the workspace and is reused by later scenarios.
+    fn use_shared_target_dir(&mut self, dir: String) {
+        self.env.push(("CARGO_TARGET_DIR".to_string(), dir.clone()));
+        self.target_dir = dir;
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Where the cargo invocation named `label` logs its output, if the run
+    /// keeps logs.
+    fn log_file(&self, label: &str) -> Option<PathBuf> {
+        self.output
+            .as_deref()
+            .map(|output| output::log_file(output, label))
+    }
+
+    /// A command running in the workspace with the scenario's extra
+    /// environment (e.g. the isolated sccache server) applied.
+    fn command(&self, program: &str) -> Command {
+        self.host.command(&self.exec_dir, &self.env, program)
+    }
+
+    fn payload_source_file(&self) -> PathBuf {
+        self.path().join(self.payload_file)
+    }
+
+    /// Rewrites the payload source, mirroring it to the host if needed.
+    fn write_main_source(&self, source: &str) -> Result<()> {
+        fs::write(self.payload_source_file(), source).context("failed to write payload source")?;
+        self.host.push(self.path(), &self.exec_dir)
+    }
+
+    /// Directory cargo places the payload's debug artifacts in.
+    fn artifact_dir(&self, prepared: &PreparedScenario) -> String {
+        let mut dir = self.target_dir.clone();
+        if let Some(target) = prepared.scenario.target {
+            dir = self.host.join(&dir, target.triple());
+        }
+        // Cargo puts `dev` builds in `debug`; other profiles use their name.
+        let profile = match prepared.scenario.profile {
+            Some(profile) => profile.label(),
+            None => "debug",
+        };
+        self.host.join(&dir, profile)
+    }
+
+    fn lockfile_hash(&self) -> Result<String> {
+        let contents = self
+            .host
+            .read(&self.host.join(&self.exec_dir, "Cargo.lock"))
+            .context("failed to read Cargo.lock")?;
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    fn restore_original_source(&self, code: &Code) -> Result<()> {
+        self.write_main_source(&code.src_main_rs)
+            .context("failed to restore original payload source")
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if self.verbose {
+            println!("[bench] Kept workspace {}", self.exec_dir);
+            if let Some(output) = &self.output
+                && matches!(self.host, Host::Local)
+            {
+                output::link_workspace(output, self.path());
+            }
+            return;
+        }
+        if let Err(err) = self.host.remove(&self.exec_dir) {
+            eprintln!("[bench][warn] {err:#}");
+        }
+    }
+}
+
+impl LockfileGuard {
+    /// Resolves dependencies once, up front, so the timed builds can run with
+    /// `--locked` and never pick up a different resolution mid-scenario.
+    fn generate(workspace: &Workspace, pins: &Pins) -> Result<Self> {
+        println!("[bench] Generating Cargo.lock...");
+        let status = workspace
+            .command("cargo")
+            .arg("generate-lockfile")
+            .arg("--quiet")
+            .status()
+            .map_err(runner::spawn_error("cargo"))
+            .context("failed to run cargo generate-lockfile")?;
+        if !status.success() {
+            bail!("cargo generate-lockfile failed with status {status}");
+        }
+        pins.apply(workspace)?;
+
+        Ok(Self {
+            hash: workspace.lockfile_hash()?,
+            changed_in: None,
+        })
+    }
+
+    fn verify(&mut self, workspace: &Workspace, phase: &str) -> Result<()> {
+        let current = workspace.lockfile_hash()?;
+        if current != self.hash && self.changed_in.is_none() {
+            eprintln!("[bench][warn] Cargo.lock changed during {phase} phase.");
+            self.changed_in = Some(phase.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl RunWriter {
+    fn create(metadata: RunMetadata) -> Result<Self> {
+        let run_id = Utc::now().format("run-%Y%m%d-%H%M%S").to_string();
+        let path = Path::new(RESULTS_DIR).join(format!("{run_id}.ron"));
+        fs::create_dir_all(path.parent().unwrap()).context("failed to create results directory")?;
+        let output = OutputDir::create(Path::new(RESULTS_DIR).join(&run_id))?;
+        let record = RunRecord {
+            schema_version: metadata::SCHEMA_VERSION,
+            run_id,
+            started_at: Utc::now(),
+            failed: false,
+            calibration: None,
+            metadata: Some(metadata),
+            scenarios: Vec::new(),
+        };
+        Ok(Self {
+            path,
+            record,
+            output,
+        })
+    }
+
+    /// Records every iteration of a scenario and returns the stored record.
+    fn push_success(
+        &mut self,
+        scenario: &PreparedScenario,
+        run: &ScenarioRun,
+    ) -> Result<&ScenarioRecord> {
+        let results = &run.results;
+        let first = results.first().context("no iterations were run")?;
+        let samples: Vec<ScenarioTimingRecord> = results
+            .iter()
+            .map(|result| ScenarioTimingRecord::from_timings(&result.timings))
+            .collect();
+        let statistics = if samples.len() > 1 {
+            ScenarioTimingRecord::statistics(&samples, slug_seed(&scenario.slug))
+        } else {
+            Vec::new()
+        };
+
+        let mut record = ScenarioRecord {
+            slug: scenario.slug.clone(),
+            full_slug: scenario.full_slug.clone(),
+            ready_marker: scenario.ready_marker.clone(),
+            payload_value: scenario.payload_value,
+            scenario: scenario.scenario,
+            custom_axes: scenario.custom_axes(),
+            timings: ScenarioTimingRecord::median(&samples),
+            lockfile_hash: Some(first.lockfile_hash.clone()),
+            invalid: results.iter().find_map(|result| result.invalid.clone()),
+            sccache: results.last().and_then(|result| result.sccache),
+            wasm: first.wasm,
+            iterations: if samples.len() > 1 {
+                samples
+            } else {
+                Vec::new()
+            },
+            statistics,
+            outliers: run.outliers.clone(),
+            environment_hash: scenario.environment_hash.clone(),
+            disk: first.disk,
+            warm_deps: results.iter().any(|result| result.warm_deps),
+            restored_target: results.iter().any(|result| result.restored_target),
+            clean_mode: first.clean_mode,
+            mutation: scenario.mutation,
+            edit_target: scenario.edit_target,
+            nice: first.nice,
+            memory_limit: memory_limit::current().map(|limit| limit.bytes),
+            workspace_root: scenario.root.as_ref().map(|root| root.name.clone()),
+            toolchain: scenario.toolchain.clone(),
+            filesystem: first.filesystem.clone(),
+            defender_scanned: first.defender_scanned,
+            bloat: first.bloat.clone(),
+            llvm_lines: first.llvm_lines.clone(),
+            crate_timings: first.crate_timings.clone(),
+            cache_growth: None,
+            error: None,
+            failure: None,
+        };
+        if let Some(stats) = &record.sccache {
+            let mut growth = self
+                .record
+                .scenarios
+                .iter()
+                .rev()
+                .find_map(|record| record.cache_growth)
+                .unwrap_or_default();
+            growth.add(stats);
+            record.cache_growth = Some(growth);
+        }
+        self.output.write_timings(&record)?;
+        self.record.scenarios.push(record);
+        self.flush()?;
+        Ok(self
+            .record
+            .scenarios
+            .last()
+            .expect("record was just pushed"))
+    }
+
+    fn push_failure(
+        &mut self,
+        scenario: &PreparedScenario,
+        error: &anyhow::Error,
+        failure: FailureKind,
+    ) -> Result<()> {
+        self.record.failed = true;
+        let record = ScenarioRecord {
+            slug: scenario.slug.clone(),
+            full_slug: scenario.full_slug.clone(),
+            ready_marker: scenario.ready_marker.clone(),
+            payload_value: scenario.payload_value,
+            scenario: scenario.scenario,
+            custom_axes: scenario.custom_axes(),
+            timings: ScenarioTimingRecord::default(),
+            lockfile_hash: None,
+            invalid: None,
+            sccache: None,
+            wasm: None,
+            iterations: Vec::new(),
+            statistics: Vec::new(),
+            outliers: Vec::new(),
+            environment_hash: None,
+            disk: None,
+            warm_deps: false,
+            restored_target: false,
+            clean_mode: CleanMode::default(),
+            mutation: scenario.mutation,
+            edit_target: scenario.edit_target,
+            nice: None,
+            memory_limit: memory_limit::current().map(|limit| limit.bytes),
+            workspace_root: scenario.root.as_ref().map(|root| root.name.clone()),
+            toolchain: scenario.toolchain.clone(),
+            filesystem: None,
+            defender_scanned: None,
+            bloat: None,
+            llvm_lines: None,
+            crate_timings: None,
+            cache_growth: None,
+            error: Some(format!("{error:?}")),
+            failure: Some(failure),
+        };
+        self.output.write_timings(&record)?;
+        self.record.scenarios.push(record);
+        self.flush()
+    }
+
+    fn flush(&self) -> Result<()> {
+        let ron =
+            ron::ser::to_string_pretty(&self.record, ron::ser::PrettyConfig::new().depth_limit(4))
+                .context("failed to serialize run record")?;
+        fs::write(&self.path, redact::redact(&ron).as_bytes())
+            .context("failed to write results file")
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn set_calibration(&mut self, calibration: Calibration) -> Result<()> {
+        self.record.calibration = Some(calibration);
+        self.flush()
+    }
+
+    /// Drops the record for `slug` so it can be pushed again; the log is
+    /// rewritten by the next push.
+    fn retract(&mut self, slug: &str) {
+        self.record.scenarios.retain(|record| record.slug != slug);
+    }
+
+    fn records(&self) -> &[ScenarioRecord] {
+        &self.record.scenarios
+    }
+}
+
+impl ScenarioTimingRecord {
+    fn from_timings(timings: &ScenarioTimings) -> Self {
+        Self {
+            phases: timings.phases.iter().map(PhaseRecord::from).collect(),
+            hotpatch_verified: timings.hotpatch_verified,
+        }
+    }
+
+    /// Name and seconds of each measured phase, in the order they ran.
+    fn phases(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.phases
+            .iter()
+            .map(|phase| (phase.name.as_str(), phase.seconds))
+    }
+
+    fn phase(&self, name: &str) -> Option<f64> {
+        self.phases()
+            .find(|(phase, _)| *phase == name)
+            .map(|(_, seconds)| seconds)
+    }
+
+    /// Names of the phases measured in any of `samples`, in the order they
+    /// ran.
+    fn phase_names(samples: &[ScenarioTimingRecord]) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for (name, _) in samples.iter().flat_map(ScenarioTimingRecord::phases) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Per-phase median across iterations. Exit codes are kept where every
+    /// iteration agrees, and each metric is the median of its values.
+    fn median(samples: &[ScenarioTimingRecord]) -> Self {
+        let phases = Self::phase_names(samples)
+            .into_iter()
+            .map(|name| {
+                let records: Vec<&PhaseRecord> = samples
+                    .iter()
+                    .flat_map(|sample| &sample.phases)
+                    .filter(|phase| phase.name == name)
+                    .collect();
+                let seconds: Vec<f64> = records.iter().map(|phase| phase.seconds).collect();
+                let exit_code = records[0]
+                    .exit_code
+                    .filter(|code| records.iter().all(|phase| phase.exit_code == Some(*code)));
+                let mut metrics: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+                for (metric, value) in records.iter().flat_map(|phase| &phase.metrics) {
+                    metrics.entry(metric.clone()).or_default().push(*value);
+                }
+                PhaseRecord {
+                    name: name.to_string(),
+                    seconds: stats::median(&seconds),
+                    exit_code,
+                    metrics: metrics
+                        .into_iter()
+                        .map(|(metric, values)| (metric, stats::median(&values)))
+                        .collect(),
+                }
+            })
+            .collect();
+        Self {
+            phases,
+            // Only verified if every iteration was.
+            hotpatch_verified: samples
+                .iter()
+                .filter_map(|t| t.hotpatch_verified)
+                .reduce(|all, verified| all && verified),
+        }
+    }
+
+    fn statistics(samples: &[ScenarioTimingRecord], seed: u64) -> Vec<PhaseStatistics> {
+        Self::phase_names(samples)
+            .into_iter()
+            .map(|phase| {
+                let values: Vec<f64> = samples
+                    .iter()
+                    .filter_map(|sample| sample.phase(phase))
+                    .collect();
+                PhaseStatistics::new(phase, &values, seed)
+            })
+            .collect()
+    }
+}
+
+impl ScenarioRecord {
+    /// Built-in and custom axis name/value pairs of this scenario.
+    fn axes(&self) -> Vec<(String, String)> {
+        self.scenario
+            .axes()
+            .iter()
+            .map(|(axis, value)| (axis.to_string(), value.to_string()))
+            .chain(
+                self.toolchain
+                    .iter()
+                    .map(|toolchain| ("toolchain".to_string(), toolchain.clone())),
+            )
+            .chain(self.custom_axes.iter().cloned())
+            .chain(
+                self.workspace_root
+                    .iter()
+                    .map(|root| ("workspace-root".to_string(), root.clone())),
+            )
+            .collect()
+    }
+
+    /// Every measurement of `phase`: one per iteration, or the single timing
+    /// for runs recorded with one iteration.
+    fn phase_samples(&self, phase: &str) -> Vec<f64> {
+        if self.iterations.is_empty() {
+            self.timings.phase(phase).into_iter().collect()
+        } else {
+            self.iterations
+                .iter()
+                .filter_map(|sample| sample.phase(phase))
+                .collect()
+        }
+    }
+}
+
+/// Stable seed for per-slug randomness such as bootstrap resampling.
+fn slug_seed(slug: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    slug.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl FailureKind {
+    fn classify(error: &anyhow::Error) -> Self {
+        // A build killed by a forwarded signal fails like any other build.
+        if process_tree::interrupted() {
+            return Self::Interrupted;
+        }
+        // The harness stops a tool that takes too long with SIGKILL, which
+        // its output then reports like an OOM kill.
+        if error
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<BenchError>())
+            .any(BenchError::is_harness_timeout)
+        {
+            return Self::Timeout;
+        }
+        // Added as context, so it is not a cause of its own in the chain.
+        if let Some(BenchError::OutOfMemory { .. }) = error.downcast_ref::<BenchError>() {
+            return Self::OutOfMemory;
+        }
+        let mut text = String::new();
+        for cause in error.chain() {
+            match cause.downcast_ref::<BenchError>() {
+                Some(BenchError::ToolMissing { .. }) => return Self::ToolchainMissing,
+                Some(BenchError::Interrupted) => return Self::Interrupted,
+                _ => {}
+            }
+            // `CapturedOutput` renders the tool's last output lines as well.
+            text.push_str(&cause.to_string());
+            text.push('\n');
+        }
+        Self::classify_text(&text)
+    }
+
+    fn classify_text(text: &str) -> Self {
+        const PATTERNS: &[(FailureKind, &[&str])] = &[
+            (
+                FailureKind::ToolchainMissing,
+                &[
+                    "is not installed",
+                    "no such command",
+                    "no default toolchain set",
+                    "is not recognized as an internal or external command",
+                    "Android NDK not found",
+                    "command not found",
+                ],
+            ),
+            (
+                FailureKind::LinkerNotFound,
+                &[
+                    "linker `",
+                    "could not exec the linker",
+                    "error: linker",
+                    "link.exe` not found",
+                ],
+            ),
+            (
+                FailureKind::OutOfMemory,
+                &[
+                    "memory allocation of",
+                    "out of memory",
+                    "SIGKILL",
+                    "signal: 9",
+                    "STATUS_NO_MEMORY",
+                    "0xc0000017",
+                ],
+            ),
+            (FailureKind::Timeout, &["timeout waiting for", "timed out"]),
+            (
+                FailureKind::CompileError,
+                &["error[E", "error: could not compile"],
+            ),
+            (
+                FailureKind::DxProtocolMismatch,
+                &[
+                    "unexpected argument",
+                    "unrecognized subcommand",
+                    "version mismatch",
+                    "incompatible version",
+                ],
+            ),
+        ];
+
+        PATTERNS
+            .iter()
+            .find(|(_, needles)| needles.iter().any(|needle| text.contains(needle)))
+            .map(|(kind, _)| *kind)
+            .unwrap_or(Self::Unknown)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::ToolchainMissing => "toolchain-missing",
+            Self::LinkerNotFound => "linker-not-found",
+            Self::OutOfMemory => "out-of-memory",
+            Self::Timeout => "timeout",
+            Self::DxProtocolMismatch => "dx-protocol-mismatch",
+            Self::CompileError => "compile-error",
+            Self::Interrupted => "interrupted",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+fn write_workspace_files(root: &Path, code: &Code) -> Result<()> {
+    fs::create_dir_all(root.join(".cargo")).map_err(BenchError::workspace_io(
+        "create .cargo directory in temporary workspace",
+    ))?;
+
+    fs::write(root.join("Cargo.toml"), &code.cargo_toml)
+        .map_err(BenchError::workspace_io("write Cargo.toml"))?;
+    let sources = std::iter::once((code.payload_file, &code.src_main_rs)).chain(
+        code.entry_files
+            .iter()
+            .map(|(file, contents)| (*file, contents)),
+    );
+    for (file, contents) in sources {
+        let path = root.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(BenchError::workspace_io(format!(
+                "create the directory of {file} in temporary workspace"
+            )))?;
+        }
+        fs::write(&path, contents)
+            .map_err(BenchError::workspace_io(format!("write generated {file}")))?;
+    }
+    fs::write(
+        root.join(".cargo").join("config.toml"),
+        &code.cargo_config_toml,
+    )
+    .map_err(BenchError::workspace_io("write .cargo/config.toml"))?;
+    fs::write(root.join("rust-toolchain.toml"), &code.rust_toolchain_toml)
+        .map_err(BenchError::workspace_io("write rust-toolchain.toml"))?;
+
+    Ok(())
+}
+
+fn prepare_scenarios(matrix: &Matrix) -> Result<Vec<PreparedScenario>> {
+    let combinations = axes::combinations(&matrix.custom);
+    let mut prepared = Vec::new();
+    for scenario in enumerate_scenarios(matrix) {
+        for toolchain in &matrix.toolchains {
+            let mut scenario = scenario;
+            if let Some(toolchain) = toolchain {
+                scenario.channel = Channel::of_toolchain(toolchain);
+                // Nightly-only axes drop out for stable, beta, and versions.
+                if !scenario.is_supported() {
+                    continue;
+                }
+            }
+            for custom in &combinations {
+                for root in &matrix.roots {
+                    let axes = scenario_axes(&scenario, toolchain, custom, root);
+                    if !matrix
+                        .constraints
+                        .iter()
+                        .all(|constraint| constraint.allows(&axes))
+                    {
+                        continue;
+                    }
+                    let mut scenario = PreparedScenario::new(
+                        scenario,
+                        toolchain.clone(),
+                        custom.clone(),
+                        root.clone(),
+                        &matrix.patches,
+                        &matrix.slug_scheme,
+                        matrix.edit_target,
+                    )?;
+                    scenario.mutation = matrix.mutation;
+                    if !matrix.cargo_config.is_empty() {
+                        scenario.code.cargo_config_toml = merge_cargo_config(
+                            &matrix.cargo_config,
+                            &scenario.code.cargo_config_toml,
+                        )
+                        .with_context(|| {
+                            format!("failed to merge [cargo_config] into {}", scenario.slug)
+                        })?;
+                    }
+                    prepared.push(scenario);
+                }
+            }
+        }
+    }
+    prepared.retain(|scenario| {
+        let tags = scenario.tags();
+        (matrix.tags.is_empty() || matrix.tags.iter().any(|tag| tags.contains(tag)))
+            && !matrix.skip_tags.iter().any(|tag| tags.contains(tag))
+    });
+    slug::check_collisions(
+        prepared
+            .iter()
+            .map(|scenario| (scenario.slug.as_str(), scenario.describe())),
+    )?;
+    Ok(prepared)
+}
+
+fn enumerate_scenarios(matrix: &Matrix) -> Vec<Scenario> {
+    let scenarios = vec![Scenario::default()];
+    let scenarios = expand_axis(scenarios, &matrix.linkers, |s, v| s.linker = v);
+    let scenarios = expand_axis(scenarios, &matrix.caches, |s, v| s.cache = v);
+    let scenarios = expand_axis(scenarios, &matrix.dynamics, |s, v| s.dynamic = v);
+    let scenarios = expand_axis(scenarios, &matrix.hotpatches, |s, v| s.hotpatching = v);
+    let scenarios = expand_axis(scenarios, &matrix.targets, |s, v| s.target = v);
+    let scenarios = expand_axis(scenarios, &matrix.build_stds, |s, v| s.build_std = v);
+    let scenarios = expand_axis(scenarios, &matrix.traces, |s, v| s.trace = v);
+    let scenarios = expand_axis(scenarios, &matrix.window_backends, |s, v| {
+        s.window_backend = v
+    });
+    let scenarios = expand_axis(scenarios, &matrix.registries, |s, v| s.registry = v);
+    let scenarios = expand_axis(scenarios, &matrix.resolvers, |s, v| s.resolver = v);
+    let scenarios = expand_axis(scenarios, &matrix.profiles, |s, v| s.profile = v);
+    let scenarios = expand_axis(scenarios, &matrix.debug_assertions, |s, v| {
+        s.debug_assertions = v
+    });
+    // Only scenarios with a compiler cache keep their warm copy.
+    let scenarios = expand_axis(scenarios, &matrix.warm_caches, |s, v| s.warm_cache = v);
+
+    scenarios
+        .into_iter()
+        .filter(Scenario::is_supported)
+        .collect()
+}
+
+/// Crosses every scenario with every value of one axis, keeping the earlier
+/// axes as the outer loop.
+fn expand_axis<T: Copy>(
+    scenarios: Vec<Scenario>,
+    values: &[T],
+    set: impl Fn(&mut Scenario, T),
+) -> Vec<Scenario> {
+    let mut expanded = Vec::with_capacity(scenarios.len() * values.len());
+    for scenario in scenarios {
+        for &value in values {
+            let mut scenario = scenario;
+            set(&mut scenario, value);
+            expanded.push(scenario);
+        }
+    }
+    expanded
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self {
+            // On macOS the default is Apple's new linker, so the comparison is
+            // against the classic one and lld instead of rust-lld.
+            linkers: if cfg!(target_os = "macos") {
+                vec![None, Some(Linker::LdClassic), Some(Linker::MacLld)]
+            } else {
+                vec![None, Some(Linker::RustLld)]
+            },
+            caches: vec![None, Some(Cache::DisableIncremental), Some(Cache::Sscache)],
+            dynamics: vec![
+                None,
+                Some(Dynamic::DynamicLinking),
+                Some(Dynamic::ShareGenerics),
+            ],
+            hotpatches: vec![None],
+            targets: vec![None],
+            build_stds: vec![None],
+            traces: vec![None],
+            window_backends: vec![None],
+            registries: vec![None],
+            resolvers: vec![None],
+            profiles: vec![None],
+            debug_assertions: vec![None],
+            warm_caches: vec![false],
+            toolchains: vec![None],
+            custom: Vec::new(),
+            constraints: Vec::new(),
+            roots: vec![None],
+            patches: Patches::default(),
+            cargo_config: toml::Table::new(),
+            mutation: Mutation::default(),
+            edit_target: EditTarget::default(),
+            tags: Vec::new(),
+            skip_tags: Vec::new(),
+            slug_scheme: SlugScheme::Full,
+        }
+    }
+}
+
+impl Matrix {
+    fn from_args(args: &RunArgs, config: &Config) -> Self {
+        let mut matrix = Self::default();
+        if !args.linkers.is_empty() {
+            matrix.linkers = dedup_values(&args.linkers);
+        }
+        if !args.caches.is_empty() {
+            matrix.caches = dedup_values(&args.caches);
+        } else if config.wrapper.is_some() {
+            matrix.caches.push(Some(Cache::Wrapper));
+        }
+        if !args.dynamics.is_empty() {
+            matrix.dynamics = dedup_values(&args.dynamics);
+        }
+        if args.hotpatch && !args.no_hotpatch {
+            matrix.hotpatches.push(Some(Hotpatching::Dx));
+        }
+        matrix
+            .targets
+            .extend(args.targets.iter().copied().map(Some));
+        if args.build_std {
+            matrix.build_stds.push(Some(BuildStd::Std));
+        }
+        for &trace in &args.traces {
+            if !matrix.traces.contains(&Some(trace)) {
+                matrix.traces.push(Some(trace));
+            }
+        }
+        if !args.toolchains.is_empty() {
+            matrix.toolchains = Vec::new();
+            for toolchain in &args.toolchains {
+                if !matrix.toolchains.contains(&Some(toolchain.clone())) {
+                    matrix.toolchains.push(Some(toolchain.clone()));
+                }
+            }
+        }
+        for &backend in &args.window_backends {
+            if !matrix.window_backends.contains(&Some(backend)) {
+                matrix.window_backends.push(Some(backend));
+            }
+        }
+        for &registry in &args.registries {
+            if !matrix.registries.contains(&Some(registry)) {
+                matrix.registries.push(Some(registry));
+            }
+        }
+        for &resolver in &args.resolvers {
+            if !matrix.resolvers.contains(&Some(resolver)) {
+                matrix.resolvers.push(Some(resolver));
+            }
+        }
+        for &profile in &args.profiles {
+            if !matrix.profiles.contains(&Some(profile)) {
+                matrix.profiles.push(Some(profile));
+            }
+        }
+        if args.warm_cache {
+            matrix.warm_caches = vec![false, true];
+        }
+        for &assertions in &args.debug_assertions {
+            if !matrix.debug_assertions.contains(&Some(assertions)) {
+                matrix.debug_assertions.push(Some(assertions));
+            }
+        }
+        matrix.custom = config.axes.clone();
+        matrix.constraints = config.constraints.clone();
+        matrix.patches = config.patch.clone();
+        matrix.cargo_config = config.cargo_config.clone();
+        matrix.mutation = args.mutation;
+        matrix.edit_target = args.edit_in;
+        matrix.tags = args.tags.clone();
+        matrix.skip_tags = args.skip_tags.clone();
+        matrix.slug_scheme = args.slug_scheme.clone();
+        if !args.workspace_roots.is_empty() {
+            matrix.roots = args.workspace_roots.iter().cloned().map(Some).collect();
+        }
+        matrix
+    }
+}
+
+/// Axis values as given on the command line, without repeats.
+fn dedup_values<T: Copy + PartialEq>(values: &[T]) -> Vec<T> {
+    let mut unique = Vec::with_capacity(values.len());
+    for &value in values {
+        if !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+
+/// Axis name/value pairs of a scenario before it is prepared.
+fn scenario_axes(
+    scenario: &Scenario,
+    toolchain: &Option<String>,
+    custom: &[AxisChoice],
+    root: &Option<WorkspaceRoot>,
+) -> Vec<(String, String)> {
+    scenario
+        .axes()
+        .iter()
+        .map(|(axis, value)| (axis.to_string(), value.to_string()))
+        .chain(
+            toolchain
+                .iter()
+                .map(|toolchain| ("toolchain".to_string(), toolchain.clone())),
+        )
+        .chain(
+            custom
+                .iter()
+                .map(|choice| (choice.axis.clone(), choice.value.name.clone())),
+        )
+        .chain(
+            root.iter()
+                .map(|root| ("workspace-root".to_string(), root.name.clone())),
+        )
+        .collect()
+}
+
+impl PreparedScenario {
+    fn new(
+        scenario: Scenario,
+        toolchain: Option<String>,
+        custom: Vec<AxisChoice>,
+        root: Option<WorkspaceRoot>,
+        patches: &Patches,
+        scheme: &SlugScheme,
+        edit_target: EditTarget,
+    ) -> Result<Self> {
+        let mut slug = scenario.slug();
+        // The channel is already in the slug, e.g. `-stable`.
+        if let Some(toolchain) = &toolchain
+            && toolchain != scenario.channel.label()
+        {
+            slug.push('-');
+            slug.push_str(&toolchain_label(toolchain));
+        }
+        for choice in &custom {
+            slug.push('-');
+            slug.push_str(&choice.value.name);
+        }
+        if let Some(root) = &root {
+            slug.push('-');
+            slug.push_str(&root.name);
+        }
+        let full_slug = slug;
+        let slug = scheme.render(
+            &full_slug,
+            &scenario_axes(&scenario, &toolchain, &custom, &root),
+        )?;
+        let full_slug = (slug != full_slug).then_some(full_slug);
+        let seed = scenario.payload_seed();
+        let ready_marker = ready_marker(&slug, seed);
+        let payload_value = payload_value(seed);
+        let mut code = Code::for_scenario(
+            &scenario,
+            &custom,
+            patches,
+            &slug,
+            &ready_marker,
+            payload_value,
+            edit_target,
+        );
+        if let Some(toolchain) = &toolchain {
+            code.rust_toolchain_toml = build_toolchain(&scenario, toolchain);
+        }
+
+        Ok(Self {
+            scenario,
+            toolchain,
+            custom,
+            root,
+            slug,
+            full_slug,
+            ready_marker,
+            payload_value,
+            mutation: Mutation::default(),
+            edit_target,
+            code,
+            environment_hash: None,
+        })
+    }
+}
+
+impl PreparedScenario {
+    fn describe(&self) -> String {
+        let mut description = self.scenario.describe();
+        for (axis, value) in self.custom_axes() {
+            description.push_str(&format!(", {axis}={value}"));
+        }
+        description
+    }
+
+    /// Hash of everything that affects how dependencies are compiled: the
+    /// generated files with the slug (package name, target dir) removed.
+    fn compile_settings_hash(&self) -> String {
+        let without_slug = |text: &str| text.replace(&self.slug, "");
+        let mut hasher = DefaultHasher::new();
+        without_slug(&self.code.cargo_config_toml).hash(&mut hasher);
+        without_slug(&self.code.cargo_toml).hash(&mut hasher);
+        self.code.rust_toolchain_toml.hash(&mut hasher);
+        self.scenario.target.hash(&mut hasher);
+        self.cargo_args().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Built-in and custom axis name/value pairs, as recorded in
+    /// [`ScenarioRecord::axes`].
+    fn axes(&self) -> Vec<(String, String)> {
+        scenario_axes(&self.scenario, &self.toolchain, &self.custom, &self.root)
+    }
+
+    /// Built-in tags of the scenario plus those of its custom axis values,
+    /// sorted and without repeats.
+    fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .scenario
+            .tags()
+            .into_iter()
+            .map(str::to_string)
+            .chain(
+                self.custom
+                    .iter()
+                    .flat_map(|choice| choice.value.tags.iter().cloned()),
+            )
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Extra `cargo` arguments from the scenario's custom axis values.
+    fn cargo_args(&self) -> Vec<&str> {
+        self.custom
+            .iter()
+            .flat_map(|choice| &choice.value.cargo_args)
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn custom_axes(&self) -> Vec<(String, String)> {
+        self.custom
+            .iter()
+            .map(|choice| (choice.axis.clone(), choice.value.name.clone()))
+            .collect()
+    }
+
+    /// Payload source for this scenario with a different payload constant.
+    /// The payload after the `--mutation` edit that makes it report
+    /// `new_value`.
+    fn mutated_source(&self, new_value: u64) -> String {
+        build_payload_source(
+            &self.ready_marker,
+            &self.mutation.edit(self.payload_value, new_value),
+            self.scenario.payload_is_library(),
+            self.edit_target,
+        )
+    }
+
+    /// Packages of the payload's workspace, the payload crate first.
+    fn packages(&self) -> Vec<String> {
+        let mut packages = vec![format!("bench-payload-{}", self.slug)];
+        if self.edit_target == EditTarget::LeafCrate {
+            packages.push(format!("bench-payload-leaf-{}", self.slug));
+        }
+        packages
+    }
+}
+
+impl Scenario {
+    fn slug(&self) -> String {
+        let parts = [
+            match self.linker {
+                Some(Linker::RustLld) => "rust-lld",
+                Some(Linker::LdClassic) => "ld-classic",
+                Some(Linker::MacLld) => "lld",
+                Some(Linker::Zig) => "zig",
+                Some(Linker::Gold) => "gold",
+                None => "default-linker",
+            },
+            match self.cache {
+                Some(Cache::DisableIncremental) => "no-incremental",
+                Some(Cache::Sscache) => "sscache",
+                Some(Cache::Wrapper) => "wrapper",
+                Some(Cache::Cachepot) => "cachepot",
+                None => "incremental",
+            },
+            match self.dynamic {
+                Some(Dynamic::DynamicLinking) => "dynamic-linking",
+                Some(Dynamic::ShareGenerics) => "share-generics",
+                None => "default-dynamic",
+            },
+            match self.hotpatching {
+                Some(Hotpatching::Dx) => "dx-hotpatch",
+                None => "no-hotpatch",
+            },
+        ];
+
+        let mut slug = parts.join("-");
+        // Host nightly builds keep the original four-part slug so history
+        // stays valid.
+        if let Some(target) = self.target {
+            slug.push('-');
+            slug.push_str(target.slug_label());
+        }
+        if self.channel != Channel::Nightly {
+            slug.push('-');
+            slug.push_str(self.channel.label());
+        }
+        if self.build_std.is_some() {
+            slug.push_str("-build-std");
+        }
+        if self.trace.is_some() {
+            slug.push('-');
+            slug.push_str(self.trace_label());
+        }
+        if let Some(backend) = self.window_backend {
+            slug.push('-');
+            slug.push_str(backend.label());
+        }
+        if let Some(registry) = self.registry {
+            slug.push('-');
+            slug.push_str(registry.label());
+            slug.push_str("-registry");
+        }
+        if let Some(resolver) = self.resolver {
+            slug.push_str("-resolver");
+            slug.push_str(resolver.label());
+        }
+        if let Some(profile) = self.profile {
+            slug.push('-');
+            slug.push_str(profile.label());
+        }
+        if let Some(assertions) = self.debug_assertions {
+            slug.push_str("-assertions-");
+            slug.push_str(assertions.label());
+        }
+        if self.warm_cache {
+            slug.push_str("-warm-cache");
+        }
+        slug
+    }
+
+    /// Whether the combination can be benchmarked at all; see `validate`.
+    fn is_supported(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Name of the Cargo profile the payload builds with, which is also its
+    /// directory under the target directory.
+    fn profile_name(&self) -> &'static str {
+        self.profile.map(Profile::label).unwrap_or("dev")
+    }
+
+    /// Android loads the payload as a shared library instead of a binary.
+    fn payload_is_library(&self) -> bool {
+        matches!(self.target, Some(Target::Aarch64Android))
+    }
+
+    fn payload_seed(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn describe(&self) -> String {
+        self.axes()
+            .iter()
+            .map(|(axis, value)| format!("{axis}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Every value each axis can take, as printed by `bench list`.
+    fn axis_values() -> BTreeMap<&'static str, Vec<&'static str>> {
+        use clap::ValueEnum;
+
+        let base = Self::default();
+        let linkers = [
+            Linker::RustLld,
+            Linker::LdClassic,
+            Linker::MacLld,
+            Linker::Zig,
+            Linker::Gold,
+        ];
+        let caches = [
+            Cache::DisableIncremental,
+            Cache::Sscache,
+            Cache::Wrapper,
+            Cache::Cachepot,
+        ];
+        let scenarios = [base]
+            .into_iter()
+            .chain(linkers.map(|linker| Self {
+                linker: Some(linker),
+                ..base
+            }))
+            .chain(caches.map(|cache| Self {
+                cache: Some(cache),
+                ..base
+            }))
+            .chain(
+                [Dynamic::DynamicLinking, Dynamic::ShareGenerics].map(|dynamic| Self {
+                    dynamic: Some(dynamic),
+                    ..base
+                }),
+            )
+            .chain([Self {
+                hotpatching: Some(Hotpatching::Dx),
+                channel: Channel::Stable,
+                build_std: Some(BuildStd::Std),
+                warm_cache: true,
+                ..base
+            }])
+            .chain(Target::value_variants().iter().map(|target| Self {
+                target: Some(*target),
+                ..base
+            }))
+            .chain(Trace::value_variants().iter().map(|trace| Self {
+                trace: Some(*trace),
+                ..base
+            }))
+            .chain(WindowBackend::value_variants().iter().map(|backend| Self {
+                window_backend: Some(*backend),
+                ..base
+            }))
+            .chain(Registry::value_variants().iter().map(|registry| Self {
+                registry: Some(*registry),
+                ..base
+            }))
+            .chain(Resolver::value_variants().iter().map(|resolver| Self {
+                resolver: Some(*resolver),
+                ..base
+            }))
+            .chain(Profile::value_variants().iter().map(|profile| Self {
+                profile: Some(*profile),
+                ..base
+            }))
+            .chain(
+                DebugAssertions::value_variants()
+                    .iter()
+                    .map(|assertions| Self {
+                        debug_assertions: Some(*assertions),
+                        ..base
+                    }),
+            );
+        let mut values: BTreeMap<&'static str, Vec<&'static str>> = BTreeMap::new();
+        for (axis, value) in scenarios.flat_map(|scenario| scenario.axes()) {
+            let known = values.entry(axis).or_default();
+            if !known.contains(&value) {
+                known.push(value);
+            }
+        }
+        values
+    }
+
+    /// Each axis name with this scenario's value on it.
+    fn axes(&self) -> [(&'static str, &'static str); 14] {
+        [
+            ("linker", self.linker_label()),
+            ("cache", self.cache_label()),
+            ("dynamic", self.dynamic_label()),
+            ("hotpatch", self.hotpatch_label()),
+            (
+                "target",
+                self.target.map(|target| target.triple()).unwrap_or("host"),
+            ),
+            ("channel", self.channel.label()),
+            ("build-std", self.build_std_label()),
+            ("trace", self.trace_label()),
+            (
+                "window-backend",
+                self.window_backend
+                    .map(WindowBackend::label)
+                    .unwrap_or("default"),
+            ),
+            (
+                "registry",
+                self.registry.map(Registry::label).unwrap_or("default"),
+            ),
+            (
+                "resolver",
+                self.resolver.map(Resolver::label).unwrap_or("default"),
+            ),
+            ("profile", self.profile_name()),
+            (
+                "debug-assertions",
+                self.debug_assertions
+                    .map(DebugAssertions::label)
+                    .unwrap_or("default"),
+            ),
+            ("cache-state", if self.warm_cache { "warm" } else { "cold" }),
+        ]
+    }
+
+    fn linker_label(&self) -> &'static str {
+        match self.linker {
+            Some(Linker::RustLld) => "rust-lld",
+            Some(Linker::LdClassic) => "ld-classic",
+            Some(Linker::MacLld) => "lld",
+            Some(Linker::Zig) => "zig",
+            Some(Linker::Gold) => "gold",
+            None => "default",
+        }
+    }
+
+    fn cache_label(&self) -> &'static str {
+        match self.cache {
+            Some(Cache::DisableIncremental) => "no-incremental",
+            Some(Cache::Sscache) => "sscache",
+            Some(Cache::Wrapper) => "wrapper",
+            Some(Cache::Cachepot) => "cachepot",
+            None => "incremental",
+        }
+    }
+
+    fn dynamic_label(&self) -> &'static str {
+        match self.dynamic {
+            Some(Dynamic::DynamicLinking) => "dynamic-linking",
+            Some(Dynamic::ShareGenerics) => "share-generics",
+            None => "default",
+        }
+    }
+
+    fn hotpatch_label(&self) -> &'static str {
+        match self.hotpatching {
+            Some(Hotpatching::Dx) => "dx",
+            None => "none",
+        }
+    }
+
+    fn build_std_label(&self) -> &'static str {
+        match self.build_std {
+            Some(BuildStd::Std) => "std",
+            None => "off",
+        }
+    }
+
+    fn trace_label(&self) -> &'static str {
+        match self.trace {
+            Some(Trace::Trace) => "trace",
+            Some(Trace::Tracy) => "tracy",
+            None => "off",
+        }
+    }
+}
+
+impl Channel {
+    /// Nightly for `nightly` and dated nightlies; anything else is treated
+    /// as stable, so nightly-only axes are left out.
+    fn of_toolchain(toolchain: &str) -> Self {
+        if toolchain.starts_with("nightly") {
+            Self::Nightly
+        } else {
+            Self::Stable
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Nightly => "nightly",
+            Self::Stable => "stable",
+        }
+    }
+}
+
+impl Target {
+    fn triple(&self) -> &'static str {
+        match self {
+            Self::Aarch64LinuxGnu => "aarch64-unknown-linux-gnu",
+            Self::X86_64LinuxGnu => "x86_64-unknown-linux-gnu",
+            Self::X86_64LinuxMusl => "x86_64-unknown-linux-musl",
+            Self::X86_64WindowsMsvc => "x86_64-pc-windows-msvc",
+            Self::Aarch64Android => "aarch64-linux-android",
+            Self::Wasm32 => "wasm32-unknown-unknown",
+        }
+    }
+
+    fn slug_label(&self) -> &'static str {
+        match self {
+            Self::Aarch64LinuxGnu => "aarch64-linux",
+            Self::X86_64LinuxGnu => "x86_64-gnu",
+            Self::X86_64LinuxMusl => "x86_64-musl",
+            Self::X86_64WindowsMsvc => "windows-msvc",
+            Self::Aarch64Android => "android-arm64",
+            Self::Wasm32 => "wasm32",
+        }
+    }
+
+    /// Cargo subcommand that wraps `build` for this target, if any.
+    fn cargo_plugin(&self) -> &'static [&'static str] {
+        match self {
+            Self::Aarch64LinuxGnu | Self::X86_64LinuxGnu | Self::X86_64LinuxMusl | Self::Wasm32 => {
+                &[]
+            }
+            Self::X86_64WindowsMsvc => &["xwin"],
+            Self::Aarch64Android => &["ndk", "--target", "arm64-v8a"],
+        }
+    }
+
+    /// Whether `--target <triple>` must be passed to `build`; `cargo ndk`
+    /// selects the target through its own flag instead.
+    fn needs_target_flag(&self) -> bool {
+        !matches!(self, Self::Aarch64Android)
+    }
+
+    /// Linker to configure for the target, when rustc's default cannot work.
+    fn cross_linker(&self) -> Option<&'static str> {
+        match self {
+            Self::Aarch64LinuxGnu => Some("aarch64-linux-gnu-gcc"),
+            // The x86_64 Linux targets link with the host's `cc` (musl brings
+            // its own self-contained CRT objects), cargo-xwin and cargo-ndk
+            // configure their linkers themselves, and rustc ships rust-lld
+            // for wasm.
+            Self::X86_64LinuxGnu
+            | Self::X86_64LinuxMusl
+            | Self::X86_64WindowsMsvc
+            | Self::Aarch64Android
+            | Self::Wasm32 => None,
+        }
+    }
+}
+
+impl Code {
+    fn for_scenario(
+        scenario: &Scenario,
+        custom: &[AxisChoice],
+        patches: &Patches,
+        slug: &str,
+        ready_marker: &str,
+        payload_value: u64,
+        edit_target: EditTarget,
+    ) -> Self {
+        let library = scenario.payload_is_library();
+        let crate_root = if library { "src/lib.rs" } else { "src/main.rs" };
+        let mut entry_files = Vec::new();
+        if let Some(entry) = build_payload_entry(library, edit_target) {
+            entry_files.push((crate_root, entry));
+        }
+        if edit_target == EditTarget::LeafCrate {
+            entry_files.push(("leaf/Cargo.toml", build_leaf_cargo_toml(slug)));
+        }
+        Self {
+            cargo_config_toml: build_cargo_config(scenario, custom, slug),
+            payload_file: edit_target.payload_file(crate_root),
+            src_main_rs: build_payload_source(
+                ready_marker,
+                &PayloadEdit::original(payload_value),
+                library,
+                edit_target,
+            ),
+            entry_files,
+            cargo_toml: build_cargo_toml(
+                scenario,
+                custom,
+                patches,
+                slug,
+                &BevySource::Release,
+                &BevyFeatures::Default,
+                edit_target,
+            ),
+            rust_toolchain_toml: build_toolchain(scenario, scenario.channel.label()),
+        }
+    }
+}
+
+/// A toolchain name as it appears in slugs and package names, e.g. `1-80-0`
+/// for `1.80.0`.
+fn toolchain_label(toolchain: &str) -> String {
+    toolchain
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn ready_marker(slug: &str, seed: u64) -> String {
+    format!("PAYLOAD_SYSTEM_IS_READY__{slug}__{seed:016x}")
+}
+
+fn payload_value(seed: u64) -> u64 {
+    seed.rotate_left(17) ^ 0x9e37_79b9_7f4a_7c15
+}
+
+/// The crate root of a payload whose source lives elsewhere: a `main` that
+/// calls its `run`. `None` when the crate root is the payload source.
+fn build_payload_entry(library: bool, edit_target: EditTarget) -> Option<String> {
+    let (visibility, entry_attribute) = entry_point(library, "bevy::prelude::");
+    let (declaration, run) = match edit_target {
+        EditTarget::Main => return None,
+        EditTarget::Module => ("mod payload;\n\n", "payload::run"),
+        EditTarget::LeafCrate => ("", "leaf::run"),
+    };
+    Some(format!(
+        "{declaration}{entry_attribute}{visibility}fn main() {{\n    {run}();\n}}\n"
+    ))
+}
+
+/// Visibility and attribute of `main`, with the path `bevy_main` is found
+/// under.
+fn entry_point(library: bool, path: &str) -> (&'static str, String) {
+    // Android's activity glue calls the `#[bevy_main]` entry point from the cdylib.
+    if library {
+        ("pub ", format!("#[{path}bevy_main]\n"))
+    } else {
+        ("", String::new())
+    }
+}
+
+/// The payload source edits change; with `--edit-in main` also the crate
+/// root with `main`.
+fn build_payload_source(
+    ready_marker: &str,
+    edit: &PayloadEdit,
+    library: bool,
+    edit_target: EditTarget,
+) -> String {
+    let main = if edit_target == EditTarget::Main {
+        let (visibility, entry_attribute) = entry_point(library, "");
+        format!("{entry_attribute}{visibility}fn main() {{\n    run();\n}}\n\n")
+    } else {
+        String::new()
+    };
+    let (harness_address_env, harness_protocol_version) =
+        (protocol::ADDRESS_ENV, protocol::VERSION);
+    let first_frame_marker = first_frame::MARKER;
+    let PayloadEdit {
+        constant,
+        value,
+        report,
+        items,
+    } = edit;
+    format!(
+        r#"use bevy::prelude::*;
+
+const READY_MARKER: &str = "{ready_marker}";
+const PAYLOAD_RANDOM_VALUE: u64 = {constant};
+const MARKER_FILE_ENV: &str = "{MARKER_FILE_ENV}";
+const HARNESS_ADDRESS_ENV: &str = "{harness_address_env}";
+const HARNESS_PROTOCOL_VERSION: u32 = {harness_protocol_version};
+
+static STARTED: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+
+{main}pub fn run() {{
+    STARTED.get_or_init(std::time::Instant::now);
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, announce_ready)
+        .add_systems(Update, (report_payload, report_frame_time, heartbeat))
+        .add_systems(Last, report_first_frame)
+        .run();
+}}
+
+fn announce_ready() {{
+    if !harness::send(&format!("READY {{}}", READY_MARKER)) {{
+        mark(READY_MARKER);
+    }}
+}}
+
+// `Last` is the end of the first frame's main-world update.
+fn report_first_frame(mut reported: Local<bool>) {{
+    if *reported {{
+        return;
+    }}
+    *reported = true;
+    let milliseconds = STARTED.get().map_or(0.0, |started| started.elapsed().as_secs_f64() * 1000.0);
+    if !harness::send(&format!("FIRST_FRAME {{milliseconds:.1}}")) {{
+        mark(&format!("{first_frame_marker}{{milliseconds:.1}}"));
+    }}
+}}
+
+fn report_frame_time(time: Res<Time>) {{
+    harness::send(&format!("FRAME {{:.3}}", time.delta_secs_f64() * 1000.0));
+}}
+
+// Client of the harness protocol, used when the harness passes an address.
+mod harness {{
+    use std::io::{{BufRead, BufReader, Write}};
+    use std::net::TcpStream;
+    use std::sync::{{Mutex, OnceLock}};
+
+    static CONNECTION: OnceLock<Option<Mutex<TcpStream>>> = OnceLock::new();
+
+    fn connect() -> Option<Mutex<TcpStream>> {{
+        let address = std::env::var(super::HARNESS_ADDRESS_ENV).ok()?;
+        let mut stream = TcpStream::connect(address).ok()?;
+        writeln!(stream, "HELLO {{}}", super::HARNESS_PROTOCOL_VERSION).ok()?;
+        let mut reader = BufReader::new(stream.try_clone().ok()?);
+        let mut reply = String::new();
+        reader.read_line(&mut reply).ok()?;
+        if reply.trim() != "ACK" {{
+            eprintln!("harness refused the connection: {{}}", reply.trim());
+            return None;
+        }}
+        std::thread::spawn(move || {{
+            for line in reader.lines() {{
+                if line.as_deref().is_ok_and(|line| line == "BYE") {{
+                    std::process::exit(0);
+                }}
+            }}
+        }});
+        Some(Mutex::new(stream))
+    }}
+
+    // Whether the message reached a connected harness.
+    pub fn send(message: &str) -> bool {{
+        CONNECTION
+            .get_or_init(connect)
+            .as_ref()
+            .is_some_and(|stream| writeln!(stream.lock().unwrap(), "{{message}}").is_ok())
+    }}
+}}
+
+// Markers go to the harness's file when it asks for one, since dx does not
+// always forward the app's output.
+fn mark(line: &str) {{
+    use std::io::Write;
+
+    match std::env::var_os(MARKER_FILE_ENV) {{
+        Some(path) => {{
+            if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(path) {{
+                let _ = writeln!(file, "{{line}}");
+            }}
+        }}
+        None => println!("{{line}}"),
+    }}
+}}
+
+// Runs every frame so a hot-patched edit shows up without a restart.
+fn report_payload(mut commands: Commands, mut reported: Local<Option<u64>>) {{
+    let value: u64 = {value};
+    if *reported != Some(value) {{
+        *reported = Some(value);
+        {report}
+    }}
+}}
+
+fn report_value(value: u64) {{
+    if !harness::send(&format!("VALUE {{value}}")) {{
+        mark(&format!("PAYLOAD_RANDOM_VALUE={{value}}"));
+    }}
+}}
+
+fn heartbeat(mut ticks: Local<u32>) {{
+    *ticks += 1;
+    if *ticks % 600 == 0 {{
+        println!("PAYLOAD_HEARTBEAT::{{}}::{{}}", READY_MARKER, *ticks);
+    }}
+}}
+{items}"#
+    )
+}
+
+/// The `base` cargo config with the `generated` one on top: tables are merged
+/// key by key, and wherever both set a value the generated one wins.
+fn merge_cargo_config(base: &toml::Table, generated: &str) -> Result<String> {
+    let mut merged = base.clone();
+    let generated: toml::Table =
+        toml::from_str(generated).context("generated cargo config is not valid TOML")?;
+    merge_tables(&mut merged, generated);
+    toml::to_string(&merged).context("failed to serialize the merged cargo config")
+}
+
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn build_cargo_config(scenario: &Scenario, custom: &[AxisChoice], slug: &str) -> String {
+    let mut output = String::new();
+    output.push_str("[build]\n");
+    output.push_str(&format!("target-dir = \"target/{slug}\"\n"));
+    // Cargo only honours `RUSTC_WRAPPER` from its own environment, not from
+    // `[env]`, so the wrapper has to be configured here to take effect.
+    if let Some(server) = scenario.cache.and_then(Cache::server) {
+        output.push_str(&format!("rustc-wrapper = \"{}\"\n", server.program()));
+    }
+
+    // Like the wrapper, `RUSTFLAGS` in `[env]` never reaches rustc.
+    let mut rustflags: Vec<&str> = Vec::new();
+    if matches!(scenario.dynamic, Some(Dynamic::ShareGenerics)) {
+        rustflags.push("-Zshare-generics=y");
+    }
+    if let Some(flag) = scenario.linker.and_then(Linker::link_arg) {
+        rustflags.push(flag);
+    }
+    for choice in custom {
+        rustflags.extend(choice.value.rustflags.iter().map(String::as_str));
+    }
+    // `target.<triple>.rustflags` replaces `build.rustflags`, so wasm needs
+    // its own list below.
+    if !rustflags.is_empty() && scenario.target != Some(Target::Wasm32) {
+        output.push_str(&format!("rustflags = {}\n", toml_string_array(&rustflags)));
+    }
+
+    let mut env_lines: Vec<(&str, &str)> = Vec::new();
+    if let Some(cache) = scenario.cache {
+        match cache {
+            Cache::DisableIncremental => env_lines.push(("CARGO_INCREMENTAL", "0")),
+            Cache::Sscache | Cache::Cachepot => {
+                env_lines.push(("CARGO_INCREMENTAL", "0"));
+            }
+            Cache::Wrapper => {}
+        }
+    }
+
+    for choice in custom {
+        env_lines.extend(
+            choice
+                .value
+                .env
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+    }
+
+    if scenario.build_std.is_some() {
+        output.push_str("\n[unstable]\n");
+        output.push_str("build-std = [\"std\", \"panic_abort\"]\n");
+    }
+
+    if !env_lines.is_empty() {
+        output.push_str("\n[env]\n");
+        for (key, value) in env_lines {
+            output.push_str(&format!("{key} = {}\n", toml::Value::from(value)));
+        }
+    }
+
+    if let Some(registry) = scenario.registry {
+        output.push_str("\n[registries.crates-io]\n");
+        output.push_str(&format!("protocol = \"{}\"\n", registry.label()));
+    }
+
+    if matches!(scenario.linker, Some(Linker::RustLld)) {
+        output.push_str("\n[target.'cfg(all())']\n");
+        output.push_str("linker = \"rust-lld.exe\"\n");
+    }
+
+    // cargo-zigbuild configures `zig cc` for the target itself.
+    if let Some(target) = scenario.target
+        && scenario.linker != Some(Linker::Zig)
+        && let Some(linker) = target.cross_linker()
+    {
+        output.push_str(&format!("\n[target.{}]\n", target.triple()));
+        output.push_str(&format!("linker = \"{linker}\"\n"));
+    }
+
+    if scenario.target == Some(Target::Wasm32) {
+        // getrandom 0.3 needs its web backend selected explicitly.
+        let mut wasm_flags = vec!["--cfg", "getrandom_backend=\"wasm_js\""];
+        wasm_flags.extend(rustflags);
+        output.push_str("\n[target.wasm32-unknown-unknown]\n");
+        output.push_str(&format!("rustflags = {}\n", toml_string_array(&wasm_flags)));
+    }
+
+    output
+}
+
+/// A profile section's lines: `keys`, overridden by the custom axes.
+fn profile_section<'a>(
+    mut keys: BTreeMap<&'a str, toml::Value>,
+    custom: &'a [AxisChoice],
+) -> String {
+    for choice in custom {
+        for (key, value) in &choice.value.profile {
+            keys.insert(key, value.clone());
+        }
+    }
+    keys.into_iter()
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect()
+}
+
+fn toml_string_array(values: &[&str]) -> String {
+    let items: Vec<String> = values
+        .iter()
+        .map(|value| toml::Value::from(*value).to_string())
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Where the payload's `bevy` dependency comes from.
+#[derive(Debug, Clone)]
+enum BevySource {
+    /// The crates.io release [`BEVY_VERSION`].
+    Release,
+    /// A commit of a Bevy git repository, for bisection.
+    Git { repo: String, rev: String },
+}
+
+impl BevySource {
+    /// Keys of the `bevy` dependency table, without the features.
+    fn dependency_keys(&self) -> String {
+        match self {
+            Self::Release => format!("version = \"{BEVY_VERSION}\""),
+            Self::Git { repo, rev } => format!(
+                "git = {}, rev = {}",
+                toml::Value::from(repo.as_str()),
+                toml::Value::from(rev.as_str())
+            ),
+        }
+    }
+}
+
+impl PreparedScenario {
+    /// Regenerates `Cargo.toml` to build against `bevy` instead of the
+    /// release.
+    fn use_bevy(&mut self, bevy: &BevySource, patches: &Patches) {
+        self.code.cargo_toml = build_cargo_toml(
+            &self.scenario,
+            &self.custom,
+            patches,
+            &self.slug,
+            bevy,
+            &BevyFeatures::Default,
+            self.edit_target,
+        );
+    }
+
+    /// Regenerates `Cargo.toml` to build the release with `features` instead
+    /// of Bevy's defaults; replaces an earlier [`Self::use_bevy`].
+    fn use_features(&mut self, features: &BevyFeatures, patches: &Patches) {
+        self.code.cargo_toml = build_cargo_toml(
+            &self.scenario,
+            &self.custom,
+            patches,
+            &self.slug,
+            &BevySource::Release,
+            features,
+            self.edit_target,
+        );
+    }
+
+    /// Regenerates `rust-toolchain.toml` to build with `channel`, e.g. a
+    /// dated nightly, instead of the scenario's channel.
+    fn use_toolchain(&mut self, channel: &str) {
+        self.code.rust_toolchain_toml = build_toolchain(&self.scenario, channel);
+    }
+}
+
+/// Which of Bevy's own features the payload starts from.
+#[derive(Debug, Clone)]
+enum BevyFeatures {
+    /// Bevy's default features.
+    Default,
+    /// `default-features = false` plus only these.
+    Only(Vec<String>),
+}
+
+/// Features the scenario enables on the `bevy` crate.
+fn bevy_features<'a>(scenario: &Scenario, custom: &'a [AxisChoice]) -> Vec<&'a str> {
+    let mut features = Vec::new();
+    if matches!(scenario.dynamic, Some(Dynamic::DynamicLinking)) {
+        features.push("dynamic_linking");
+    }
+    if matches!(scenario.hotpatching, Some(Hotpatching::Dx)) {
+        features.push("hotpatching");
+    }
+    match scenario.trace {
+        Some(Trace::Trace) => features.push("trace"),
+        // Implies `trace`.
+        Some(Trace::Tracy) => features.push("trace_tracy"),
+        None => {}
+    }
+    for choice in custom {
+        features.extend(choice.value.features.iter().map(String::as_str));
+    }
+    features
+}
+
+fn build_cargo_toml(
+    scenario: &Scenario,
+    custom: &[AxisChoice],
+    patches: &Patches,
+    slug: &str,
+    bevy: &BevySource,
+    features: &BevyFeatures,
+    edit_target: EditTarget,
+) -> String {
+    let mut bevy_features = bevy_features(scenario, custom);
+    let mut features_clause = String::new();
+    match features {
+        BevyFeatures::Only(only) => {
+            features_clause.push_str(", default-features = false");
+            bevy_features.extend(only.iter().map(String::as_str));
+        }
+        // Both backends are default features, so one alone means turning the
+        // defaults off and listing the rest.
+        BevyFeatures::Default if scenario.window_backend.is_some() => {
+            features_clause.push_str(", default-features = false");
+            bevy_features.extend(BEVY_DEFAULT_FEATURES_WITHOUT_BACKENDS);
+        }
+        BevyFeatures::Default => {}
+    }
+    if let Some(backend) = scenario.window_backend {
+        bevy_features.push(backend.label());
+    }
+    if !bevy_features.is_empty() {
+        let feature_list = bevy_features
+            .into_iter()
+            .map(|feat| format!("\"{feat}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        features_clause.push_str(&format!(", features = [{feature_list}]"));
+    }
+
+    let wasm_dependencies = if scenario.target == Some(Target::Wasm32) {
+        "\n[target.'cfg(target_arch = \"wasm32\")'.dependencies]\ngetrandom = { version = \"0.3\", features = [\"wasm_js\"] }\n"
+    } else {
+        ""
+    };
+    // Custom axes may override the defaults, so build the section as a map.
+    let mut dev = BTreeMap::from([("opt-level", toml::Value::from(1))]);
+    let mut dev_dependencies = BTreeMap::from([("opt-level", toml::Value::from(3))]);
+    let mut extra = scenario.profile.map(Profile::keys).unwrap_or_default();
+    let mut extra_dependencies = BTreeMap::new();
+    if let Some(assertions) = scenario.debug_assertions {
+        // Dependencies are every package outside the payload, Bevy included.
+        let (payload, dependencies) = assertions.settings();
+        let (keys, dependency_keys) = match scenario.profile {
+            Some(_) => (&mut extra, &mut extra_dependencies),
+            None => (&mut dev, &mut dev_dependencies),
+        };
+        keys.insert("debug-assertions", toml::Value::from(payload));
+        dependency_keys.insert("debug-assertions", toml::Value::from(dependencies));
+    }
+    let profile = profile_section(dev, custom);
+    let dev_dependencies = profile_section(dev_dependencies, &[]);
+    let mut extra_profile = String::new();
+    if let Some(profile) = scenario.profile {
+        let name = profile.label();
+        extra_profile.push_str(&format!(
+            "\n[profile.{name}]\n{}",
+            profile_section(extra, custom)
+        ));
+        if !extra_dependencies.is_empty() {
+            extra_profile.push_str(&format!(
+                "\n[profile.{name}.package.\"*\"]\n{}",
+                profile_section(extra_dependencies, &[])
+            ));
+        }
+    }
+    let lib_section = if scenario.payload_is_library() {
+        "\n[lib]\ncrate-type = [\"cdylib\"]\n"
+    } else {
+        ""
+    };
+    let patch_section = patches.cargo_section();
+    let bevy_source = bevy.dependency_keys();
+    // The leaf crate takes Bevy from the workspace, so both crates always
+    // build against the same source; features unify with the payload's.
+    let (leaf_dependency, workspace_section) = if edit_target == EditTarget::LeafCrate {
+        (
+            format!("leaf = {{ package = \"bench-payload-leaf-{slug}\", path = \"leaf\" }}\n"),
+            format!(
+                "\n[workspace]\n\n[workspace.dependencies]\nbevy = {{ {bevy_source}, default-features = false }}\n"
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    let resolver = scenario
+        .resolver
+        .map(|resolver| format!("resolver = \"{}\"\n", resolver.label()))
+        .unwrap_or_default();
+
+    format!(
+        r#"[package]
+name = "bench-payload-{slug}"
+version = "0.1.0"
+edition = "2024"
+{resolver}{lib_section}
+[dependencies]
+bevy = {{ {bevy_source}{features_clause} }}
+{leaf_dependency}{wasm_dependencies}
+[profile.dev]
+{profile}
+[profile.dev.package."*"]
+{dev_dependencies}{extra_profile}{patch_section}{workspace_section}"#
+    )
+}
+
+/// Manifest of the leaf crate of `--edit-in leaf-crate`.
+fn build_leaf_cargo_toml(slug: &str) -> String {
+    format!(
+        r#"[package]
+name = "bench-payload-leaf-{slug}"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+bevy = {{ workspace = true }}
+"#
+    )
+}
+
+fn build_toolchain(scenario: &Scenario, channel: &str) -> String {
+    // `-Zbuild-std` compiles the standard library from the `rust-src` sources.
+    let components = if scenario.build_std.is_some() {
+        r#"["llvm-tools-preview", "rust-src"]"#
+    } else {
+        r#"["llvm-tools-preview"]"#
+    };
+    let mut output = format!(
+        r#"[toolchain]
+channel = "{channel}"
+components = {components}
+profile = "default"
+"#
+    );
+    if let Some(target) = scenario.target {
+        output.push_str(&format!("targets = [\"{}\"]\n", target.triple()));
+    }
+    output
+}
@@ -0,0 +1,282 @@
+//! `--link-time`: how much of each build was spent in the linker. Cargo links
+//! through a small wrapper script generated into the workspace, which runs
+//! this binary as a shim in front of the scenario's real linker; the shim
+//! appends the start, end, peak memory, and command line of every invocation
+//! to a log the harness reads after each phase, so the link metrics do not
+//! depend on timing data from cargo.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::redact;
+use crate::remote::Host;
+use crate::runner;
+use crate::{Linker, PreparedScenario, Target, Workspace};
+
+/// The real linker, set by the wrapper script only, so a rustc wrapper shim
+/// further up the process tree never mistakes itself for the linker shim.
+const LINKER_ENV: &str = "BENCH_LINKER";
+/// File the shim appends one JSON [`Invocation`] per line to.
+const LOG_ENV: &str = "BENCH_LINKER_LOG";
+
+/// One run of the linker, as the shim logs it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Invocation {
+    /// Seconds since the Unix epoch.
+    start: f64,
+    end: f64,
+    /// Largest resident set of the linker or any process it waited for.
+    #[serde(default)]
+    peak_rss_bytes: Option<u64>,
+    /// The linker and its arguments.
+    command: Vec<String>,
+}
+
+impl Invocation {
+    fn seconds(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+}
+
+/// When the wrapper script runs this binary, runs the real linker, logs the
+/// invocation, and returns the linker's exit code.
+pub fn shim() -> Option<i32> {
+    let linker = std::env::var_os(LINKER_ENV)?;
+    let args: Vec<_> = std::env::args_os().skip(1).collect();
+    let start = SystemTime::now();
+    let status = Command::new(&linker).args(&args).status();
+    let end = SystemTime::now();
+    if let Some(log) = std::env::var_os(LOG_ENV) {
+        let seconds = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64()
+        };
+        let invocation = Invocation {
+            start: seconds(start),
+            end: seconds(end),
+            peak_rss_bytes: children_peak_rss(),
+            command: std::iter::once(&linker)
+                .chain(&args)
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        };
+        // Logging must never fail the link it observes.
+        if let Ok(line) = serde_json::to_string(&invocation) {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log)
+                .and_then(|mut file| writeln!(file, "{line}"));
+        }
+    }
+    match status {
+        Ok(status) => Some(status.code().unwrap_or(1)),
+        Err(err) => {
+            eprintln!(
+                "error: failed to run linker {}: {err}",
+                linker.to_string_lossy()
+            );
+            Some(1)
+        }
+    }
+}
+
+/// The linker cargo would run for `prepared`, if the shim can stand in for
+/// it. Linkers rustc recognizes by name (rust-lld, wasm's lld) or that a
+/// cargo plugin configures (zig, cargo-xwin, cargo-ndk) are left alone.
+fn real_linker(prepared: &PreparedScenario) -> Option<&'static str> {
+    if matches!(
+        prepared.scenario.linker,
+        Some(Linker::RustLld | Linker::Zig)
+    ) {
+        return None;
+    }
+    match prepared.scenario.target {
+        None | Some(Target::X86_64LinuxGnu | Target::X86_64LinuxMusl) => Some("cc"),
+        Some(target) => target.cross_linker(),
+    }
+}
+
+/// Makes cargo builds in `workspace` link through the shim. Returns the
+/// shim's log file, or `None` with a warning where that is not possible.
+pub(crate) fn configure(
+    workspace: &mut Workspace,
+    prepared: &PreparedScenario,
+) -> Result<Option<String>> {
+    let skip = |reason: &str| {
+        eprintln!("[bench][warn] Not measuring link time: {reason}");
+        Ok(None)
+    };
+    if !matches!(workspace.host, Host::Local) {
+        return skip("the linker runs on a remote host");
+    }
+    if cfg!(windows) {
+        return skip("the linker wrapper is a shell script");
+    }
+    let Some(linker) = real_linker(prepared) else {
+        return skip("rustc or a cargo plugin picks this scenario's linker");
+    };
+    let triple = match prepared.scenario.target {
+        Some(target) => target.triple().to_string(),
+        None => host_triple(workspace)?,
+    };
+    let harness = std::env::current_exe()
+        .context("failed to locate the harness binary for the linker shim")?;
+    let script = workspace.path().join("link-wrapper");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\n{LINKER_ENV}={} exec {} \"$@\"\n",
+            shell_quote(linker),
+            shell_quote(&harness.display().to_string())
+        ),
+    )
+    .context("failed to write the linker wrapper")?;
+    make_executable(&script)?;
+    let log = workspace.host.join(&workspace.exec_dir, "link-wrapper.log");
+    let variable = format!(
+        "CARGO_TARGET_{}_LINKER",
+        triple.to_uppercase().replace(['-', '.'], "_")
+    );
+    workspace.cargo_env.extend([
+        (variable, script.display().to_string()),
+        (LOG_ENV.to_string(), log.clone()),
+    ]);
+    Ok(Some(log))
+}
+
+/// The triple rustc builds for without `--target`, from the workspace's
+/// toolchain.
+fn host_triple(workspace: &Workspace) -> Result<String> {
+    let output = workspace
+        .command("rustc")
+        .arg("-vV")
+        .output()
+        .map_err(runner::spawn_error("rustc"))
+        .context("failed to run rustc -vV")?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_string)
+        .context("rustc -vV did not report a host triple")
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .context("failed to make the linker wrapper executable")
+}
+
+#[cfg(not(unix))]
+fn make_executable(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Largest resident set of the waited-for children of this process.
+#[cfg(unix)]
+fn children_peak_rss() -> Option<u64> {
+    // SAFETY: an all-zero `rusage` is valid, and getrusage only writes to it.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: `usage` outlives the call.
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        return None;
+    }
+    let peak = u64::try_from(usage.ru_maxrss).ok()?;
+    // macOS reports bytes, everything else kilobytes.
+    Some(if cfg!(target_os = "macos") {
+        peak
+    } else {
+        peak * 1024
+    })
+}
+
+#[cfg(not(unix))]
+fn children_peak_rss() -> Option<u64> {
+    None
+}
+
+/// Link invocations since the last call, in the order they started. Removes
+/// the log, so the next phase starts from an empty one.
+pub(crate) fn take(log: &str) -> Vec<Invocation> {
+    let Ok(contents) = fs::read_to_string(log) else {
+        return Vec::new();
+    };
+    let _ = fs::remove_file(log);
+    let mut invocations: Vec<Invocation> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    invocations.sort_by(|a, b| a.start.total_cmp(&b.start));
+    invocations
+}
+
+/// Phase metrics of `invocations`: `links`, `link_time` (the wall time
+/// during which at least one linker ran), `link_time_max` (the slowest
+/// invocation), and `link_peak_rss_bytes` where it was measured.
+pub(crate) fn metrics(invocations: &[Invocation]) -> BTreeMap<String, f64> {
+    // Build scripts and binaries can link in parallel; overlapping spans
+    // count once.
+    let (mut total, mut covered_until) = (0.0, f64::MIN);
+    for invocation in invocations {
+        let start = invocation.start.max(covered_until);
+        if invocation.end > start {
+            total += invocation.end - start;
+            covered_until = invocation.end;
+        }
+    }
+    let longest = invocations
+        .iter()
+        .map(Invocation::seconds)
+        .fold(0.0, f64::max);
+    let mut metrics = BTreeMap::from([
+        ("links".to_string(), invocations.len() as f64),
+        ("link_time".to_string(), total),
+        ("link_time_max".to_string(), longest),
+    ]);
+    if let Some(peak) = invocations
+        .iter()
+        .filter_map(|invocation| invocation.peak_rss_bytes)
+        .max()
+    {
+        metrics.insert("link_peak_rss_bytes".to_string(), peak as f64);
+    }
+    metrics
+}
+
+/// Appends `invocations` of `phase` to the scenario's link log, one line
+/// each with the duration, peak memory, and command line.
+pub(crate) fn write_log(path: &Path, phase: &str, invocations: &[Invocation]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("failed to create the log directory")?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    for invocation in invocations {
+        let memory = invocation
+            .peak_rss_bytes
+            .map_or_else(|| "n/a".to_string(), crate::format_bytes);
+        writeln!(
+            file,
+            "{phase} {:.3}s {memory} {}",
+            invocation.seconds(),
+            redact::redact(&invocation.command.join(" "))
+        )
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
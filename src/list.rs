@@ -0,0 +1,101 @@
+//! `bench list`: the scenarios a run with the same flags would benchmark.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::cli::ListArgs;
+use crate::config::Config;
+use crate::selection::{self, Design};
+use crate::{Matrix, prepare_scenarios};
+
+#[derive(Debug, Serialize)]
+struct Entry {
+    slug: String,
+    /// Axis values in table order; serialized as an object.
+    #[serde(serialize_with = "as_object")]
+    axes: Vec<(String, String)>,
+    tags: Vec<String>,
+    /// Tools the scenario needs that are not on the local `PATH`.
+    missing: Vec<&'static str>,
+}
+
+impl Entry {
+    fn prerequisites(&self) -> String {
+        if self.missing.is_empty() {
+            "ok".to_string()
+        } else {
+            format!("missing {}", self.missing.join(", "))
+        }
+    }
+}
+
+fn as_object<S: serde::Serializer>(
+    axes: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(axes.iter().map(|(axis, value)| (axis, value)))
+}
+
+pub fn run(args: &ListArgs, config: &Config) -> Result<()> {
+    let mut prepared = prepare_scenarios(&Matrix::from_args(&args.run, config))?;
+    if args.run.design == Design::Fractional {
+        selection::fractional(&mut prepared);
+    }
+    let entries: Vec<Entry> = prepared
+        .iter()
+        .map(|prepared| Entry {
+            slug: prepared.slug.clone(),
+            axes: prepared.axes(),
+            tags: prepared.tags(),
+            missing: prepared.scenario.missing_tools(),
+        })
+        .collect();
+
+    if args.json {
+        let json =
+            serde_json::to_string_pretty(&entries).context("failed to serialize scenarios")?;
+        println!("{json}");
+        return Ok(());
+    }
+    print_table(&entries);
+    let ready = entries
+        .iter()
+        .filter(|entry| entry.missing.is_empty())
+        .count();
+    println!("\n{ready} of {} scenario(s) can run here.", entries.len());
+    Ok(())
+}
+
+fn print_table(entries: &[Entry]) {
+    let Some(first) = entries.first() else {
+        return;
+    };
+    let mut header: Vec<String> = vec!["slug".to_string()];
+    header.extend(first.axes.iter().map(|(axis, _)| axis.clone()));
+    header.extend(["tags".to_string(), "prerequisites".to_string()]);
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            let mut row = vec![entry.slug.clone()];
+            row.extend(entry.axes.iter().map(|(_, value)| value.clone()));
+            row.push(entry.tags.join(","));
+            row.push(entry.prerequisites());
+            row
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(String::len).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
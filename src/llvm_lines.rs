@@ -0,0 +1,123 @@
+//! Optional `cargo llvm-lines` analysis: how much LLVM IR the payload crate
+//! generates, and which generic functions are instantiated the most.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{PreparedScenario, ScenarioRecord, Workspace};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlvmLines {
+    pub total_lines: u64,
+    pub total_copies: u64,
+    /// Largest functions by generated lines, most first.
+    pub top: Vec<Instantiation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instantiation {
+    pub function: String,
+    pub lines: u64,
+    pub copies: u64,
+}
+
+/// Runs `cargo llvm-lines` in the workspace and keeps the `top` largest
+/// functions. Not timed; it rebuilds the payload crate with `--emit=llvm-ir`.
+pub fn measure(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    top: usize,
+) -> Result<LlvmLines> {
+    println!("[bench] Running cargo llvm-lines in {}", workspace.exec_dir);
+    let mut command = workspace.command("cargo");
+    // `--locked`, like every build, so the analysis cannot rewrite Cargo.lock.
+    command.arg("llvm-lines").arg("--locked");
+    if let Some(target) = prepared.scenario.target {
+        if !target.cargo_plugin().is_empty() {
+            bail!(
+                "cargo llvm-lines cannot build through the {} cargo plugin",
+                target.triple()
+            );
+        }
+        command.arg("--target").arg(target.triple());
+    }
+    let output = command.output().context("failed to run cargo llvm-lines")?;
+    if !output.status.success() {
+        bail!(
+            "cargo llvm-lines failed with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse(&String::from_utf8_lossy(&output.stdout), top)
+}
+
+/// Parses the table printed by `cargo llvm-lines`:
+///
+/// ```text
+///   Lines          Copies        Function name
+///   -----          ------        -------------
+///   30737          1107          (TOTAL)
+///    1395 (4.5%,  4.5%)  83 (7.5%,  7.5%)  core::ptr::drop_in_place
+/// ```
+fn parse(stdout: &str, top: usize) -> Result<LlvmLines> {
+    let mut rows = stdout.lines().filter_map(parse_row);
+    let total = rows
+        .find(|row| row.function == "(TOTAL)")
+        .context("cargo llvm-lines printed no (TOTAL) row")?;
+    Ok(LlvmLines {
+        total_lines: total.lines,
+        total_copies: total.copies,
+        top: rows.take(top).collect(),
+    })
+}
+
+/// A row is a line count and a copy count, each optionally followed by a
+/// parenthesized percentage, then the function name.
+fn parse_row(line: &str) -> Option<Instantiation> {
+    let mut rest = line.trim_start();
+    let mut counts = [0u64; 2];
+    for count in &mut counts {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        *count = rest[..end].parse().ok()?;
+        rest = rest[end..].trim_start();
+        if rest.starts_with('(')
+            && let Some(close) = rest.find(')')
+            && rest[..close].ends_with('%')
+        {
+            rest = rest[close + 1..].trim_start();
+        }
+    }
+    (!rest.is_empty()).then(|| Instantiation {
+        function: rest.to_string(),
+        lines: counts[0],
+        copies: counts[1],
+    })
+}
+
+/// Prints each scenario's LLVM IR totals and its largest instantiations.
+pub fn report(records: &[ScenarioRecord]) {
+    let measured: Vec<(&str, &LlvmLines)> = records
+        .iter()
+        .filter_map(|record| Some((record.slug.as_str(), record.llvm_lines.as_ref()?)))
+        .collect();
+    if measured.is_empty() {
+        return;
+    }
+
+    println!("\nLLVM IR generated by the payload crate (cargo llvm-lines):");
+    for (slug, lines) in measured {
+        println!(
+            "  {slug}: {} lines, {} copies",
+            lines.total_lines, lines.total_copies
+        );
+        for instantiation in &lines.top {
+            println!(
+                "    {:>8} lines {:>6} copies  {}",
+                instantiation.lines, instantiation.copies, instantiation.function
+            );
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! `--memory-limit`: caps the memory of every tool the harness starts, to
+//! find out whether a configuration builds on a smaller machine such as an
+//! 8 GiB CI runner.
+//!
+//! Linux puts the tools in a cgroup v2 with `memory.max` and reads its OOM
+//! kills from `memory.events`. That needs a delegated cgroup, e.g. running
+//! under `systemd-run --user --scope -p Delegate=yes`. Windows sets a job
+//! memory limit on each tool's Job Object and counts the jobs that hit it.
+
+use anyhow::{Result, bail};
+use std::sync::OnceLock;
+
+use crate::error::BenchError;
+
+static LIMIT: OnceLock<MemoryLimit> = OnceLock::new();
+/// Jobs that reported a violation of their memory limit.
+#[cfg(windows)]
+static OOM_KILLS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+#[derive(Debug)]
+pub(crate) struct MemoryLimit {
+    pub bytes: u64,
+    #[cfg(target_os = "linux")]
+    cgroup: std::path::PathBuf,
+}
+
+/// Applies `bytes` to every tool started from now on.
+pub(crate) fn install(bytes: u64) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    let limit = MemoryLimit {
+        bytes,
+        cgroup: cgroup::create(bytes)?,
+    };
+    #[cfg(windows)]
+    let limit = MemoryLimit { bytes };
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = bytes;
+        bail!("--memory-limit is only supported on Linux and Windows");
+    }
+    #[cfg(any(target_os = "linux", windows))]
+    {
+        if LIMIT.set(limit).is_err() {
+            bail!("a memory limit is already installed");
+        }
+        Ok(())
+    }
+}
+
+/// The limit in effect, if any.
+pub(crate) fn current() -> Option<&'static MemoryLimit> {
+    LIMIT.get()
+}
+
+/// Number of times a tool has been stopped for exceeding the limit so far.
+pub(crate) fn oom_kills() -> u64 {
+    #[cfg(target_os = "linux")]
+    {
+        current().map_or(0, |limit| cgroup::oom_kills(&limit.cgroup))
+    }
+    #[cfg(windows)]
+    {
+        OOM_KILLS.load(std::sync::atomic::Ordering::SeqCst)
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        0
+    }
+}
+
+/// Removes the cgroup once every tool has exited. Best effort: a leftover
+/// empty cgroup does no harm.
+pub(crate) fn release() {
+    #[cfg(target_os = "linux")]
+    if let Some(limit) = current() {
+        let _ = std::fs::remove_dir(&limit.cgroup);
+    }
+}
+
+/// Counts a tool's Job Object that ran into the limit.
+#[cfg(windows)]
+pub(crate) fn record_oom_kill() {
+    OOM_KILLS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Marks `err` as [`BenchError::OutOfMemory`] if a tool hit the limit since
+/// [`oom_kills`] returned `before`, so the failure is not just an anonymous
+/// killed build.
+pub(crate) fn explain(err: anyhow::Error, before: u64) -> anyhow::Error {
+    match current() {
+        Some(limit) if oom_kills() > before => err.context(BenchError::OutOfMemory {
+            limit_mib: limit.bytes / (1024 * 1024),
+        }),
+        _ => err,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod cgroup {
+    use anyhow::{Context, Result, bail};
+    use std::fs::{self, File, OpenOptions};
+    use std::path::{Path, PathBuf};
+
+    const ROOT: &str = "/sys/fs/cgroup";
+
+    /// Creates the cgroup the tools run in, next to a leaf the harness moves
+    /// itself into: cgroup v2 only enables controllers for the children of
+    /// a group without processes of its own.
+    pub(super) fn create(bytes: u64) -> Result<PathBuf> {
+        let own =
+            fs::read_to_string("/proc/self/cgroup").context("failed to read /proc/self/cgroup")?;
+        // On a hybrid setup the memory controller stays on cgroup v1.
+        let unified = Path::new(ROOT).join("cgroup.controllers").exists();
+        let Some(path) = own
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .filter(|_| unified)
+        else {
+            bail!("--memory-limit needs the unified cgroup v2 hierarchy");
+        };
+        let base = Path::new(ROOT).join(path.trim_start_matches('/'));
+        let hint = || {
+            format!(
+                "--memory-limit needs a writable cgroup at {}; run under `systemd-run --user --scope -p Delegate=yes`",
+                base.display()
+            )
+        };
+        let controllers =
+            fs::read_to_string(base.join("cgroup.subtree_control")).with_context(hint)?;
+        if !controllers.split_whitespace().any(|name| name == "memory") {
+            let harness = base.join("bench-harness");
+            fs::create_dir_all(&harness).with_context(hint)?;
+            fs::write(harness.join("cgroup.procs"), "0").with_context(hint)?;
+            fs::write(base.join("cgroup.subtree_control"), "+memory").with_context(hint)?;
+        }
+        let cgroup = base.join(format!("bench-tools-{}", std::process::id()));
+        fs::create_dir_all(&cgroup).with_context(hint)?;
+        fs::write(cgroup.join("memory.max"), bytes.to_string()).with_context(hint)?;
+        // Without this the kernel swaps instead of enforcing the limit; the
+        // file is missing when the kernel has no swap accounting.
+        let _ = fs::write(cgroup.join("memory.swap.max"), "0");
+        Ok(cgroup)
+    }
+
+    /// `cgroup.procs` of the limited cgroup, for a child to move itself
+    /// into before it runs the tool.
+    pub(crate) fn procs() -> Option<File> {
+        let limit = super::current()?;
+        OpenOptions::new()
+            .write(true)
+            .open(limit.cgroup.join("cgroup.procs"))
+            .ok()
+    }
+
+    pub(super) fn oom_kills(cgroup: &Path) -> u64 {
+        fs::read_to_string(cgroup.join("memory.events"))
+            .ok()
+            .and_then(|events| {
+                events.lines().find_map(|line| {
+                    line.strip_prefix("oom_kill ")
+                        .and_then(|count| count.trim().parse().ok())
+                })
+            })
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,71 @@
+//! Provenance stamped into every results file, and the schema version that
+//! decides which files can be compared with each other.
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use crate::RunRecord;
+use crate::cli::RunArgs;
+use crate::config::Config;
+
+/// Version of the results file format. Bump it whenever a change makes older
+/// files incomparable (e.g. a phase is measured differently), not for added
+/// fields with serde defaults.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Files written before the schema was versioned match version 1.
+pub fn legacy_schema_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub tool_version: String,
+    /// `git describe` of the harness at build time.
+    pub tool_revision: String,
+    /// Hash of the run arguments and configuration file in effect.
+    pub config_hash: String,
+    pub command_line: Vec<String>,
+}
+
+impl RunMetadata {
+    pub fn collect(args: &RunArgs, config: &Config) -> Self {
+        let mut hasher = DefaultHasher::new();
+        format!("{args:?}").hash(&mut hasher);
+        format!("{config:?}").hash(&mut hasher);
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            tool_revision: env!("BENCH_GIT_DESCRIBE").to_string(),
+            config_hash: format!("{:016x}", hasher.finish()),
+            command_line: std::env::args().collect(),
+        }
+    }
+}
+
+/// Rejects files written by a newer harness, whose fields may not mean what
+/// this version thinks they mean.
+pub fn check_supported(record: &RunRecord, path: &Path) -> Result<()> {
+    if record.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{} uses results schema v{}, but this tool only understands up to v{SCHEMA_VERSION}",
+            path.display(),
+            record.schema_version
+        );
+    }
+    Ok(())
+}
+
+/// Rejects comparing runs recorded with different schema versions.
+pub fn check_comparable(baseline: &RunRecord, candidate: &RunRecord) -> Result<()> {
+    if baseline.schema_version != candidate.schema_version {
+        bail!(
+            "cannot compare results schema v{} (baseline) with v{} (candidate)",
+            baseline.schema_version,
+            candidate.schema_version
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! The edit the `modified`, `hotpatch`, and `hotpatch rebuild` phases make to
+//! the payload (`--mutation`). Changing a constant is the cheapest edit there
+//! is; the other kinds change what rustc has to recompile and what dx has to
+//! patch. Every kind makes the payload report a new value, so a hotpatch is
+//! verified the same way whatever the edit. `--edit-in` moves the edited
+//! code out of the crate root, into a module or a crate of its own.
+
+use serde::{Deserialize, Serialize};
+
+/// What the payload edit changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Mutation {
+    /// The value of the `const` the payload reports.
+    #[default]
+    #[value(name = "constant")]
+    Constant,
+    /// An expression in the body of the system that reports the value.
+    #[value(name = "system-body")]
+    SystemBody,
+    /// A new system, run by the reporting system to report the value.
+    #[value(name = "add-system")]
+    AddSystem,
+    /// A new component type, spawned on an entity the value is read back from.
+    #[value(name = "add-component")]
+    AddComponent,
+}
+
+impl Mutation {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Constant => "constant",
+            Self::SystemBody => "system-body",
+            Self::AddSystem => "add-system",
+            Self::AddComponent => "add-component",
+        }
+    }
+
+    /// The change in words, for `--explain`.
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            Self::Constant => "changing the constant",
+            Self::SystemBody => "changing the body of report_payload",
+            Self::AddSystem => "adding a system",
+            Self::AddComponent => "adding a component type",
+        }
+    }
+
+    /// The edit that makes a payload generated with `original` report `new`.
+    pub(crate) fn edit(self, original: u64, new: u64) -> PayloadEdit {
+        let mask = format!("PAYLOAD_RANDOM_VALUE ^ {:#x}", original ^ new);
+        match self {
+            Self::Constant => PayloadEdit::original(new),
+            Self::SystemBody => PayloadEdit {
+                value: mask,
+                ..PayloadEdit::original(original)
+            },
+            Self::AddSystem => PayloadEdit {
+                value: mask,
+                report: "commands.run_system_cached_with(report_from_added_system, value);"
+                    .to_string(),
+                items: r#"
+fn report_from_added_system(In(value): In<u64>) {
+    report_value(value);
+}
+"#
+                .to_string(),
+                ..PayloadEdit::original(original)
+            },
+            Self::AddComponent => PayloadEdit {
+                value: mask,
+                report: r#"let entity = commands.spawn(AddedComponent(value)).id();
+        commands.queue(move |world: &mut World| {
+            if let Some(component) = world.get::<AddedComponent>(entity) {
+                report_value(component.0);
+            }
+        });"#
+                    .to_string(),
+                items: r#"
+#[derive(Component)]
+struct AddedComponent(u64);
+"#
+                .to_string(),
+                ..PayloadEdit::original(original)
+            },
+        }
+    }
+}
+
+/// The parts of the payload source an edit changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PayloadEdit {
+    /// Value of `PAYLOAD_RANDOM_VALUE`.
+    pub constant: u64,
+    /// Expression `report_payload` computes the reported value with.
+    pub value: String,
+    /// Statements reporting `value`, with `commands` in scope.
+    pub report: String,
+    /// Items appended to the source.
+    pub items: String,
+}
+
+impl PayloadEdit {
+    /// The payload before any edit.
+    pub(crate) fn original(value: u64) -> Self {
+        Self {
+            constant: value,
+            value: "PAYLOAD_RANDOM_VALUE".to_string(),
+            report: "commands.queue(move |_: &mut World| report_value(value));".to_string(),
+            items: String::new(),
+        }
+    }
+}
+
+/// Where in the payload the edit lands (`--edit-in`).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+pub enum EditTarget {
+    /// The crate root, which then holds the whole payload.
+    #[default]
+    #[value(name = "main")]
+    Main,
+    /// `src/payload.rs`, a module the crate root declares.
+    #[value(name = "module")]
+    Module,
+    /// `leaf/src/lib.rs`, a library crate in the payload's workspace that
+    /// the payload crate depends on.
+    #[value(name = "leaf-crate")]
+    LeafCrate,
+}
+
+impl EditTarget {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Module => "module",
+            Self::LeafCrate => "leaf-crate",
+        }
+    }
+
+    /// The file edits change, for a payload crate rooted at `root`.
+    pub(crate) fn payload_file(self, root: &'static str) -> &'static str {
+        match self {
+            Self::Main => root,
+            Self::Module => "src/payload.rs",
+            Self::LeafCrate => "leaf/src/lib.rs",
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Detection of iterations that deviate far from the rest, typically caused by
+//! background work (indexing, antivirus scans) rather than the configuration.
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats;
+use crate::{ScenarioResult, ScenarioTimingRecord};
+
+/// Iterations further than this many median absolute deviations from the
+/// median are treated as outliers.
+const MAD_THRESHOLD: f64 = 3.0;
+
+/// Fewer samples than this give no meaningful notion of "typical".
+const MIN_SAMPLES: usize = 3;
+
+/// Floor of the median absolute deviation, relative to the median. When most
+/// iterations measure exactly the same, the deviation is zero, and a lone
+/// different one is still caught without flagging rounding noise.
+const MIN_RELATIVE_MAD: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outlier {
+    /// One-based iteration number.
+    pub iteration: usize,
+    pub phase: String,
+    pub seconds: f64,
+    pub median: f64,
+    pub suspected_cause: String,
+    /// Whether the iteration was run again and its measurement replaced.
+    pub remeasured: bool,
+}
+
+pub fn detect(results: &[ScenarioResult]) -> Vec<Outlier> {
+    if results.len() < MIN_SAMPLES {
+        return Vec::new();
+    }
+    let samples: Vec<ScenarioTimingRecord> = results
+        .iter()
+        .map(|result| ScenarioTimingRecord::from_timings(&result.timings))
+        .collect();
+    let loads: Vec<f64> = results
+        .iter()
+        .filter_map(|result| result.load_average)
+        .collect();
+    let typical_load = (!loads.is_empty()).then(|| stats::median(&loads));
+
+    let mut outliers = Vec::new();
+    for (phase, _) in samples[0].phases() {
+        let values: Vec<Option<f64>> = samples.iter().map(|sample| sample.phase(phase)).collect();
+        let Some(median) = typical(&values) else {
+            continue;
+        };
+        for index in deviating(&values, median) {
+            let Some(value) = values[index] else {
+                continue;
+            };
+            outliers.push(Outlier {
+                iteration: index + 1,
+                phase: phase.to_string(),
+                seconds: value,
+                median,
+                suspected_cause: suspected_cause(
+                    value > median,
+                    results[index].load_average,
+                    typical_load,
+                ),
+                remeasured: false,
+            });
+        }
+    }
+    outliers
+}
+
+/// Median of the measured `values`, if at least [`MIN_SAMPLES`] were.
+fn typical(values: &[Option<f64>]) -> Option<f64> {
+    let measured: Vec<f64> = values.iter().flatten().copied().collect();
+    (measured.len() >= MIN_SAMPLES).then(|| stats::median(&measured))
+}
+
+/// Indices of the measured `values` further than [`MAD_THRESHOLD`] median
+/// absolute deviations from `median`.
+fn deviating(values: &[Option<f64>], median: f64) -> Vec<usize> {
+    let deviations: Vec<f64> = values
+        .iter()
+        .flatten()
+        .map(|value| (value - median).abs())
+        .collect();
+    let mad = stats::median(&deviations).max(median.abs() * MIN_RELATIVE_MAD);
+    if mad == 0.0 {
+        return Vec::new();
+    }
+    values
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| {
+            value.is_some_and(|value| (value - median).abs() > MAD_THRESHOLD * mad)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn suspected_cause(slower: bool, load: Option<f64>, typical_load: Option<f64>) -> String {
+    if let (Some(load), Some(typical)) = (load, typical_load)
+        && slower
+        && load > typical + 1.0
+    {
+        return format!("high background load (load average {load:.1} vs typical {typical:.1})");
+    }
+    if slower {
+        "unexplained slowdown (background indexing, antivirus scan, or thermal throttling?)"
+            .to_string()
+    } else {
+        "unexpectedly fast (warm OS file cache or shared cache hit?)".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flagged(values: &[Option<f64>]) -> Vec<usize> {
+        typical(values).map_or_else(Vec::new, |median| deviating(values, median))
+    }
+
+    #[test]
+    fn spread_samples_flag_values_beyond_the_threshold() {
+        // Median 10, deviations 0.5, 0, 0.5, 0, 1.5 and 5: MAD 0.5, so more
+        // than 1.5 away is an outlier.
+        let values = [9.5, 10.0, 10.5, 10.0, 11.5, 15.0].map(Some);
+
+        assert_eq!(flagged(&values), vec![5]);
+    }
+
+    #[test]
+    fn value_exactly_at_the_threshold_is_kept() {
+        // Median 10, MAD 1: 13 is exactly three deviations away.
+        let values = [9.0, 10.0, 11.0, 10.0, 13.0].map(Some);
+
+        assert_eq!(flagged(&values), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn zero_deviation_still_flags_a_lone_different_sample() {
+        let values = [10.0, 10.0, 10.0, 10.0, 30.0].map(Some);
+
+        assert_eq!(flagged(&values), vec![4]);
+        // Noise below the relative floor is not an outlier.
+        let values = [10.0, 10.0, 10.0, 10.0, 10.02].map(Some);
+        assert_eq!(flagged(&values), Vec::<usize>::new());
+        assert_eq!(flagged(&[Some(0.0); 4]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn exactly_min_samples_are_enough() {
+        let values = [Some(10.0), Some(10.0), Some(50.0)];
+
+        assert_eq!(values.len(), MIN_SAMPLES);
+        assert_eq!(flagged(&values), vec![2]);
+    }
+
+    #[test]
+    fn fewer_than_min_samples_flag_nothing() {
+        assert_eq!(flagged(&[Some(10.0), Some(50.0)]), Vec::<usize>::new());
+        // Missing measurements do not count toward the minimum.
+        assert_eq!(
+            flagged(&[Some(10.0), None, Some(50.0)]),
+            Vec::<usize>::new()
+        );
+        assert_eq!(typical(&[]), None);
+    }
+}
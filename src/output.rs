@@ -0,0 +1,88 @@
+//! Per-run output directory next to the run log, so everything a run
+//! produces besides the log itself has one place:
+//!
+//! ```text
+//! results/<run-id>/<slug>/
+//!     logs/<phase>.log       full output of each cargo invocation
+//!     logs/phases.log        BEGIN/END markers of every phase
+//!     timings.json           the scenario's record
+//!     cargo-config.toml      the effective `.cargo/config.toml`
+//!     cargo-timings.html     cargo's report, with --crate-timings
+//!     workspace              link to the kept workspace, with --verbose
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{ScenarioRecord, redact};
+
+#[derive(Debug, Clone)]
+pub struct OutputDir {
+    root: PathBuf,
+}
+
+impl OutputDir {
+    pub fn create(root: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&root)
+            .with_context(|| format!("failed to create output directory {}", root.display()))?;
+        Ok(Self { root })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory of `slug`, with its `logs` subdirectory created.
+    pub fn scenario(&self, slug: &str) -> Result<PathBuf> {
+        let dir = self.root.join(slug);
+        fs::create_dir_all(dir.join("logs"))
+            .with_context(|| format!("failed to create output directory {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    /// Writes `record` to its scenario's `timings.json`.
+    pub(crate) fn write_timings(&self, record: &ScenarioRecord) -> Result<()> {
+        let path = self.scenario(&record.slug)?.join("timings.json");
+        let json = serde_json::to_string_pretty(record).context("failed to serialize timings")?;
+        fs::write(&path, redact::redact(&json).as_bytes())
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Stores the scenario's effective `.cargo/config.toml`, including what was
+/// merged in from `[cargo_config]`.
+pub fn write_cargo_config(dir: &Path, contents: &str) -> Result<()> {
+    let path = dir.join("cargo-config.toml");
+    fs::write(&path, redact::redact(contents).as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Log file for a cargo invocation named `label` in the scenario directory
+/// `dir`, e.g. `logs/clean.log`.
+pub fn log_file(dir: &Path, label: &str) -> PathBuf {
+    dir.join("logs")
+        .join(format!("{}.log", label.replace(' ', "-")))
+}
+
+/// Points `<dir>/workspace` at a workspace kept with `--verbose`. Only a
+/// convenience, so a failure is just a warning.
+pub fn link_workspace(dir: &Path, workspace: &Path) {
+    let link = dir.join("workspace");
+    let _ = fs::remove_file(&link);
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(workspace, &link);
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_dir(workspace, &link);
+    #[cfg(not(any(unix, windows)))]
+    let linked: std::io::Result<()> = {
+        let _ = workspace;
+        Ok(())
+    };
+    if let Err(err) = linked {
+        eprintln!(
+            "[bench][warn] Could not link {} to the kept workspace: {err}",
+            link.display()
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Pareto-optimal scenarios across rebuild time, disk usage, and binary size.
+
+use crate::{ScenarioRecord, format_bytes};
+
+struct Candidate<'a> {
+    slug: &'a str,
+    rebuild_seconds: f64,
+    workspace_bytes: u64,
+    binary_bytes: u64,
+}
+
+impl Candidate<'_> {
+    /// No worse on every objective and strictly better on at least one.
+    fn dominates(&self, other: &Self) -> bool {
+        let no_worse = self.rebuild_seconds <= other.rebuild_seconds
+            && self.workspace_bytes <= other.workspace_bytes
+            && self.binary_bytes <= other.binary_bytes;
+        let better = self.rebuild_seconds < other.rebuild_seconds
+            || self.workspace_bytes < other.workspace_bytes
+            || self.binary_bytes < other.binary_bytes;
+        no_worse && better
+    }
+}
+
+/// Prints the scenarios no other scenario beats on all of rebuild time (the
+/// modified-source build), workspace disk usage, and binary size at once.
+pub fn report(records: &[ScenarioRecord]) {
+    let candidates: Vec<Candidate> = records
+        .iter()
+        .filter(|record| record.error.is_none())
+        .filter_map(|record| {
+            let disk = record.disk?;
+            Some(Candidate {
+                slug: &record.slug,
+                rebuild_seconds: record.timings.phase("modified")?,
+                workspace_bytes: disk.workspace_bytes,
+                binary_bytes: disk.binary_bytes,
+            })
+        })
+        .collect();
+    if candidates.len() < 2 {
+        return;
+    }
+
+    let mut front: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|candidate| !candidates.iter().any(|other| other.dominates(candidate)))
+        .collect();
+    front.sort_by(|a, b| a.rebuild_seconds.total_cmp(&b.rebuild_seconds));
+
+    println!(
+        "\nPareto front ({} of {} scenario(s); rebuild time vs. disk vs. binary size):",
+        front.len(),
+        candidates.len()
+    );
+    for candidate in front {
+        println!(
+            "  {:<60} rebuild={:.3}s disk={} binary={}",
+            candidate.slug,
+            candidate.rebuild_seconds,
+            format_bytes(candidate.workspace_bytes),
+            format_bytes(candidate.binary_bytes)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! `[patch.<registry>]` overrides from the config file, copied into every
+//! generated `Cargo.toml` so a local Bevy checkout can be benchmarked across
+//! the whole matrix.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::remote::Host;
+
+/// Registry (usually `crates-io`) to crate name to dependency table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Patches(BTreeMap<String, BTreeMap<String, toml::Table>>);
+
+impl Patches {
+    /// Makes relative `path` entries absolute against the config file's
+    /// directory, since the payload is built in a temporary directory.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        for table in self.0.values_mut().flat_map(BTreeMap::values_mut) {
+            if let Some(toml::Value::String(path)) = table.get_mut("path")
+                && Path::new(path.as_str()).is_relative()
+            {
+                let absolute = base.join(path.as_str());
+                let absolute = absolute.canonicalize().unwrap_or(absolute);
+                *path = absolute.display().to_string();
+            }
+        }
+    }
+
+    /// The `[patch.*]` sections for the generated `Cargo.toml`.
+    pub fn cargo_section(&self) -> String {
+        let mut output = String::new();
+        for (registry, crates) in &self.0 {
+            output.push_str(&format!(
+                "\n[patch.{}]\n",
+                toml::Value::from(registry.as_str())
+            ));
+            for (name, table) in crates {
+                output.push_str(&format!("{name} = {}\n", toml::Value::Table(table.clone())));
+            }
+        }
+        output
+    }
+
+    /// Git revision of every path override, so results are re-measured when
+    /// the checkout moves to another commit or has local edits.
+    pub fn revisions(&self, host: &Host) -> Vec<String> {
+        self.0
+            .values()
+            .flatten()
+            .filter_map(|(name, table)| {
+                let path = table.get("path")?.as_str()?;
+                let revision = host
+                    .probe(
+                        "git",
+                        &["-C", path, "describe", "--always", "--dirty", "--tags"],
+                    )
+                    .unwrap_or_else(|| "unknown".to_string());
+                Some(format!("{name}@{revision}"))
+            })
+            .collect()
+    }
+}
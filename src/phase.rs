@@ -0,0 +1,143 @@
+//! The timed phases of a scenario. Each scenario defines its own phase list,
+//! and every measured phase is stored as a named result rather than in a
+//! dedicated field, so adding a phase only takes a variant here and a step in
+//! the scenario runner.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{Linker, Scenario, Target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// `cargo fetch` into an empty `CARGO_HOME`, for the registry axis.
+    Fetch,
+    /// First build in a fresh workspace.
+    Clean,
+    /// Rebuild without any change.
+    Second,
+    /// Rebuild without any change in a copy of the workspace at another
+    /// path (`--relocate`).
+    Relocated,
+    /// `wasm-opt` on the wasm payload.
+    WasmOpt,
+    /// Rebuild after changing the payload source.
+    Modified,
+    /// From starting the built payload to the end of its first frame
+    /// (`--first-frame`).
+    FirstFrame,
+    /// From a source change under `dx serve` to the patched payload running.
+    Hotpatch,
+    /// `cargo build` after the hotpatch's source change, in a copy of the
+    /// workspace: the rebuild the hotpatch saves.
+    HotpatchRebuild,
+}
+
+impl Phase {
+    /// Name in results logs, reports, and budgets.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fetch => "fetch",
+            Self::Clean => "clean",
+            Self::Second => "second",
+            Self::Relocated => "relocated",
+            Self::WasmOpt => "wasm-opt",
+            Self::Modified => "modified",
+            Self::FirstFrame => "first-frame",
+            Self::Hotpatch => "hotpatch",
+            Self::HotpatchRebuild => "hotpatch-rebuild",
+        }
+    }
+
+    /// Phases that run `cargo build` in the workspace; the lockfile is
+    /// checked after each, and `after_build` hooks run after them.
+    pub fn is_cargo_build(self) -> bool {
+        matches!(self, Self::Clean | Self::Second | Self::Modified)
+    }
+
+    /// The phases of `scenario`, in the order they run. `first_frame` adds
+    /// [`Self::FirstFrame`] for payloads that run on the build machine, and
+    /// `relocate` adds [`Self::Relocated`].
+    pub fn for_scenario(scenario: &Scenario, first_frame: bool, relocate: bool) -> Vec<Self> {
+        let mut phases = Vec::new();
+        if scenario.registry.is_some() {
+            phases.push(Self::Fetch);
+        }
+        phases.extend([Self::Clean, Self::Second]);
+        if relocate {
+            phases.push(Self::Relocated);
+        }
+        if scenario.target == Some(Target::Wasm32) {
+            phases.push(Self::WasmOpt);
+        }
+        phases.push(Self::Modified);
+        // `cargo run` would relink with the default linker, since only
+        // `cargo zigbuild` uses zig.
+        if first_frame && scenario.target.is_none() && scenario.linker != Some(Linker::Zig) {
+            phases.push(Self::FirstFrame);
+        }
+        if scenario.hotpatching.is_some() {
+            phases.extend([Self::Hotpatch, Self::HotpatchRebuild]);
+        }
+        phases
+    }
+}
+
+/// Outcome of one phase of one iteration.
+#[derive(Debug, Clone)]
+pub struct PhaseResult {
+    pub phase: Phase,
+    pub duration: Duration,
+    /// Exit code of the tool that ran the phase; `None` when the harness
+    /// stopped it, as it does `dx serve`.
+    pub exit_code: Option<i32>,
+    /// Further measurements taken during the phase.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl PhaseResult {
+    pub fn new(phase: Phase, duration: Duration, exit_code: Option<i32>) -> Self {
+        Self {
+            phase,
+            duration,
+            exit_code,
+            metrics: BTreeMap::new(),
+        }
+    }
+}
+
+/// A [`PhaseResult`] as stored in the results log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseRecord {
+    pub name: String,
+    pub seconds: f64,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl From<&PhaseResult> for PhaseRecord {
+    fn from(result: &PhaseResult) -> Self {
+        Self {
+            name: result.phase.name().to_string(),
+            seconds: result.duration.as_secs_f64(),
+            exit_code: result.exit_code,
+            metrics: result.metrics.clone(),
+        }
+    }
+}
+
+impl PhaseRecord {
+    /// A phase read from a log written before phases were named, which only
+    /// stored the duration.
+    pub fn legacy(name: &str, seconds: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            seconds,
+            exit_code: None,
+            metrics: BTreeMap::new(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Exact versions for Bevy's heaviest transitive dependencies (`--pin-deps`
+//! and `[pin]`), set in each scenario's `Cargo.lock` right after it is
+//! generated. Bevy depends on them through semver ranges, so without pins a
+//! new wgpu or winit release changes what a run a week later compiles.
+
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Workspace, runner};
+
+/// Versions `--pin-deps` uses, matching the Bevy release the payload
+/// depends on ([`crate::BEVY_VERSION`]).
+const DEFAULT_PINS: &[(&str, &str)] = &[
+    ("naga", "26.0.0"),
+    ("wgpu", "26.0.1"),
+    ("wgpu-core", "26.0.1"),
+    ("wgpu-hal", "26.0.4"),
+    ("wgpu-types", "26.0.0"),
+    ("winit", "0.30.12"),
+];
+
+/// Crate name to exact version.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Pins(BTreeMap<String, String>);
+
+impl Pins {
+    /// The built-in pins if `defaults` is set, overridden and extended by
+    /// `[pin]` from the config file.
+    pub(crate) fn new(defaults: bool, config: &BTreeMap<String, String>) -> Self {
+        let mut pins = BTreeMap::new();
+        if defaults {
+            pins.extend(
+                DEFAULT_PINS
+                    .iter()
+                    .map(|&(name, version)| (name.to_string(), version.to_string())),
+            );
+        }
+        pins.extend(config.clone());
+        Self(pins)
+    }
+
+    /// `name@version` of every pin.
+    pub(crate) fn facts(&self) -> impl Iterator<Item = String> + '_ {
+        self.0
+            .iter()
+            .map(|(name, version)| format!("{name}@{version}"))
+    }
+
+    /// Moves every pinned crate in the workspace's `Cargo.lock` to its
+    /// version. Crates the payload does not depend on, such as winit when no
+    /// window backend is enabled, are skipped.
+    pub(crate) fn apply(&self, workspace: &Workspace) -> Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let lockfile = workspace
+            .host
+            .read(&workspace.host.join(&workspace.exec_dir, "Cargo.lock"))
+            .context("failed to read Cargo.lock")?;
+        let locked = locked_packages(&lockfile)?;
+        for (name, version) in &self.0 {
+            if !locked.contains(name.as_str()) {
+                println!("[bench] Not pinning {name}: the payload does not depend on it");
+                continue;
+            }
+            println!("[bench] Pinning {name} to {version}...");
+            let status = workspace
+                .command("cargo")
+                .args(["update", "--quiet", "-p", name, "--precise", version])
+                .status()
+                .map_err(runner::spawn_error("cargo"))
+                .context("failed to run cargo update")?;
+            if !status.success() {
+                bail!("pinning {name} to {version} failed with status {status}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names of the packages in a `Cargo.lock`.
+fn locked_packages(lockfile: &[u8]) -> Result<HashSet<String>> {
+    let lockfile: toml::Table = std::str::from_utf8(lockfile)
+        .ok()
+        .and_then(|text| toml::from_str(text).ok())
+        .context("failed to parse Cargo.lock")?;
+    let packages = lockfile
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(packages
+        .iter()
+        .filter_map(|package| package.get("name")?.as_str())
+        .map(str::to_string)
+        .collect())
+}
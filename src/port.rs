@@ -0,0 +1,31 @@
+//! Ports for the servers the harness starts. Each server gets its own port
+//! from the OS instead of the tool's default, so concurrent benchmark runs,
+//! or a run next to a developer's own `dx serve`, never collide.
+
+use anyhow::{Context, Result};
+use std::net::TcpListener;
+
+/// A port nothing on this machine listens on right now.
+pub(crate) fn free(purpose: &str) -> Result<u16> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .with_context(|| format!("failed to reserve a port for {purpose}"))?;
+    Ok(listener
+        .local_addr()
+        .context("failed to read reserved port")?
+        .port())
+}
+
+/// Whether a tool's output says it could not bind its port, as printed by
+/// the Rust standard library on Linux, macOS, and Windows.
+pub(crate) fn is_conflict(output: &str) -> bool {
+    let output = output.to_ascii_lowercase();
+    [
+        "address already in use",
+        "addrinuse",
+        "os error 98)",
+        "os error 48)",
+        "os error 10048)",
+    ]
+    .iter()
+    .any(|needle| output.contains(needle))
+}
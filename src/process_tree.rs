@@ -0,0 +1,333 @@
+//! Keeps every descendant of a spawned tool under the harness's control.
+//! Killing `cargo` or `dx` alone leaves rustc, the linker, or the running
+//! game behind, so the whole tree is tied to the child instead.
+
+use std::io;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once SIGINT or SIGTERM has been forwarded to the running tools.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the run was interrupted by a signal; no new tools are started
+/// afterwards.
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Whatever ties a child's descendants to it: a Job Object on Windows, a
+/// process group on Unix.
+#[derive(Debug)]
+pub(crate) struct ProcessTree {
+    #[cfg(windows)]
+    job: job::Job,
+    #[cfg(unix)]
+    group: group::Group,
+}
+
+impl ProcessTree {
+    /// Sets up `command` so its descendants can be tracked once spawned,
+    /// and lowers its priority to `nice` if set.
+    /// A `--memory-limit` is applied here on Linux and in [`Self::attach`]
+    /// on Windows.
+    pub(crate) fn prepare(command: &mut Command, nice: Option<i32>) {
+        #[cfg(unix)]
+        group::isolate(command, nice);
+        #[cfg(windows)]
+        job::deprioritize(command, nice);
+        #[cfg(not(any(windows, unix)))]
+        let _ = (command, nice);
+    }
+
+    /// Ties `child`, and everything it starts from now on, to the returned
+    /// tree.
+    pub(crate) fn attach(child: &Child) -> io::Result<Self> {
+        #[cfg(windows)]
+        {
+            let limit = crate::memory_limit::current().map(|limit| limit.bytes);
+            let job = job::Job::kill_on_close(limit)?;
+            job.assign(child)?;
+            Ok(Self { job })
+        }
+        #[cfg(unix)]
+        {
+            Ok(Self {
+                group: group::Group::register(child),
+            })
+        }
+        #[cfg(not(any(windows, unix)))]
+        {
+            let _ = child;
+            Ok(Self {})
+        }
+    }
+
+    /// Kills the child and all of its descendants.
+    pub(crate) fn kill(&self, child: &mut Child) -> io::Result<()> {
+        #[cfg(windows)]
+        {
+            let _ = child;
+            self.job.terminate()
+        }
+        #[cfg(unix)]
+        {
+            let _ = child;
+            self.group.kill()
+        }
+        #[cfg(not(any(windows, unix)))]
+        {
+            child.kill()
+        }
+    }
+}
+
+/// Forwards SIGINT and SIGTERM to the process groups of the running tools
+/// instead of exiting, so the current build stops cleanly and the scenario
+/// is recorded as interrupted. A second signal exits immediately. Children
+/// get their own process group, so a terminal Ctrl-C no longer reaches them
+/// directly. Does nothing outside Unix, where Ctrl-C already reaches every
+/// process attached to the console.
+pub(crate) fn forward_interrupts() -> io::Result<()> {
+    #[cfg(unix)]
+    group::forward_signals()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+mod group {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Mutex;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    use super::INTERRUPTED;
+
+    /// Process groups of the tools currently running.
+    static ACTIVE: Mutex<Vec<libc::pid_t>> = Mutex::new(Vec::new());
+
+    /// Starts the child as the leader of a new process group. Reading the
+    /// terminal from a background group would stop it, so stdin is closed.
+    /// A `nice` value is applied to the child before it runs the tool.
+    pub(super) fn isolate(command: &mut Command, nice: Option<i32>) {
+        command.process_group(0).stdin(Stdio::null());
+        if let Some(nice) = nice {
+            // SAFETY: setpriority is async-signal-safe and touches no memory
+            // shared with the parent.
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(procs) = crate::memory_limit::cgroup::procs() {
+            use std::os::fd::AsRawFd;
+            // SAFETY: write is async-signal-safe, and the file stays open
+            // until the command is dropped; writing 0 moves the caller.
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1) != 1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
+
+    /// A child's process group, tracked while the child may be running.
+    #[derive(Debug)]
+    pub(super) struct Group(libc::pid_t);
+
+    impl Group {
+        /// The child leads its own group, so the group id is its pid.
+        pub(super) fn register(child: &Child) -> Self {
+            let id = child.id() as libc::pid_t;
+            ACTIVE.lock().expect("process group lock poisoned").push(id);
+            Self(id)
+        }
+
+        pub(super) fn kill(&self) -> io::Result<()> {
+            signal_group(self.0, libc::SIGKILL)
+        }
+    }
+
+    impl Drop for Group {
+        fn drop(&mut self) {
+            ACTIVE
+                .lock()
+                .expect("process group lock poisoned")
+                .retain(|id| *id != self.0);
+        }
+    }
+
+    fn signal_group(group: libc::pid_t, signal: libc::c_int) -> io::Result<()> {
+        // SAFETY: `kill` has no memory-safety preconditions.
+        if unsafe { libc::kill(-group, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub(super) fn forward_signals() -> io::Result<()> {
+        let mut signals = Signals::new([SIGINT, SIGTERM])?;
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                let groups = ACTIVE.lock().expect("process group lock poisoned").clone();
+                if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                    eprintln!("\n[bench][warn] Interrupted again; killing tools and exiting.");
+                    for group in groups {
+                        let _ = signal_group(group, libc::SIGKILL);
+                    }
+                    std::process::exit(128 + signal);
+                }
+                eprintln!(
+                    "\n[bench][warn] Interrupted; stopping the running tools (again to exit now)."
+                );
+                for group in groups {
+                    let _ = signal_group(group, signal);
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod job {
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::process::CommandExt;
+    use std::process::{Child, Command};
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_JOB_MEMORY,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOBOBJECT_LIMIT_VIOLATION_INFORMATION,
+        JobObjectBasicAndIoAccountingInformation, JobObjectExtendedLimitInformation,
+        JobObjectLimitViolationInformation, QueryInformationJobObject, SetInformationJobObject,
+        TerminateJobObject,
+    };
+    use windows_sys::Win32::System::Threading::{BELOW_NORMAL_PRIORITY_CLASS, IDLE_PRIORITY_CLASS};
+
+    /// Windows has priority classes instead of niceness: the upper half of
+    /// the Unix range maps to idle, the rest to below normal.
+    pub(super) fn deprioritize(command: &mut Command, nice: Option<i32>) {
+        match nice {
+            Some(15..) => {
+                command.creation_flags(IDLE_PRIORITY_CLASS);
+            }
+            Some(_) => {
+                command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+            }
+            None => {}
+        }
+    }
+
+    /// A Job Object that kills its processes when the last handle closes,
+    /// which also happens when the harness itself crashes, and optionally
+    /// caps their combined memory.
+    #[derive(Debug)]
+    pub(super) struct Job(HANDLE);
+
+    impl Job {
+        pub(super) fn kill_on_close(memory_limit: Option<u64>) -> io::Result<Self> {
+            // SAFETY: no security attributes and an anonymous job.
+            let handle = unsafe { CreateJobObjectW(ptr::null(), ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Self(handle);
+            let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(bytes) = memory_limit {
+                limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                limits.JobMemoryLimit = bytes as usize;
+            }
+            // SAFETY: `limits` is the structure the information class expects
+            // and outlives the call.
+            let ok = unsafe {
+                SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    (&raw const limits).cast(),
+                    size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        /// Processes the child started before this call stay outside the
+        /// job; the window is the few instructions since `spawn` returned.
+        pub(super) fn assign(&self, child: &Child) -> io::Result<()> {
+            // SAFETY: both handles are valid for the duration of the call.
+            let ok = unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle()) };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub(super) fn terminate(&self) -> io::Result<()> {
+            // SAFETY: the job handle is owned and open.
+            let ok = unsafe { TerminateJobObject(self.0, 1) };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            let mut accounting = JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION::default();
+            // SAFETY: `accounting` is the structure the information class
+            // expects and outlives the call.
+            let ok = unsafe {
+                QueryInformationJobObject(
+                    self.0,
+                    JobObjectBasicAndIoAccountingInformation,
+                    (&raw mut accounting).cast(),
+                    size_of::<JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION>() as u32,
+                    ptr::null_mut(),
+                )
+            };
+            if ok != 0 {
+                crate::io_counters::record(
+                    accounting.IoInfo.ReadTransferCount,
+                    accounting.IoInfo.WriteTransferCount,
+                );
+            }
+            let mut violation = JOBOBJECT_LIMIT_VIOLATION_INFORMATION::default();
+            // SAFETY: as above.
+            let ok = unsafe {
+                QueryInformationJobObject(
+                    self.0,
+                    JobObjectLimitViolationInformation,
+                    (&raw mut violation).cast(),
+                    size_of::<JOBOBJECT_LIMIT_VIOLATION_INFORMATION>() as u32,
+                    ptr::null_mut(),
+                )
+            };
+            if ok != 0 && violation.ViolationLimitFlags & JOB_OBJECT_LIMIT_JOB_MEMORY != 0 {
+                crate::memory_limit::record_oom_kill();
+            }
+            // SAFETY: the handle is owned and closed exactly once.
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
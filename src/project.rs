@@ -0,0 +1,254 @@
+//! `bench project`: builds a binary of an existing project under each
+//! scenario instead of the generated payload, including one package of a
+//! multi-crate workspace. As with `bench examples`, the project's files are
+//! left alone apart from touching the binary's source: each scenario's cargo
+//! config and profile settings reach cargo through `--config`, which applies
+//! to the whole workspace.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::cli::ProjectArgs;
+use crate::config::Config;
+use crate::examples::{self, BuildEnv, Timing};
+use crate::wrapper::RustcWrapper;
+use crate::{Linker, PreparedScenario, bevy_features};
+
+/// The parts of `cargo metadata --no-deps` used here.
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    workspace_root: PathBuf,
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<BuildTarget>,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildTarget {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    name: String,
+    rename: Option<String>,
+}
+
+impl Package {
+    fn bins(&self) -> impl Iterator<Item = &BuildTarget> {
+        self.targets
+            .iter()
+            .filter(|target| target.kind.iter().any(|kind| kind == "bin"))
+    }
+
+    /// The key Bevy's features are enabled under, if the package depends on
+    /// Bevy directly.
+    fn bevy_key(&self) -> Option<&str> {
+        self.dependencies
+            .iter()
+            .find(|dependency| dependency.name == "bevy")
+            .map(|dependency| dependency.rename.as_deref().unwrap_or("bevy"))
+    }
+}
+
+pub fn run(args: &ProjectArgs, config: &Config) -> Result<()> {
+    if args.run.remote.is_some() {
+        bail!("`bench project` builds a local project and cannot use --remote");
+    }
+    let metadata = metadata(&args.path)?;
+    let package = select_package(&metadata, &args.path, args.package.as_deref())?;
+    let bin = select_bin(package, args.bin.as_deref())?;
+    let target = format!("{}/{}", package.name, bin.name);
+    println!(
+        "[bench] Benchmarking {target} in the workspace at {}.",
+        metadata.workspace_root.display()
+    );
+
+    let locked = examples::use_locked(&metadata.workspace_root);
+    let mut timings = Vec::new();
+    for scenario in &examples::host_scenarios(&args.run, config)? {
+        println!("[bench] Building {target} for {}...", scenario.slug);
+        match time_bin(
+            &metadata.workspace_root,
+            scenario,
+            config.wrapper.as_ref(),
+            package,
+            bin,
+            locked,
+        ) {
+            Ok(timing) => timings.push(timing),
+            Err(error) => eprintln!(
+                "[bench][warn] {target} failed for {}: {error:#}",
+                scenario.slug
+            ),
+        }
+    }
+    examples::print_table("binary", &timings);
+    Ok(())
+}
+
+fn metadata(dir: &Path) -> Result<Metadata> {
+    let output = Command::new("cargo")
+        .current_dir(dir)
+        .args(["metadata", "--no-deps", "--locked", "--format-version", "1"])
+        .output()
+        .context("failed to run cargo metadata")?;
+    if !output.status.success() {
+        bail!(
+            "cargo metadata in {} failed with status {}: {}",
+            dir.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    serde_json::from_slice(&output.stdout).context("failed to parse cargo metadata")
+}
+
+/// `name`, or else the package whose manifest is in `dir`, or else the
+/// workspace's only package.
+fn select_package<'a>(
+    metadata: &'a Metadata,
+    dir: &Path,
+    name: Option<&str>,
+) -> Result<&'a Package> {
+    let names = || {
+        metadata
+            .packages
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if let Some(name) = name {
+        return metadata
+            .packages
+            .iter()
+            .find(|package| package.name == name)
+            .with_context(|| format!("no package {name} in the workspace (found {})", names()));
+    }
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("failed to resolve {}", dir.display()))?;
+    let in_dir = metadata.packages.iter().find(|package| {
+        package
+            .manifest_path
+            .parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .is_some_and(|parent| parent == dir)
+    });
+    match (in_dir, metadata.packages.as_slice()) {
+        (Some(package), _) | (None, [package]) => Ok(package),
+        _ => bail!(
+            "the workspace has several packages; pick one with --package ({})",
+            names()
+        ),
+    }
+}
+
+/// `name`, or else the package's only binary, or else the one named after
+/// the package, as `cargo run` picks it.
+fn select_bin<'a>(package: &'a Package, name: Option<&str>) -> Result<&'a BuildTarget> {
+    let bins: Vec<_> = package.bins().collect();
+    let names = || {
+        bins.iter()
+            .map(|bin| bin.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let found = match (name, bins.as_slice()) {
+        (Some(name), _) => bins.iter().find(|bin| bin.name == name),
+        (None, [bin]) => Some(bin),
+        (None, _) => bins.iter().find(|bin| bin.name == package.name),
+    };
+    found
+        .copied()
+        .with_context(|| match (name, bins.is_empty()) {
+            (_, true) => format!("package {} has no binary", package.name),
+            (Some(name), false) => format!(
+                "package {} has no binary {name} (found {})",
+                package.name,
+                names()
+            ),
+            (None, false) => format!(
+                "package {} has several binaries; pick one with --bin ({})",
+                package.name,
+                names()
+            ),
+        })
+}
+
+fn time_bin(
+    root: &Path,
+    prepared: &PreparedScenario,
+    wrapper: Option<&RustcWrapper>,
+    package: &Package,
+    bin: &BuildTarget,
+    locked: bool,
+) -> Result<Timing> {
+    let features = bevy_features(&prepared.scenario, &prepared.custom);
+    let bevy = match (features.is_empty(), package.bevy_key()) {
+        (true, _) => "",
+        (false, Some(key)) => key,
+        (false, None) => bail!(
+            "{} does not depend on bevy, so the scenario's Bevy features cannot be enabled",
+            package.name
+        ),
+    };
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("bench-{}-{}-", prepared.slug, bin.name))
+        .tempdir()
+        .context("failed to create the binary's target directory")?;
+    let config = dir.path().join("config.toml");
+    fs::write(&config, &prepared.code.cargo_config_toml)
+        .context("failed to write the scenario's cargo config")?;
+    let env = BuildEnv::new(prepared, wrapper, dir.path())?;
+
+    let build = || -> Result<f64> {
+        let mut command = Command::new("cargo");
+        command
+            .current_dir(root)
+            .envs(env.vars.iter().map(|(key, value)| (key, value)))
+            .arg(Linker::build_subcommand(prepared.scenario.linker))
+            .arg("--quiet")
+            .args(["--package", &package.name, "--bin", &bin.name]);
+        if locked {
+            command.arg("--locked");
+        }
+        examples::config_args(&mut command, prepared, &config);
+        if !features.is_empty() {
+            let features: Vec<_> = features
+                .iter()
+                .map(|feature| format!("{bevy}/{feature}"))
+                .collect();
+            command.arg("--features").arg(features.join(","));
+        }
+        let start = Instant::now();
+        let status = command.status().context("failed to run cargo")?;
+        if !status.success() {
+            bail!("cargo build --bin {} failed with status {status}", bin.name);
+        }
+        Ok(start.elapsed().as_secs_f64())
+    };
+
+    let clean = build()?;
+    examples::touch(&bin.src_path)?;
+    let touched = build()?;
+    Ok(Timing {
+        slug: prepared.slug.clone(),
+        target: format!("{}/{}", package.name, bin.name),
+        clean,
+        touched,
+    })
+}
@@ -0,0 +1,196 @@
+//! The harness protocol behind `--ready-channel socket`: a line-based
+//! exchange over TCP between the harness and the payload. The payload
+//! connects to the address in [`ADDRESS_ENV`] and sends:
+//!
+//! - `HELLO <version>`, answered with `ACK`, or with `NACK <reason>` before
+//!   the harness hangs up;
+//! - `READY <marker>` once the app runs;
+//! - `FIRST_FRAME <milliseconds>` at the end of its first frame, counted
+//!   from `main`;
+//! - `VALUE <n>` whenever its payload value changes;
+//! - `FRAME <milliseconds>` after every frame.
+//!
+//! Before stopping the session the harness sends `BYE`, and the payload
+//! exits.
+
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::first_frame;
+use crate::runner::{StreamEvent, StreamKind};
+
+/// Protocol version the payload announces in `HELLO`.
+pub const VERSION: u32 = 1;
+/// Environment variable holding the address the payload connects to.
+pub const ADDRESS_ENV: &str = "BENCH_HARNESS_ADDR";
+
+/// How often the listener checks for new connections.
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+/// A message from the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Hello(u32),
+    Ready(String),
+    /// Milliseconds from `main` to the end of the first frame.
+    FirstFrame(f64),
+    Value(u64),
+    /// Duration of the last frame in milliseconds.
+    Frame(f64),
+}
+
+impl Message {
+    pub fn parse(line: &str) -> Option<Self> {
+        let (kind, rest) = line.trim().split_once(' ')?;
+        match kind {
+            "HELLO" => rest.parse().ok().map(Self::Hello),
+            "READY" => Some(Self::Ready(rest.to_string())),
+            "FIRST_FRAME" => rest.parse().ok().map(Self::FirstFrame),
+            "VALUE" => rest.parse().ok().map(Self::Value),
+            "FRAME" => rest.parse().ok().map(Self::Frame),
+            _ => None,
+        }
+    }
+}
+
+/// Listens for the payload on a free local port.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    address: SocketAddr,
+}
+
+impl Server {
+    pub fn bind() -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .context("failed to listen for the payload's harness connection")?;
+        let address = listener
+            .local_addr()
+            .context("failed to read the harness listener address")?;
+        Ok(Self { listener, address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Accepts payload connections on a background thread until the returned
+    /// session is dropped. Readiness and value messages are sent to `tx` as
+    /// the marker lines the payload would otherwise print; frame times as
+    /// [`StreamEvent::FrameTime`].
+    pub(crate) fn serve(&self, tx: Sender<StreamEvent>) -> Result<Session> {
+        let listener = self
+            .listener
+            .try_clone()
+            .context("failed to share the harness listener")?;
+        listener
+            .set_nonblocking(true)
+            .context("failed to poll the harness listener")?;
+        let session = Session {
+            stop: Arc::new(AtomicBool::new(false)),
+            connections: Arc::new(Mutex::new(Vec::new())),
+        };
+        let (stop, connections) = (Arc::clone(&session.stop), Arc::clone(&session.connections));
+        thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // Accepted sockets inherit non-blocking mode on some
+                        // platforms.
+                        if stream.set_nonblocking(false).is_err() {
+                            continue;
+                        }
+                        if let Ok(writer) = stream.try_clone() {
+                            connections
+                                .lock()
+                                .expect("harness connections lock poisoned")
+                                .push(writer);
+                        }
+                        let tx = tx.clone();
+                        thread::spawn(move || {
+                            if let Err(err) = handle(stream, &tx) {
+                                eprintln!("[bench][warn] Harness connection failed: {err}");
+                            }
+                        });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL);
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(session)
+    }
+}
+
+/// A served protocol session; dropping it says `BYE` to every connected
+/// payload and stops accepting new ones.
+pub(crate) struct Session {
+    stop: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let connections = self
+            .connections
+            .lock()
+            .expect("harness connections lock poisoned");
+        for mut connection in connections.iter() {
+            let _ = writeln!(connection, "BYE");
+        }
+    }
+}
+
+/// Runs the handshake, then forwards the payload's messages until it hangs
+/// up or the receiver is gone.
+fn handle(stream: TcpStream, tx: &Sender<StreamEvent>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    match lines
+        .next()
+        .transpose()?
+        .as_deref()
+        .and_then(Message::parse)
+    {
+        Some(Message::Hello(VERSION)) => writeln!(writer, "ACK")?,
+        Some(Message::Hello(version)) => {
+            writeln!(writer, "NACK unsupported protocol version {version}")?;
+            return Ok(());
+        }
+        _ => {
+            writeln!(writer, "NACK expected HELLO")?;
+            return Ok(());
+        }
+    }
+    for line in lines {
+        let line = line?;
+        let event = match Message::parse(&line) {
+            Some(Message::Ready(marker)) => StreamEvent::Line(StreamKind::Marker, marker),
+            Some(Message::FirstFrame(milliseconds)) => StreamEvent::Line(
+                StreamKind::Marker,
+                format!("{}{milliseconds}", first_frame::MARKER),
+            ),
+            Some(Message::Value(value)) => {
+                StreamEvent::Line(StreamKind::Marker, format!("PAYLOAD_RANDOM_VALUE={value}"))
+            }
+            Some(Message::Frame(milliseconds)) => StreamEvent::FrameTime(milliseconds),
+            Some(Message::Hello(_)) | None => {
+                eprintln!("[bench][warn] Unexpected harness message: {line}");
+                continue;
+            }
+        };
+        if tx.send(event).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Captured `dx serve` sessions: every output line with its time since dx
+//! started, plus the verdict the live run reached for its first patch. Replaying a recording
+//! through [`hotpatch::watch`] checks that marker parsing still reaches the
+//! same verdict, e.g. for output from another dx version.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::cli::ReplayArgs;
+use crate::error::BenchError;
+use crate::hotpatch::{self, HotpatchOutcome};
+use crate::redact;
+use crate::runner::{CommandRunner, FakeRunner, Script, StreamKind};
+
+/// Ready and patch timeout when replaying without the original pacing; every
+/// line is available immediately, so anything longer only slows down a
+/// failing test.
+const FAST_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    /// `dx --version` of the recorded session, when known.
+    #[serde(default)]
+    pub dx_version: Option<String>,
+    pub ready_marker: String,
+    /// Line the patched payload was expected to print, once triggered.
+    #[serde(default)]
+    pub patched_line: Option<String>,
+    pub lines: Vec<RecordedLine>,
+    /// Exit code, if dx exited on its own rather than being shut down.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub verdict: Option<Verdict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedLine {
+    /// Milliseconds since dx was started.
+    pub at_ms: u64,
+    pub stream: StreamKind,
+    pub line: String,
+}
+
+/// How a hotpatch session ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Verified,
+    Unverified(String),
+    Timeout,
+    Exited,
+    /// Any other failure, such as the trigger failing to write the source.
+    Failed,
+}
+
+impl Recording {
+    pub fn new(ready_marker: impl Into<String>, dx_version: Option<String>) -> Self {
+        Self {
+            dx_version,
+            ready_marker: ready_marker.into(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, at: Duration, stream: StreamKind, line: &str) {
+        self.lines.push(RecordedLine {
+            at_ms: at.as_millis() as u64,
+            stream,
+            line: line.to_string(),
+        });
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read recording {}", path.display()))?;
+        ron::from_str(&contents)
+            .with_context(|| format!("failed to parse recording {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .context("failed to serialize recording")?;
+        fs::write(path, redact::redact(&ron).as_bytes())
+            .with_context(|| format!("failed to write recording {}", path.display()))
+    }
+
+    /// The recorded output as a fake process script. With `realtime`, lines
+    /// keep their original spacing.
+    pub fn script(&self, realtime: bool) -> Script {
+        let mut script = Script::new();
+        let mut previous = 0;
+        for recorded in &self.lines {
+            if realtime && recorded.at_ms > previous {
+                script = script.delay(Duration::from_millis(recorded.at_ms - previous));
+                previous = recorded.at_ms;
+            }
+            script = match recorded.stream {
+                // The payload prints no markers when it writes them to a
+                // file, so they replay as its stdout.
+                StreamKind::Stdout | StreamKind::Marker => script.stdout(&recorded.line),
+                StreamKind::Stderr => script.stderr(&recorded.line),
+            };
+        }
+        match self.exit_code {
+            Some(code) => script.exit(code),
+            None => script.hang(),
+        }
+    }
+
+    /// Feeds the recording through the hotpatch watcher, triggering with the
+    /// recorded patched line.
+    pub fn replay(&self, realtime: bool) -> Result<HotpatchOutcome> {
+        let runner = FakeRunner::new().script("dx", self.script(realtime));
+        let mut process = runner.spawn(&mut Command::new("dx"), "dx")?;
+        let (ready_timeout, patch_timeout) = if realtime {
+            (hotpatch::READY_TIMEOUT, hotpatch::PATCH_TIMEOUT)
+        } else {
+            (FAST_TIMEOUT, FAST_TIMEOUT)
+        };
+        hotpatch::watch(
+            process.as_mut(),
+            &self.ready_marker,
+            ready_timeout,
+            patch_timeout,
+            None,
+            || {
+                self.patched_line
+                    .clone()
+                    .context("the recording has no patched line to wait for")
+            },
+            None,
+        )
+    }
+}
+
+/// `bench replay`: replays a recording and fails if the verdict changed.
+pub fn run(args: &ReplayArgs) -> Result<()> {
+    let recording = Recording::load(&args.recording)?;
+    println!(
+        "Replaying {} ({} lines, {})",
+        args.recording.display(),
+        recording.lines.len(),
+        recording
+            .dx_version
+            .as_deref()
+            .unwrap_or("unknown dx version")
+    );
+    let result = recording.replay(args.realtime);
+    let verdict = Verdict::of(&result);
+    match &result {
+        Ok(outcome) => println!("Hotpatch observed after {:.2?}.", outcome.elapsed),
+        Err(err) => println!("Hotpatch failed: {err}"),
+    }
+    match &recording.verdict {
+        Some(recorded) if *recorded != verdict => {
+            bail!("replay verdict {verdict:?} differs from the recorded {recorded:?}")
+        }
+        Some(_) => println!("Verdict {verdict:?} matches the recording."),
+        None => println!("Verdict {verdict:?} (the recording has none to compare)."),
+    }
+    Ok(())
+}
+
+impl Verdict {
+    pub fn of(result: &Result<HotpatchOutcome>) -> Self {
+        match result {
+            Ok(outcome) => Self::of_outcome(outcome),
+            Err(err) => Self::of_error(err),
+        }
+    }
+
+    pub fn of_outcome(outcome: &HotpatchOutcome) -> Self {
+        match &outcome.problem {
+            Some(problem) => Self::Unverified(problem.clone()),
+            None => Self::Verified,
+        }
+    }
+
+    pub fn of_error(err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<BenchError>() {
+            Some(BenchError::HotpatchTimeout { .. } | BenchError::PatchTimeout { .. }) => {
+                Self::Timeout
+            }
+            Some(BenchError::HotpatchExited { .. }) => Self::Exited,
+            _ => Self::Failed,
+        }
+    }
+}
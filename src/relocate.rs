@@ -0,0 +1,90 @@
+//! `--relocate`: how much of a target directory survives a move to another
+//! absolute path, as when CI restores a cached target directory into a
+//! checkout at a different location. The built workspace is copied, target
+//! directory and modification times included, and the unchanged payload is
+//! rebuilt in the copy.
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::phase::{Phase, PhaseResult};
+use crate::remote::Host;
+use crate::runner::CommandRunner;
+use crate::{PreparedScenario, Workspace, run_cargo_build};
+
+/// Times the rebuild in the relocated copy and, for local builds, records
+/// how many build units cargo reused.
+pub(crate) fn measure(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+    runner: &dyn CommandRunner,
+) -> Result<PhaseResult> {
+    println!("[bench] Copying the workspace to another path to time a relocated rebuild...");
+    let copy = workspace.duplicate(prepared)?;
+    let started = SystemTime::now();
+    let elapsed = run_cargo_build(&copy, prepared, Phase::Relocated.name(), runner)?;
+    let mut result = PhaseResult::new(Phase::Relocated, elapsed, Some(0));
+    // A remote build's fingerprints are not visible from here.
+    if matches!(copy.host, Host::Local) {
+        let (units, rebuilt) = count_units(Path::new(&copy.target_dir), started);
+        if units > 0 {
+            println!(
+                "[bench] The relocated build reused {} of {units} build units.",
+                units - rebuilt
+            );
+            result.metrics.insert("units".to_string(), units as f64);
+            result
+                .metrics
+                .insert("units_rebuilt".to_string(), rebuilt as f64);
+            result.metrics.insert(
+                "units_reused_fraction".to_string(),
+                (units - rebuilt) as f64 / units as f64,
+            );
+        }
+    }
+    Ok(result)
+}
+
+/// Build units in `target_dir`, and how many of them cargo rebuilt since
+/// `since`. Each unit has a directory in its profile's `.fingerprint`, and
+/// cargo only writes to those of units it rebuilds.
+fn count_units(target_dir: &Path, since: SystemTime) -> (usize, usize) {
+    let (mut units, mut rebuilt) = (0, 0);
+    // `<target>/<profile>`, or `<target>/<triple>/<profile>` for a target.
+    let profile_dirs = subdirs(target_dir).into_iter().flat_map(|dir| {
+        let mut dirs = subdirs(&dir);
+        dirs.push(dir);
+        dirs
+    });
+    for profile_dir in profile_dirs {
+        for unit in subdirs(&profile_dir.join(".fingerprint")) {
+            units += 1;
+            let written = fs::read_dir(&unit)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .any(|entry| {
+                    entry
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(|modified| modified >= since)
+                });
+            if written {
+                rebuilt += 1;
+            }
+        }
+    }
+    (units, rebuilt)
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
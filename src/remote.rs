@@ -0,0 +1,367 @@
+//! Where scenario commands execute: on this machine, or on a remote builder
+//! reached over SSH with the generated workspace mirrored via rsync.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub enum Host {
+    Local,
+    Ssh(SshHost),
+}
+
+#[derive(Debug, Clone)]
+pub struct SshHost {
+    /// `user@host` (or any destination understood by `ssh`).
+    pub destination: String,
+    /// Directory on the remote machine, relative to the login directory,
+    /// under which scenario workspaces are mirrored.
+    pub root: String,
+}
+
+impl Host {
+    pub fn from_remote(remote: Option<&str>, root: &str) -> Self {
+        match remote {
+            Some(destination) => Self::Ssh(SshHost {
+                destination: destination.to_string(),
+                root: root.trim_end_matches('/').to_string(),
+            }),
+            None => Self::Local,
+        }
+    }
+
+    /// Directory commands run in for a workspace whose local copy is `local`.
+    pub fn workspace_dir(&self, local: &Path) -> String {
+        match self {
+            Self::Local => local.display().to_string(),
+            Self::Ssh(ssh) => {
+                let name = local
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "workspace".to_string());
+                format!("{}/{name}", ssh.root)
+            }
+        }
+    }
+
+    pub fn join(&self, dir: &str, child: &str) -> String {
+        match self {
+            Self::Local => Path::new(dir).join(child).display().to_string(),
+            Self::Ssh(_) => format!("{dir}/{child}"),
+        }
+    }
+
+    /// Builds a command for `program` running in `dir` with `env` applied.
+    ///
+    /// For SSH hosts the remote shell receives the arguments appended to the
+    /// returned command joined with spaces, so they must not need quoting.
+    pub fn command(&self, dir: &str, env: &[(String, String)], program: &str) -> Command {
+        match self {
+            Self::Local => {
+                let mut command = Command::new(program);
+                command.current_dir(dir).envs(env.iter().cloned());
+                command
+            }
+            Self::Ssh(ssh) => {
+                let mut script = format!("cd {} && env", shell_quote(dir));
+                for (key, value) in env {
+                    script.push_str(&format!(" {key}={}", shell_quote(value)));
+                }
+                script.push(' ');
+                script.push_str(program);
+
+                let mut command = ssh.ssh();
+                command.arg(script);
+                command
+            }
+        }
+    }
+
+    /// Absolute path of a persistent directory named `name` for state shared
+    /// across scenarios. Absolute because it is handed to tools running in
+    /// a workspace directory.
+    pub fn shared_dir(&self, name: &str) -> Result<String> {
+        match self {
+            Self::Local => Ok(std::env::temp_dir()
+                .join("bevy-bench-shared")
+                .join(name)
+                .display()
+                .to_string()),
+            Self::Ssh(ssh) => {
+                let home = self.probe("pwd", &[]).with_context(|| {
+                    format!("failed to find home directory on {}", ssh.destination)
+                })?;
+                Ok(format!("{home}/{}/shared/{name}", ssh.root))
+            }
+        }
+    }
+
+    pub fn dir_exists(&self, dir: &str) -> Result<bool> {
+        match self {
+            Self::Local => Ok(Path::new(dir).is_dir()),
+            Self::Ssh(ssh) => {
+                let status = ssh
+                    .ssh()
+                    .arg(format!("test -d {}", shell_quote(dir)))
+                    .status()
+                    .with_context(|| format!("failed to check {dir} on {}", ssh.destination))?;
+                Ok(status.success())
+            }
+        }
+    }
+
+    /// Runs `script` through the host's shell (`sh -c`, or `cmd /C` on a local
+    /// Windows machine) in `dir` with `env` applied.
+    pub fn shell(&self, dir: &str, env: &[(String, String)], script: &str) -> Command {
+        match self {
+            Self::Local => {
+                let (shell, flag) = if cfg!(windows) {
+                    ("cmd", "/C")
+                } else {
+                    ("sh", "-c")
+                };
+                let mut command = self.command(dir, env, shell);
+                command.arg(flag).arg(script);
+                command
+            }
+            Self::Ssh(_) => {
+                let mut command = self.command(dir, env, "sh -c");
+                command.arg(shell_quote(script));
+                command
+            }
+        }
+    }
+
+    /// Mirrors the local workspace to the host. A no-op for local runs.
+    pub fn push(&self, local: &Path, dir: &str) -> Result<()> {
+        let Self::Ssh(ssh) = self else {
+            return Ok(());
+        };
+        run_checked(
+            ssh.ssh().arg(format!("mkdir -p {}", shell_quote(dir))),
+            "create remote workspace directory",
+        )?;
+        run_checked(
+            Command::new("rsync")
+                .arg("-a")
+                .arg("--delete")
+                .arg("--exclude")
+                .arg("target/")
+                .arg("--exclude")
+                .arg(".sccache/")
+                .arg("--exclude")
+                .arg(".cachepot/")
+                .arg(format!("{}/", local.display()))
+                .arg(format!("{}:{dir}/", ssh.destination)),
+            "rsync workspace to remote host",
+        )
+    }
+
+    /// Reads a file from the host, e.g. the lockfile cargo generated there.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Local => std::fs::read(path).with_context(|| format!("failed to read {path}")),
+            Self::Ssh(ssh) => {
+                let output = ssh
+                    .ssh()
+                    .arg(format!("cat {}", shell_quote(path)))
+                    .output()
+                    .with_context(|| format!("failed to read {path} on {}", ssh.destination))?;
+                if !output.status.success() {
+                    bail!(
+                        "reading {path} on {} failed with status {}",
+                        ssh.destination,
+                        output.status
+                    );
+                }
+                Ok(output.stdout)
+            }
+        }
+    }
+
+    pub fn file_size(&self, path: &str) -> Result<u64> {
+        match self {
+            Self::Local => Ok(std::fs::metadata(path)
+                .with_context(|| format!("failed to stat {path}"))?
+                .len()),
+            Self::Ssh(ssh) => {
+                let output = ssh
+                    .ssh()
+                    .arg(format!("wc -c < {}", shell_quote(path)))
+                    .output()
+                    .with_context(|| format!("failed to stat {path} on {}", ssh.destination))?;
+                if !output.status.success() {
+                    bail!(
+                        "stat of {path} on {} failed with status {}",
+                        ssh.destination,
+                        output.status
+                    );
+                }
+                String::from_utf8_lossy(&output.stdout)
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("unexpected size output for {path}"))
+            }
+        }
+    }
+
+    /// Total size in bytes of the files under `dir`.
+    pub fn dir_size(&self, dir: &str) -> Result<u64> {
+        match self {
+            Self::Local => local_dir_size(Path::new(dir)),
+            Self::Ssh(ssh) => {
+                let output = ssh
+                    .ssh()
+                    .arg(format!("du -sb {}", shell_quote(dir)))
+                    .output()
+                    .with_context(|| format!("failed to measure {dir} on {}", ssh.destination))?;
+                if !output.status.success() {
+                    bail!(
+                        "measuring {dir} on {} failed with status {}",
+                        ssh.destination,
+                        output.status
+                    );
+                }
+                String::from_utf8_lossy(&output.stdout)
+                    .split_whitespace()
+                    .next()
+                    .and_then(|size| size.parse().ok())
+                    .with_context(|| format!("unexpected du output for {dir}"))
+            }
+        }
+    }
+
+    /// First line of `program args` run on the host, or `None` if the tool
+    /// is missing or fails.
+    pub fn probe(&self, program: &str, args: &[&str]) -> Option<String> {
+        let output = self.command(".", &[], program).args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Some(stdout.lines().next()?.trim().to_string())
+    }
+
+    /// One-minute load average, on hosts that expose `/proc/loadavg`.
+    pub fn load_average(&self) -> Option<f64> {
+        let contents = self.read("/proc/loadavg").ok()?;
+        String::from_utf8_lossy(&contents)
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    /// Removes a remote workspace. Local workspaces clean themselves up.
+    /// Copies the contents of `from` into `to` on the host, keeping
+    /// modification times so cargo's fingerprints stay meaningful.
+    pub fn copy_dir(&self, from: &str, to: &str) -> Result<()> {
+        match self {
+            Self::Local => copy_local_dir(Path::new(from), Path::new(to)),
+            Self::Ssh(ssh) => run_checked(
+                ssh.ssh().arg(format!(
+                    "mkdir -p {to} && cp -a {from}/. {to}/",
+                    from = shell_quote(from),
+                    to = shell_quote(to)
+                )),
+                "copy remote directory",
+            ),
+        }
+    }
+
+    pub fn remove(&self, dir: &str) -> Result<()> {
+        let Self::Ssh(ssh) = self else {
+            return Ok(());
+        };
+        run_checked(
+            ssh.ssh().arg(format!("rm -rf {}", shell_quote(dir))),
+            "remove remote workspace",
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Local => "local machine".to_string(),
+            Self::Ssh(ssh) => format!("{} (under ~/{})", ssh.destination, ssh.root),
+        }
+    }
+}
+
+impl SshHost {
+    fn ssh(&self) -> Command {
+        let mut command = Command::new("ssh");
+        command
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg("ServerAliveInterval=30")
+            .arg(&self.destination);
+        command
+    }
+}
+
+fn run_checked(command: &mut Command, action: &str) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to {action}"))?;
+    if !status.success() {
+        bail!("failed to {action}: exit status {status}");
+    }
+    Ok(())
+}
+
+fn local_dir_size(dir: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?
+    {
+        let entry = entry.with_context(|| format!("failed to read entry of {}", dir.display()))?;
+        let metadata = entry
+            .metadata()
+            .with_context(|| format!("failed to stat {}", entry.path().display()))?;
+        total += if metadata.is_dir() {
+            local_dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+fn copy_local_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+    for entry in
+        std::fs::read_dir(from).with_context(|| format!("failed to read {}", from.display()))?
+    {
+        let entry = entry.with_context(|| format!("failed to read entry of {}", from.display()))?;
+        let (source, destination) = (entry.path(), to.join(entry.file_name()));
+        let metadata = entry
+            .metadata()
+            .with_context(|| format!("failed to stat {}", source.display()))?;
+        if metadata.is_dir() {
+            copy_local_dir(&source, &destination)?;
+            continue;
+        }
+        std::fs::copy(&source, &destination).with_context(|| {
+            format!(
+                "failed to copy {} to {}",
+                source.display(),
+                destination.display()
+            )
+        })?;
+        let modified = metadata
+            .modified()
+            .with_context(|| format!("failed to read mtime of {}", source.display()))?;
+        std::fs::File::options()
+            .write(true)
+            .open(&destination)
+            .and_then(|file| file.set_modified(modified))
+            .with_context(|| format!("failed to set mtime of {}", destination.display()))?;
+    }
+    Ok(())
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
@@ -0,0 +1,182 @@
+//! Final comparison table printed at the end of a run.
+
+use std::io::IsTerminal;
+
+use crate::history::History;
+use crate::{Scenario, ScenarioRecord};
+
+/// A phase this much slower than its historical median counts as a regression.
+const REGRESSION_THRESHOLD: f64 = 0.10;
+
+const COLUMNS: [&str; 5] = [
+    "clean",
+    "second",
+    "modified",
+    "hotpatch",
+    "hotpatch-rebuild",
+];
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether to emit ANSI colors: not disabled by flag or `NO_COLOR`, and
+/// stdout is a terminal.
+pub fn use_color(no_color: bool) -> bool {
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::io::stdout().is_terminal()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Highlight {
+    None,
+    Fastest,
+    Regression,
+}
+
+/// Prints one row per successful scenario with the phase timings aligned in
+/// columns. The fastest value in each column is green; values more than 10%
+/// slower than the scenario's historical median are red. Without colors the
+/// same cells are marked with `*` and `!`.
+///
+/// When the reference scenario succeeded, every cell also shows the speedup
+/// over the reference's time in that phase and the change in percent.
+pub fn print_table(
+    records: &[ScenarioRecord],
+    history: &History,
+    reference: Option<&str>,
+    color: bool,
+) {
+    let records: Vec<&ScenarioRecord> = records
+        .iter()
+        .filter(|record| record.error.is_none())
+        .collect();
+    if records.is_empty() {
+        return;
+    }
+    let reference = find_reference(&records, reference);
+
+    let fastest: Vec<Option<f64>> = COLUMNS
+        .iter()
+        .map(|phase| {
+            records
+                .iter()
+                .filter_map(|record| record.timings.phase(phase))
+                .min_by(f64::total_cmp)
+        })
+        .collect();
+
+    let rows: Vec<(&str, Vec<(String, Highlight)>)> = records
+        .iter()
+        .map(|record| {
+            let baseline = history.baseline(&record.slug);
+            let cells = COLUMNS
+                .iter()
+                .zip(&fastest)
+                .map(|(phase, fastest)| {
+                    let Some(seconds) = record.timings.phase(phase) else {
+                        return ("-".to_string(), Highlight::None);
+                    };
+                    let regressed = baseline
+                        .and_then(|baseline| baseline.phase(phase))
+                        .is_some_and(|before| seconds > before * (1.0 + REGRESSION_THRESHOLD));
+                    let highlight = if regressed {
+                        Highlight::Regression
+                    } else if *fastest == Some(seconds) {
+                        Highlight::Fastest
+                    } else {
+                        Highlight::None
+                    };
+                    let marker = match (color, highlight) {
+                        (true, _) | (false, Highlight::None) => "",
+                        (false, Highlight::Fastest) => "*",
+                        (false, Highlight::Regression) => "!",
+                    };
+                    let relative = reference
+                        .and_then(|reference| reference.timings.phase(phase))
+                        .map(|base| {
+                            let change = (seconds - base) / base * 100.0;
+                            format!(" {:>5.2}x {change:>+4.0}%", base / seconds)
+                        })
+                        .unwrap_or_default();
+                    (format!("{seconds:.3}s{marker}{relative}"), highlight)
+                })
+                .collect();
+            (record.slug.as_str(), cells)
+        })
+        .collect();
+
+    let slug_width = rows
+        .iter()
+        .map(|(slug, _)| slug.len())
+        .chain(["scenario".len()])
+        .max()
+        .unwrap_or_default();
+    let widths: Vec<usize> = COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            rows.iter()
+                .map(|(_, cells)| cells[index].0.len())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    println!();
+    let mut header = format!("{:<slug_width$}", "scenario");
+    for (column, width) in COLUMNS.iter().zip(&widths) {
+        header.push_str(&format!("  {column:>width$}"));
+    }
+    println!("{header}");
+    println!("{}", "-".repeat(header.len()));
+    for (slug, cells) in rows {
+        let mut line = format!("{slug:<slug_width$}");
+        for ((text, highlight), width) in cells.iter().zip(&widths) {
+            // Pad before coloring so escape codes do not skew the alignment.
+            let cell = format!("{text:>width$}");
+            let cell = match (color, highlight) {
+                (true, Highlight::Fastest) => format!("{GREEN}{cell}{RESET}"),
+                (true, Highlight::Regression) => format!("{RED}{cell}{RESET}"),
+                _ => cell,
+            };
+            line.push_str("  ");
+            line.push_str(&cell);
+        }
+        println!("{line}");
+    }
+    if !color {
+        println!("* fastest in column, ! more than 10% slower than history");
+    }
+    if let Some(reference) = reference {
+        println!(
+            "Speedups are relative to {} (above 1x is faster).",
+            reference.slug
+        );
+    }
+}
+
+/// The scenario named by `--reference`, or else the one with every built-in
+/// axis at its default.
+fn find_reference<'a>(
+    records: &[&'a ScenarioRecord],
+    slug: Option<&str>,
+) -> Option<&'a ScenarioRecord> {
+    match slug {
+        Some(slug) => {
+            let found = records.iter().find(|record| record.slug == slug).copied();
+            if found.is_none() {
+                eprintln!(
+                    "[bench][warn] Reference scenario {slug} did not run successfully; showing absolute times only."
+                );
+            }
+            found
+        }
+        None => records
+            .iter()
+            .find(|record| record.scenario == Scenario::default())
+            .copied(),
+    }
+}
@@ -0,0 +1,668 @@
+//! The layer between the harness and `std::process`: timed builds and
+//! `dx serve` go through a [`CommandRunner`], so tests can replace the real
+//! tools with [`FakeRunner`] scripts.
+
+use anyhow::{Context, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, PipeReader, PipeWriter, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture;
+use crate::error::{BenchError, CapturedOutput};
+use crate::process_tree::{self, ProcessTree};
+use crate::redact;
+use crate::tail::OutputTail;
+use crate::timeline;
+use crate::watchdog;
+
+/// Starts processes. The harness uses [`SystemRunner`]; tests use
+/// [`FakeRunner`].
+pub trait CommandRunner: Send + Sync + fmt::Debug {
+    /// Spawns `command` with piped stdout and stderr. `tool` names the
+    /// executable in errors.
+    fn spawn(&self, command: &mut Command, tool: &str) -> Result<Box<dyn Process>>;
+}
+
+/// A running child process.
+pub trait Process {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>>;
+    fn wait(&mut self) -> io::Result<ExitStatus>;
+    fn kill(&mut self) -> io::Result<()>;
+    /// Operating system process id, for diagnostics.
+    fn id(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Runs real processes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemRunner {
+    nice: Option<i32>,
+}
+
+impl SystemRunner {
+    /// Starts every process, and so everything it starts, with niceness
+    /// `nice` (`--nice`), or a lower priority class on Windows.
+    pub fn niced(nice: i32) -> Self {
+        Self { nice: Some(nice) }
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn spawn(&self, command: &mut Command, tool: &str) -> Result<Box<dyn Process>> {
+        if process_tree::interrupted() {
+            return Err(BenchError::Interrupted.into());
+        }
+        ProcessTree::prepare(command, self.nice);
+        let mut child = spawn_tool(command.stdout(Stdio::piped()).stderr(Stdio::piped()), tool)?;
+        match ProcessTree::attach(&child) {
+            Ok(tree) => Ok(Box::new(SystemProcess { child, tree })),
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(anyhow::Error::new(err).context(format!(
+                    "failed to tie the {tool} process tree to the harness"
+                )))
+            }
+        }
+    }
+}
+
+/// A real child whose descendants are killed along with it.
+#[derive(Debug)]
+struct SystemProcess {
+    child: Child,
+    tree: ProcessTree,
+}
+
+impl Process for SystemProcess {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child.take_stdout()
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child.take_stderr()
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.child.wait()
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        self.tree.kill(&mut self.child)
+    }
+
+    fn id(&self) -> Option<u32> {
+        Some(self.child.id())
+    }
+}
+
+impl Process for Child {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as Box<dyn Read + Send>)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self)
+    }
+
+    fn id(&self) -> Option<u32> {
+        Some(Child::id(self))
+    }
+}
+
+/// Spawns `command`, reporting a missing executable as
+/// [`BenchError::ToolMissing`].
+pub(crate) fn spawn_tool(command: &mut Command, tool: &str) -> Result<Child> {
+    command.spawn().map_err(spawn_error(tool))
+}
+
+/// Maps the error of starting `tool`, e.g. with [`Command::status`], to
+/// [`BenchError::ToolMissing`] if the executable was not found. Only here is
+/// `NotFound` about the tool rather than a file it was given.
+pub(crate) fn spawn_error(tool: &str) -> impl FnOnce(io::Error) -> anyhow::Error + '_ {
+    move |err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            BenchError::ToolMissing {
+                tool: tool.to_string(),
+            }
+            .into()
+        } else {
+            anyhow::Error::new(err)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StreamKind {
+    Stdout,
+    Stderr,
+    /// A line the payload appended to its marker file.
+    Marker,
+}
+
+/// One line or state change from the readers of a process's output.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Line(StreamKind, String),
+    Closed(StreamKind),
+    /// A frame time in milliseconds reported over the harness protocol.
+    FrameTime(f64),
+}
+
+/// Runs a `cargo build` to completion and returns how long it took. A
+/// non-zero exit becomes [`BenchError::BuildFailed`] with the output tail.
+pub fn run_build(
+    runner: &dyn CommandRunner,
+    command: &mut Command,
+    label: &str,
+) -> Result<Duration> {
+    run_build_logged(runner, command, label, None)
+}
+
+/// A build log the readers of both pipes append to, each line as the tool
+/// printed it.
+type SharedLog = Arc<Mutex<File>>;
+
+/// Like [`run_build`], but also appends the full output to `log`.
+pub fn run_build_logged(
+    runner: &dyn CommandRunner,
+    command: &mut Command,
+    label: &str,
+    log: Option<&Path>,
+) -> Result<Duration> {
+    let log: Option<SharedLog> = log
+        .map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(|file| Arc::new(Mutex::new(file)))
+                .with_context(|| format!("failed to open build log {}", path.display()))
+        })
+        .transpose()?;
+    let write_log = |message: String| -> Result<()> {
+        if let Some(log) = &log {
+            let mut log = log.lock().expect("build log lock poisoned");
+            writeln!(log, "{} {message}", timeline::stamp())
+                .context("failed to write build log")?;
+        }
+        Ok(())
+    };
+    write_log(format!("BEGIN cargo build ({label})"))?;
+    let start = Instant::now();
+    let mut process = runner
+        .spawn(command, "cargo")
+        .with_context(|| format!("failed to run cargo build ({label})"))?;
+
+    let tail = collect_output(process.as_mut(), "cargo", log.clone())?;
+    let status = process
+        .wait()
+        .with_context(|| format!("failed to wait for cargo build ({label})"))?;
+    let elapsed = start.elapsed();
+    write_log(format!(
+        "END cargo build ({label}) {} {:.3}s",
+        if status.success() {
+            "ok".to_string()
+        } else {
+            format!("failed ({status})")
+        },
+        elapsed.as_secs_f64()
+    ))?;
+
+    if !status.success() {
+        return Err(BenchError::BuildFailed {
+            phase: label.to_string(),
+            status,
+            output: CapturedOutput::new(format!("cargo build ({label})"), tail),
+        }
+        .into());
+    }
+
+    Ok(elapsed)
+}
+
+/// Forwards a process's stdout/stderr to the console, and to `log` if set,
+/// until both pipes close and returns the last lines of each.
+fn collect_output(
+    process: &mut dyn Process,
+    source: &str,
+    log: Option<SharedLog>,
+) -> Result<OutputTail> {
+    let (tx, rx) = mpsc::channel();
+    stream_output_into(process, source, tx, log)?;
+    let mut tail = OutputTail::default();
+    let mut last_output = Instant::now();
+    loop {
+        let event = match watchdog::current() {
+            Some(watchdog) => {
+                match rx.recv_timeout(watchdog.window.saturating_sub(last_output.elapsed())) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let silent = last_output.elapsed();
+                        if watchdog.stalled(source, process, &tail, silent) {
+                            shutdown_process(process, source)?;
+                            return Err(BenchError::Stalled {
+                                tool: source.to_string(),
+                                silent_seconds: silent.as_secs(),
+                                output: CapturedOutput::new(source, tail),
+                            }
+                            .into());
+                        }
+                        last_output = Instant::now();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match rx.recv() {
+                Ok(event) => event,
+                Err(_) => break,
+            },
+        };
+        last_output = Instant::now();
+        if let StreamEvent::Line(kind, line) = event {
+            forward_stream_line(source, kind, &line);
+            tail.push(kind, line);
+        }
+    }
+    Ok(tail)
+}
+
+/// Reads both pipes of `process` on background threads, one event per line
+/// with the line's [sanitized](capture::sanitize) text.
+pub(crate) fn stream_output(
+    process: &mut dyn Process,
+    source: &str,
+) -> Result<mpsc::Receiver<StreamEvent>> {
+    let (tx, rx) = mpsc::channel();
+    stream_output_into(process, source, tx, None)?;
+    Ok(rx)
+}
+
+/// Like [`stream_output`], but sends to `tx`, so other sources can share the
+/// channel, and appends the raw lines to `log` if set.
+pub(crate) fn stream_output_into(
+    process: &mut dyn Process,
+    source: &str,
+    tx: Sender<StreamEvent>,
+    log: Option<SharedLog>,
+) -> Result<()> {
+    let stdout = process
+        .take_stdout()
+        .with_context(|| format!("{source} stdout pipe missing"))?;
+    let stderr = process
+        .take_stderr()
+        .with_context(|| format!("{source} stderr pipe missing"))?;
+
+    spawn_stream_reader(stdout, StreamKind::Stdout, tx.clone(), log.clone());
+    spawn_stream_reader(stderr, StreamKind::Stderr, tx, log);
+    Ok(())
+}
+
+fn spawn_stream_reader<R>(
+    reader: R,
+    kind: StreamKind,
+    tx: Sender<StreamEvent>,
+    log: Option<SharedLog>,
+) where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut log_failed = false;
+        let _ = capture::read_lines(BufReader::new(reader), |raw| {
+            if let Some(log) = &log
+                && !log_failed
+                && let Err(err) = write_raw_line(log, kind, raw)
+            {
+                eprintln!("[bench][warn] Failed to write build log: {err}");
+                log_failed = true;
+            }
+            tx.send(StreamEvent::Line(kind, capture::sanitize(raw)))
+                .is_ok()
+        });
+        let _ = tx.send(StreamEvent::Closed(kind));
+    });
+}
+
+/// Appends `raw` to `log` with a timestamp and its stream; secrets are masked
+/// and every other byte is kept.
+fn write_raw_line(log: &SharedLog, kind: StreamKind, raw: &[u8]) -> io::Result<()> {
+    let mut line = format!("{} [{}] ", timeline::stamp(), kind.label()).into_bytes();
+    line.extend_from_slice(&redact::redact_bytes(raw));
+    line.push(b'\n');
+    log.lock()
+        .expect("build log lock poisoned")
+        .write_all(&line)
+}
+
+pub(crate) fn forward_stream_line(source: &str, kind: StreamKind, line: &str) {
+    let (line, stamp) = (redact::redact(line), timeline::stamp());
+    match kind {
+        StreamKind::Stdout => println!("{stamp} [{source}] {line}"),
+        StreamKind::Stderr => eprintln!("{stamp} [{source}][stderr] {line}"),
+        StreamKind::Marker => println!("{stamp} [{source}][marker] {line}"),
+    }
+}
+
+impl StreamKind {
+    /// Name of the stream in log files.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+            Self::Marker => "marker",
+        }
+    }
+}
+
+pub(crate) fn shutdown_process(process: &mut dyn Process, source: &str) -> Result<()> {
+    if process.try_wait()?.is_none() {
+        process.kill().ok();
+        process
+            .wait()
+            .with_context(|| format!("failed to wait for {source} during shutdown"))?;
+    }
+    Ok(())
+}
+
+/// Canned output for one fake process.
+#[derive(Debug, Clone)]
+pub struct Script {
+    steps: Vec<Step>,
+    /// Exit code after the last step; `None` keeps running until killed.
+    exit: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Line(StreamKind, String),
+    /// Bytes written to stdout as they are, without a newline.
+    Bytes(Vec<u8>),
+    Delay(Duration),
+}
+
+impl Script {
+    /// A process that prints nothing and exits successfully.
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            exit: Some(0),
+        }
+    }
+
+    pub fn stdout(mut self, line: impl Into<String>) -> Self {
+        self.steps.push(Step::Line(StreamKind::Stdout, line.into()));
+        self
+    }
+
+    /// Writes `bytes` to stdout unchanged, for output that is not UTF-8 or
+    /// not split into lines.
+    pub fn stdout_bytes(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.steps.push(Step::Bytes(bytes.into()));
+        self
+    }
+
+    pub fn stderr(mut self, line: impl Into<String>) -> Self {
+        self.steps.push(Step::Line(StreamKind::Stderr, line.into()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.steps.push(Step::Delay(delay));
+        self
+    }
+
+    pub fn exit(mut self, code: i32) -> Self {
+        self.exit = Some(code);
+        self
+    }
+
+    /// Keeps the process alive after the last step, like `dx serve`.
+    pub fn hang(mut self) -> Self {
+        self.exit = None;
+        self
+    }
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replays [`Script`]s instead of running tools, in the order they were
+/// queued per tool. Spawning a tool with no script left fails like a missing
+/// executable.
+#[derive(Debug, Default)]
+pub struct FakeRunner {
+    scripts: Mutex<HashMap<String, VecDeque<Script>>>,
+    invocations: Mutex<Vec<Vec<String>>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `script` for the next spawn of `tool`.
+    pub fn script(self, tool: &str, script: Script) -> Self {
+        self.scripts
+            .lock()
+            .expect("fake runner lock poisoned")
+            .entry(tool.to_string())
+            .or_default()
+            .push_back(script);
+        self
+    }
+
+    /// Program and arguments of every command spawned so far.
+    pub fn invocations(&self) -> Vec<Vec<String>> {
+        self.invocations
+            .lock()
+            .expect("fake runner lock poisoned")
+            .clone()
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn spawn(&self, command: &mut Command, tool: &str) -> Result<Box<dyn Process>> {
+        let invocation = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        self.invocations
+            .lock()
+            .expect("fake runner lock poisoned")
+            .push(invocation);
+        let script = self
+            .scripts
+            .lock()
+            .expect("fake runner lock poisoned")
+            .get_mut(tool)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| BenchError::ToolMissing {
+                tool: tool.to_string(),
+            })?;
+        Ok(Box::new(FakeProcess::start(script)?))
+    }
+}
+
+/// Writes a script to real pipes from a background thread.
+struct FakeProcess {
+    stdout: Option<PipeReader>,
+    stderr: Option<PipeReader>,
+    state: Arc<(Mutex<FakeState>, Condvar)>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    killed: bool,
+    status: Option<ExitStatus>,
+}
+
+impl FakeProcess {
+    fn start(script: Script) -> io::Result<Self> {
+        let (stdout, stdout_writer) = io::pipe()?;
+        let (stderr, stderr_writer) = io::pipe()?;
+        let state = Arc::new((Mutex::new(FakeState::default()), Condvar::new()));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || play(script, stdout_writer, stderr_writer, &shared));
+        Ok(Self {
+            stdout: Some(stdout),
+            stderr: Some(stderr),
+            state,
+        })
+    }
+}
+
+fn play(
+    script: Script,
+    mut stdout: PipeWriter,
+    mut stderr: PipeWriter,
+    state: &(Mutex<FakeState>, Condvar),
+) {
+    let (lock, condvar) = state;
+    let killed_within = |timeout: Duration| {
+        let guard = lock.lock().expect("fake process lock poisoned");
+        condvar
+            .wait_timeout_while(guard, timeout, |state| !state.killed)
+            .expect("fake process lock poisoned")
+            .0
+            .killed
+    };
+
+    let mut killed = false;
+    for step in script.steps {
+        match step {
+            // Scripts have no marker file; only `stdout` and `stderr` add lines.
+            Step::Line(StreamKind::Stdout | StreamKind::Marker, line) => {
+                let _ = writeln!(stdout, "{line}");
+            }
+            Step::Line(StreamKind::Stderr, line) => {
+                let _ = writeln!(stderr, "{line}");
+            }
+            Step::Bytes(bytes) => {
+                let _ = stdout.write_all(&bytes);
+            }
+            Step::Delay(delay) => killed = killed_within(delay),
+        }
+        if killed || lock.lock().expect("fake process lock poisoned").killed {
+            killed = true;
+            break;
+        }
+    }
+    if !killed && script.exit.is_none() {
+        let guard = lock.lock().expect("fake process lock poisoned");
+        drop(
+            condvar
+                .wait_while(guard, |state| !state.killed)
+                .expect("fake process lock poisoned"),
+        );
+        killed = true;
+    }
+
+    // The status is set before the pipes close, as a real process has exited
+    // by the time its output ends.
+    let status = match script.exit {
+        Some(code) if !killed => exit_status(code),
+        _ => killed_status(),
+    };
+    lock.lock().expect("fake process lock poisoned").status = Some(status);
+    condvar.notify_all();
+    drop(stdout);
+    drop(stderr);
+}
+
+impl Process for FakeProcess {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as Box<dyn Read + Send>)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Ok(self
+            .state
+            .0
+            .lock()
+            .expect("fake process lock poisoned")
+            .status)
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        let (lock, condvar) = &*self.state;
+        let guard = lock.lock().expect("fake process lock poisoned");
+        let guard = condvar
+            .wait_while(guard, |state| state.status.is_none())
+            .expect("fake process lock poisoned");
+        Ok(guard.status.expect("waited for a status"))
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        let (lock, condvar) = &*self.state;
+        lock.lock().expect("fake process lock poisoned").killed = true;
+        condvar.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// The status of a process terminated by [`Process::kill`].
+#[cfg(unix)]
+fn killed_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    // SIGKILL
+    ExitStatus::from_raw(9)
+}
+
+#[cfg(windows)]
+fn killed_status() -> ExitStatus {
+    exit_status(1)
+}
@@ -0,0 +1,219 @@
+//! Dedicated sccache server per scenario, so cache contents and statistics
+//! never bleed between scenarios. cachepot, an sccache fork, runs the same
+//! way under its own names.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::port;
+use crate::remote::Host;
+
+/// An sccache-style compiler cache with a local server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompilerCache {
+    Sccache,
+    Cachepot,
+}
+
+impl CompilerCache {
+    /// Program name, also used as cargo's `rustc-wrapper`.
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Sccache => "sccache",
+            Self::Cachepot => "cachepot",
+        }
+    }
+
+    /// Prefix of the tool's environment variables.
+    pub(crate) fn env_prefix(self) -> &'static str {
+        match self {
+            Self::Sccache => "SCCACHE",
+            Self::Cachepot => "CACHEPOT",
+        }
+    }
+
+    /// Cache directory inside the workspace.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Self::Sccache => ".sccache",
+            Self::Cachepot => ".cachepot",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SccacheServer {
+    tool: CompilerCache,
+    host: Host,
+    workspace: String,
+    dir: String,
+    port: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SccacheStats {
+    pub compile_requests: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Compilations the cache refused, e.g. incremental ones.
+    #[serde(default)]
+    pub non_cacheable: u64,
+    /// Size of the cache directory after the scenario.
+    #[serde(default)]
+    pub cache_bytes: Option<u64>,
+}
+
+/// Compiler cache use of a run up to and including one scenario: what a
+/// single cache shared by all of them would have grown to.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CacheGrowth {
+    /// Scenarios with compiler cache statistics so far.
+    pub scenarios: usize,
+    pub cache_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub non_cacheable: u64,
+}
+
+impl CacheGrowth {
+    /// Adds one scenario's statistics.
+    pub fn add(&mut self, stats: &SccacheStats) {
+        self.scenarios += 1;
+        self.cache_bytes += stats.cache_bytes.unwrap_or(0);
+        self.hits += stats.hits;
+        self.misses += stats.misses;
+        self.non_cacheable += stats.non_cacheable;
+    }
+
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+impl SccacheServer {
+    /// Starts a server on a free port with its cache stored under
+    /// `workspace/.sccache` (or `.cachepot`). The server is stopped when the
+    /// value is dropped.
+    pub fn start(host: &Host, workspace: &str, tool: CompilerCache) -> Result<Self> {
+        let program = tool.program();
+        let dir = host.join(workspace, tool.dir_name());
+        let port = port::free(program)?;
+        let server = Self {
+            tool,
+            host: host.clone(),
+            workspace: workspace.to_string(),
+            dir,
+            port,
+        };
+        println!(
+            "[bench] Starting isolated {program} server on port {port} ({}_DIR={})",
+            tool.env_prefix(),
+            server.dir
+        );
+        let status = server
+            .command()
+            .arg("--start-server")
+            .status()
+            .with_context(|| format!("failed to start {program} server"))?;
+        if !status.success() {
+            bail!("{program} --start-server failed with status {status}");
+        }
+        Ok(server)
+    }
+
+    /// Environment every cargo/dx invocation needs so the wrapper talks to
+    /// this server rather than a shared default one.
+    pub fn env(&self) -> Vec<(String, String)> {
+        let prefix = self.tool.env_prefix();
+        vec![
+            (format!("{prefix}_DIR"), self.dir.clone()),
+            (format!("{prefix}_SERVER_PORT"), self.port.to_string()),
+        ]
+    }
+
+    pub fn stats(&self) -> Result<SccacheStats> {
+        let program = self.tool.program();
+        let output = self
+            .command()
+            .arg("--show-stats")
+            .output()
+            .with_context(|| format!("failed to query {program} stats"))?;
+        if !output.status.success() {
+            bail!(
+                "{program} --show-stats failed with status {}",
+                output.status
+            );
+        }
+        let mut stats = SccacheStats::parse(&String::from_utf8_lossy(&output.stdout));
+        stats.cache_bytes = self
+            .host
+            .dir_size(&self.dir)
+            .inspect_err(|err| {
+                eprintln!("[bench][warn] Could not measure the {program} cache: {err:#}")
+            })
+            .ok();
+        // cachepot reads the same variables under its own prefix; a server
+        // that saw no request means cargo compiled without it.
+        if self.tool == CompilerCache::Cachepot && stats.compile_requests == 0 {
+            bail!(
+                "the cachepot server received no compile requests; cargo did not build through it"
+            );
+        }
+        Ok(stats)
+    }
+
+    /// Resets the statistics, so they only cover builds from here on.
+    pub fn zero_stats(&self) -> Result<()> {
+        let program = self.tool.program();
+        let status = self
+            .command()
+            .arg("--zero-stats")
+            .status()
+            .with_context(|| format!("failed to reset {program} stats"))?;
+        if !status.success() {
+            bail!("{program} --zero-stats failed with status {status}");
+        }
+        Ok(())
+    }
+
+    fn command(&self) -> Command {
+        self.host
+            .command(&self.workspace, &self.env(), self.tool.program())
+    }
+}
+
+impl Drop for SccacheServer {
+    fn drop(&mut self) {
+        let _ = self.command().arg("--stop-server").output();
+    }
+}
+
+impl SccacheStats {
+    /// Parses the human-readable `sccache --show-stats` table.
+    fn parse(text: &str) -> Self {
+        let mut stats = Self::default();
+        for line in text.lines() {
+            let Some((label, value)) = line.trim().rsplit_once(char::is_whitespace) else {
+                continue;
+            };
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+            match label.trim() {
+                "Compile requests" => stats.compile_requests = value,
+                "Cache hits" => stats.hits = value,
+                "Cache misses" => stats.misses = value,
+                "Non-cacheable calls" => stats.non_cacheable = value,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
@@ -0,0 +1,347 @@
+//! Choosing which scenarios of a large matrix to run: a balanced fractional
+//! design, a reproducible random sample, or an order that covers every axis
+//! value as early as possible so a time budget still yields balanced data.
+
+use std::collections::HashSet;
+
+use crate::PreparedScenario;
+use crate::stats::SplitMix64;
+
+/// Keeps `count` scenarios picked at random from `seed`, in their original
+/// order. The same matrix and seed always give the same sample.
+pub fn sample(scenarios: &mut Vec<PreparedScenario>, count: usize, seed: u64) {
+    if count >= scenarios.len() {
+        return;
+    }
+    // Partial Fisher-Yates over the indices.
+    let mut rng = SplitMix64::new(seed);
+    let mut indices: Vec<usize> = (0..scenarios.len()).collect();
+    for i in 0..count {
+        let j = i + rng.below(indices.len() - i);
+        indices.swap(i, j);
+    }
+    let keep: HashSet<usize> = indices[..count].iter().copied().collect();
+    let mut index = 0;
+    scenarios.retain(|_| {
+        index += 1;
+        keep.contains(&(index - 1))
+    });
+}
+
+/// Reorders scenarios so each one adds as many not-yet-covered axis values
+/// as possible. Ties keep the existing order, which is cheapest-first when
+/// history is available.
+pub fn order_for_coverage(scenarios: &mut Vec<PreparedScenario>) {
+    let mut remaining: Vec<(PreparedScenario, Vec<(String, String)>)> = scenarios
+        .drain(..)
+        .map(|scenario| {
+            let axes = scenario.axes();
+            (scenario, axes)
+        })
+        .collect();
+    let mut covered: HashSet<(String, String)> = HashSet::new();
+    while !remaining.is_empty() {
+        let gain = |axes: &[(String, String)]| {
+            axes.iter()
+                .filter(|value| !covered.contains(*value))
+                .count()
+        };
+        // `max_by_key` keeps the last maximum, so search in reverse to keep
+        // the first one.
+        let best = (0..remaining.len())
+            .rev()
+            .max_by_key(|&index| gain(&remaining[index].1))
+            .expect("remaining is not empty");
+        let (scenario, axes) = remaining.remove(best);
+        covered.extend(axes);
+        scenarios.push(scenario);
+    }
+}
+
+/// Which part of the matrix cross-product to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Design {
+    /// Every valid combination.
+    #[default]
+    Full,
+    /// A balanced fraction that still estimates each axis's main effect.
+    Fractional,
+}
+
+/// Keeps a balanced fraction of the matrix, in the style of an orthogonal
+/// array: every value of an axis appears about equally often, and so does
+/// every pair of values from two axes. Averaging over the rows with a given
+/// value then still cancels out the other axes, so the per-axis analysis
+/// stays valid on far fewer scenarios.
+///
+/// Returns the number of axes the design balances.
+pub fn fractional(scenarios: &mut Vec<PreparedScenario>) -> usize {
+    let rows: Vec<Vec<(String, String)>> = scenarios.iter().map(PreparedScenario::axes).collect();
+    let axis_count = rows.iter().map(Vec::len).min().unwrap_or_default();
+    // Only axes that vary can be balanced; value indices per axis.
+    let mut levels: Vec<(usize, Vec<String>)> = Vec::new();
+    for axis in 0..axis_count {
+        let mut values: Vec<String> = rows.iter().map(|row| row[axis].1.clone()).collect();
+        values.sort();
+        values.dedup();
+        if values.len() > 1 {
+            levels.push((axis, values));
+        }
+    }
+    let coded: Vec<Vec<usize>> = rows
+        .iter()
+        .map(|row| {
+            levels
+                .iter()
+                .map(|(axis, values)| {
+                    values
+                        .iter()
+                        .position(|value| *value == row[*axis].1)
+                        .expect("value collected above")
+                })
+                .collect()
+        })
+        .collect();
+    let sizes: Vec<usize> = levels.iter().map(|(_, values)| values.len()).collect();
+    let runs = design_size(&sizes).min(scenarios.len());
+
+    let mut counts = Counts::new(&sizes);
+    let mut chosen = vec![false; scenarios.len()];
+    for _ in 0..runs {
+        let best = (0..coded.len())
+            .filter(|&index| !chosen[index])
+            .min_by(|&a, &b| {
+                let cost = |index: usize| counts.cost_with(&coded[index], runs);
+                cost(a).total_cmp(&cost(b))
+            })
+            .expect("runs never exceeds the scenario count");
+        counts.add(&coded[best]);
+        chosen[best] = true;
+    }
+
+    let mut index = 0;
+    scenarios.retain(|_| {
+        index += 1;
+        chosen[index - 1]
+    });
+    levels.len()
+}
+
+/// Smallest run count that can hold every pair of values at least once and
+/// leaves enough degrees of freedom for all main effects, rounded up so each
+/// axis's values can appear equally often.
+fn design_size(sizes: &[usize]) -> usize {
+    let main_effects = 1 + sizes.iter().map(|size| size - 1).sum::<usize>();
+    let pairs = sizes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| sizes[i + 1..].iter().map(move |b| a * b))
+        .max()
+        .unwrap_or(1);
+    let multiple = sizes.iter().fold(1, |multiple, &size| lcm(multiple, size));
+    main_effects.max(pairs).div_ceil(multiple) * multiple
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    let gcd = |mut a: usize, mut b: usize| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    a / gcd(a, b) * b
+}
+
+/// How often each value, and each pair of values across two axes, has been
+/// picked so far.
+struct Counts {
+    sizes: Vec<usize>,
+    values: Vec<Vec<usize>>,
+    /// Indexed by axis pair `(i, j)` with `i < j`, then `a * sizes[j] + b`.
+    pairs: Vec<Vec<usize>>,
+}
+
+impl Counts {
+    fn new(sizes: &[usize]) -> Self {
+        let mut pairs = Vec::new();
+        for (i, a) in sizes.iter().enumerate() {
+            for b in &sizes[i + 1..] {
+                pairs.push(vec![0; a * b]);
+            }
+        }
+        Self {
+            sizes: sizes.to_vec(),
+            values: sizes.iter().map(|size| vec![0; *size]).collect(),
+            pairs,
+        }
+    }
+
+    fn cells<'a>(&'a self, row: &'a [usize]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let n = self.sizes.len();
+        (0..n)
+            .flat_map(move |i| (i + 1..n).map(move |j| (i, j)))
+            .enumerate()
+            .map(move |(pair, (i, j))| (pair, row[i] * self.sizes[j] + row[j]))
+    }
+
+    fn add(&mut self, row: &[usize]) {
+        for (axis, value) in row.iter().enumerate() {
+            self.values[axis][*value] += 1;
+        }
+        let cells: Vec<(usize, usize)> = self.cells(row).collect();
+        for (pair, cell) in cells {
+            self.pairs[pair][cell] += 1;
+        }
+    }
+
+    /// How much adding `row` next would unbalance the design: prefer rarely
+    /// picked values and pairs, and penalize going past their balanced
+    /// share. Value balance weighs more than pair balance since the main
+    /// effects depend on it directly.
+    fn cost_with(&self, row: &[usize], runs: usize) -> f64 {
+        let overshoot = |count: usize, target: f64| {
+            let excess = count as f64 + 1.0 - target;
+            count as f64 + excess.max(0.0).powi(2)
+        };
+        let values: f64 = row
+            .iter()
+            .enumerate()
+            .map(|(axis, value)| {
+                overshoot(
+                    self.values[axis][*value],
+                    runs as f64 / self.sizes[axis] as f64,
+                )
+            })
+            .sum();
+        let pairs: f64 = self
+            .cells(row)
+            .map(|(pair, cell)| {
+                let target = runs as f64 / self.pairs[pair].len() as f64;
+                overshoot(self.pairs[pair][cell], target)
+            })
+            .sum();
+        values * 4.0 + pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Matrix, prepare_scenarios};
+
+    fn scenarios() -> Vec<PreparedScenario> {
+        prepare_scenarios(&Matrix::default()).unwrap()
+    }
+
+    fn slugs(scenarios: &[PreparedScenario]) -> Vec<&str> {
+        scenarios
+            .iter()
+            .map(|scenario| scenario.slug.as_str())
+            .collect()
+    }
+
+    fn values(scenarios: &[PreparedScenario]) -> HashSet<(String, String)> {
+        scenarios.iter().flat_map(PreparedScenario::axes).collect()
+    }
+
+    #[test]
+    fn empty_matrix_stays_empty() {
+        let mut empty = Vec::new();
+
+        sample(&mut empty, 3, 1);
+        order_for_coverage(&mut empty);
+        assert_eq!(fractional(&mut empty), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn sample_is_reproducible_and_keeps_order() {
+        let all = scenarios();
+        let mut first = scenarios();
+        let mut second = scenarios();
+
+        sample(&mut first, 5, 7);
+        sample(&mut second, 5, 7);
+
+        assert_eq!(first.len(), 5);
+        assert_eq!(slugs(&first), slugs(&second));
+        let positions: Vec<usize> = first
+            .iter()
+            .map(|kept| all.iter().position(|s| s.slug == kept.slug).unwrap())
+            .collect();
+        assert!(positions.is_sorted());
+    }
+
+    #[test]
+    fn sample_of_at_least_the_matrix_keeps_everything() {
+        let all = scenarios();
+        for count in [all.len(), all.len() + 1] {
+            let mut kept = scenarios();
+            sample(&mut kept, count, 7);
+            assert_eq!(slugs(&kept), slugs(&all));
+        }
+        let mut none = scenarios();
+        sample(&mut none, 0, 7);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn coverage_order_reaches_every_value_first() {
+        let all = scenarios();
+        let mut ordered = scenarios();
+
+        order_for_coverage(&mut ordered);
+
+        assert_eq!(ordered.len(), all.len());
+        // Every scenario ties at first, and ties keep the existing order.
+        assert_eq!(ordered[0].slug, all[0].slug);
+        // Three values on the widest axis, so three scenarios can cover all.
+        assert_eq!(values(&ordered[..3]), values(&all));
+    }
+
+    #[test]
+    fn fractional_design_balances_each_axis() {
+        let all = scenarios();
+        let mut kept = scenarios();
+
+        let balanced = fractional(&mut kept);
+
+        assert_eq!(balanced, 3);
+        assert!(kept.len() < all.len());
+        assert_eq!(values(&kept), values(&all));
+        for axis in ["linker", "cache", "dynamic"] {
+            let mut counts: Vec<usize> = values(&all)
+                .iter()
+                .filter(|(name, _)| name == axis)
+                .map(|(_, value)| {
+                    kept.iter()
+                        .filter(|scenario| {
+                            scenario.axes().contains(&(axis.to_string(), value.clone()))
+                        })
+                        .count()
+                })
+                .collect();
+            counts.dedup();
+            assert_eq!(counts.len(), 1, "{axis} is unbalanced");
+        }
+    }
+
+    #[test]
+    fn fractional_design_of_one_scenario_keeps_it() {
+        let mut one = scenarios();
+        one.truncate(1);
+
+        assert_eq!(fractional(&mut one), 0);
+        assert_eq!(one.len(), 1);
+    }
+
+    #[test]
+    fn design_size_fits_pairs_and_balance() {
+        assert_eq!(design_size(&[]), 1);
+        assert_eq!(design_size(&[2]), 2);
+        // Nine pairs of two three-valued axes, rounded up to a multiple of 6.
+        assert_eq!(design_size(&[2, 3, 3]), 12);
+        assert_eq!(design_size(&[2, 2, 2, 2]), 6);
+        assert_eq!(lcm(4, 6), 12);
+    }
+}
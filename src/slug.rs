@@ -0,0 +1,227 @@
+//! How scenario slugs are named. The full slug joins every axis label and
+//! grows with each custom axis; a short or patterned slug keeps directory
+//! names and report columns readable, and the run log maps it back to the
+//! full configuration.
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+/// Placeholders that only some scenarios have a value for.
+const KNOWN_OPTIONAL: [&str; 1] = ["workspace-root"];
+
+/// Strategy for turning a scenario into its slug (`--slug-scheme`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SlugScheme {
+    /// Every axis label joined with `-`.
+    #[default]
+    Full,
+    /// `s` and eight hex digits of the full slug's hash.
+    Short,
+    /// A pattern with `{axis}` placeholders, plus `{hash}` for the short
+    /// hash.
+    Pattern(String),
+}
+
+pub(crate) fn parse_slug_scheme(input: &str) -> Result<SlugScheme> {
+    Ok(match input {
+        "full" => SlugScheme::Full,
+        "short" => SlugScheme::Short,
+        pattern if pattern.contains('{') => SlugScheme::Pattern(pattern.to_string()),
+        _ => bail!(
+            "unknown slug scheme `{input}` (use full, short, or a pattern such as `{{linker}}-{{cache}}`)"
+        ),
+    })
+}
+
+impl SlugScheme {
+    /// The slug for a scenario whose full slug is `full`, with `axes` as
+    /// axis name/value pairs.
+    pub(crate) fn render(&self, full: &str, axes: &[(String, String)]) -> Result<String> {
+        let slug = match self {
+            Self::Full => return Ok(full.to_string()),
+            Self::Short => short_hash(full),
+            Self::Pattern(pattern) => expand(pattern, full, axes)?,
+        };
+        if slug.is_empty()
+            || !slug
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            bail!(
+                "slug `{slug}` must be non-empty and use only letters, digits, `-`, `_`, and `.`"
+            );
+        }
+        Ok(slug)
+    }
+}
+
+/// 64-bit FNV-1a: unlike std's hashers, fixed across Rust releases, so a
+/// short slug keeps matching history and baselines.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn short_hash(full: &str) -> String {
+    format!("s{:08x}", fnv1a(full.as_bytes()) as u32)
+}
+
+fn expand(pattern: &str, full: &str, axes: &[(String, String)]) -> Result<String> {
+    let mut slug = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        slug.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            bail!("unclosed `{{` in slug pattern `{pattern}`");
+        };
+        let name = &rest[start + 1..start + end];
+        match name {
+            "hash" => slug.push_str(&short_hash(full)),
+            _ => match axes.iter().find(|(axis, _)| axis == name) {
+                Some((_, value)) => slug.push_str(value),
+                // Axes a scenario does not have, such as `workspace-root`
+                // without `--workspace-root`, expand to nothing.
+                None if KNOWN_OPTIONAL.contains(&name) => {}
+                None => bail!(
+                    "unknown axis `{name}` in slug pattern `{pattern}` (axes: {})",
+                    axes.iter()
+                        .map(|(axis, _)| axis.as_str())
+                        .chain(["hash"])
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            },
+        }
+        rest = &rest[start + end + 1..];
+    }
+    slug.push_str(rest);
+    Ok(collapse_dashes(&slug))
+}
+
+/// `slug` without leading, trailing, or repeated dashes, which placeholders
+/// that expand to nothing leave behind.
+fn collapse_dashes(slug: &str) -> String {
+    let mut collapsed = String::with_capacity(slug.len());
+    for c in slug.chars() {
+        if c != '-' || (!collapsed.is_empty() && !collapsed.ends_with('-')) {
+            collapsed.push(c);
+        }
+    }
+    collapsed.trim_end_matches('-').to_string()
+}
+
+/// Fails when two scenarios get the same slug, naming both configurations.
+pub(crate) fn check_collisions<'a>(
+    slugs: impl IntoIterator<Item = (&'a str, String)>,
+) -> Result<()> {
+    let mut seen: HashMap<&str, String> = HashMap::new();
+    for (slug, description) in slugs {
+        if let Some(first) = seen.insert(slug, description.clone()) {
+            bail!(
+                "scenarios [{first}] and [{description}] both get the slug `{slug}`; \
+                 add the axes they differ in to the --slug-scheme pattern"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axes() -> Vec<(String, String)> {
+        [
+            ("linker", "rust-lld"),
+            ("cache", "sscache"),
+            ("profile", "dev"),
+        ]
+        .iter()
+        .map(|(axis, value)| (axis.to_string(), value.to_string()))
+        .collect()
+    }
+
+    fn pattern(text: &str) -> SlugScheme {
+        parse_slug_scheme(text).unwrap()
+    }
+
+    #[test]
+    fn short_hash_is_fixed() {
+        // FNV-1a of "a" is 0xaf63dc4c8601ec8c.
+        assert_eq!(short_hash("a"), "s8601ec8c");
+        assert_eq!(
+            SlugScheme::Short
+                .render("rust-lld-sscache", &axes())
+                .unwrap(),
+            short_hash("rust-lld-sscache")
+        );
+        assert_ne!(
+            short_hash("rust-lld-sscache"),
+            short_hash("rust-lld-cachepot")
+        );
+    }
+
+    #[test]
+    fn pattern_expands_axes_and_hash() {
+        let full = "rust-lld-sscache-dev";
+
+        assert_eq!(
+            pattern("{linker}_{cache}.{profile}")
+                .render(full, &axes())
+                .unwrap(),
+            "rust-lld_sscache.dev"
+        );
+        assert_eq!(
+            pattern("{cache}-{hash}").render(full, &axes()).unwrap(),
+            format!("sscache-{}", short_hash(full))
+        );
+        assert_eq!(SlugScheme::Full.render(full, &axes()).unwrap(), full);
+    }
+
+    #[test]
+    fn pattern_errors_are_reported() {
+        let unknown = pattern("{linkr}").render("x", &axes()).unwrap_err();
+        assert!(unknown.to_string().contains("unknown axis `linkr`"));
+        assert!(pattern("{linker").render("x", &axes()).is_err());
+        assert!(pattern("{linker}/{cache}").render("x", &axes()).is_err());
+        assert!(pattern("{workspace-root}").render("x", &axes()).is_err());
+        assert!(parse_slug_scheme("linker").is_err());
+    }
+
+    #[test]
+    fn missing_optional_axes_leave_no_stray_dashes() {
+        assert_eq!(
+            pattern("{workspace-root}-{linker}-{workspace-root}-{workspace-root}-{cache}-{workspace-root}")
+                .render("x", &axes())
+                .unwrap(),
+            "rust-lld-sscache"
+        );
+        assert_eq!(collapse_dashes("a---b"), "a-b");
+        assert_eq!(collapse_dashes("--a--b--"), "a-b");
+        assert_eq!(collapse_dashes("a_-_b"), "a_-_b");
+    }
+
+    #[test]
+    fn collisions_name_both_scenarios() {
+        assert!(
+            check_collisions([
+                ("a", "linker=default".to_string()),
+                ("b", "linker=zig".to_string())
+            ])
+            .is_ok()
+        );
+
+        let err = check_collisions([
+            ("a", "linker=default".to_string()),
+            ("b", "linker=zig".to_string()),
+            ("a", "linker=gold".to_string()),
+        ])
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("[linker=default]"), "{err}");
+        assert!(err.contains("[linker=gold]"), "{err}");
+        assert!(err.contains("`a`"), "{err}");
+    }
+}
@@ -0,0 +1,76 @@
+//! Target directory snapshots (`--save-target`, `--restore-target`): the
+//! target directory after a scenario's clean build, packed with `tar --zstd`,
+//! so the incremental phases can be measured against a warm target directory
+//! on CI machines that start from a blank disk, as with a restored CI cache.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{Workspace, runner};
+
+/// The snapshot of the scenario `slug` in `dir`.
+pub(crate) fn path(dir: &Path, slug: &str) -> PathBuf {
+    dir.join(format!("{slug}.tar.zst"))
+}
+
+/// Packs the workspace's target directory into `dir`, replacing an older
+/// snapshot of the scenario.
+pub(crate) fn save(workspace: &Workspace, dir: &Path, slug: &str) -> Result<()> {
+    let snapshot = path(dir, slug);
+    println!(
+        "[bench] Saving the target directory to {}...",
+        snapshot.display()
+    );
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create snapshot directory {}", dir.display()))?;
+    let status = Command::new("tar")
+        .arg("--zstd")
+        .arg("-cf")
+        .arg(&snapshot)
+        .arg("-C")
+        .arg(&workspace.target_dir)
+        .arg(".")
+        .status()
+        .map_err(runner::spawn_error("tar"))
+        .context("failed to run tar")?;
+    if !status.success() {
+        bail!("saving {} failed with status {status}", snapshot.display());
+    }
+    Ok(())
+}
+
+/// Unpacks the scenario's snapshot from `dir` into the workspace's target
+/// directory. Returns whether there was one.
+pub(crate) fn restore(workspace: &Workspace, dir: &Path, slug: &str) -> Result<bool> {
+    let snapshot = path(dir, slug);
+    if !snapshot.is_file() {
+        println!(
+            "[bench] No target directory snapshot at {}; running the clean build.",
+            snapshot.display()
+        );
+        return Ok(false);
+    }
+    println!(
+        "[bench] Restoring the target directory from {}...",
+        snapshot.display()
+    );
+    fs::create_dir_all(&workspace.target_dir).context("failed to create the target directory")?;
+    let status = Command::new("tar")
+        .arg("--zstd")
+        .arg("-xf")
+        .arg(&snapshot)
+        .arg("-C")
+        .arg(&workspace.target_dir)
+        .status()
+        .map_err(runner::spawn_error("tar"))
+        .context("failed to run tar")?;
+    if !status.success() {
+        bail!(
+            "restoring {} failed with status {status}",
+            snapshot.display()
+        );
+    }
+    Ok(true)
+}
@@ -0,0 +1,204 @@
+//! Summary statistics over repeated iterations of a scenario.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of bootstrap resamples behind every confidence interval.
+const BOOTSTRAP_RESAMPLES: usize = 2000;
+
+/// Distribution of one phase's durations across iterations, in seconds. The
+/// confidence interval is a 95% bootstrap interval for the median.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseStatistics {
+    pub phase: String,
+    pub samples: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+/// Whether two sets of samples differ beyond what resampling noise explains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Significance {
+    Significant,
+    NotSignificant,
+    /// At least one side has a single sample, so no interval can be formed.
+    Unknown,
+}
+
+impl PhaseStatistics {
+    pub fn new(phase: &str, values: &[f64], seed: u64) -> Self {
+        let (ci_low, ci_high) = bootstrap_ci(seed, |rng| median(&resample(values, rng)));
+        Self {
+            phase: phase.to_string(),
+            samples: values.len(),
+            p50: percentile(values, 0.50),
+            p95: percentile(values, 0.95),
+            ci_low,
+            ci_high,
+        }
+    }
+}
+
+/// Compares `candidate` against `baseline` by bootstrapping the difference of
+/// their medians: the change is significant when the 95% interval of that
+/// difference excludes zero.
+pub fn compare(baseline: &[f64], candidate: &[f64], seed: u64) -> Significance {
+    if baseline.len() < 2 || candidate.len() < 2 {
+        return Significance::Unknown;
+    }
+    let (low, high) = bootstrap_ci(seed, |rng| {
+        median(&resample(candidate, rng)) - median(&resample(baseline, rng))
+    });
+    if low > 0.0 || high < 0.0 {
+        Significance::Significant
+    } else {
+        Significance::NotSignificant
+    }
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    percentile(values, 0.5)
+}
+
+/// Sample standard deviation over the mean, or `None` for fewer than two
+/// values or a zero mean.
+pub fn coefficient_of_variation(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    (mean > 0.0).then(|| variance.sqrt() / mean)
+}
+
+/// Linear-interpolated percentile (`q` in `0.0..=1.0`). Empty input yields NaN.
+pub fn percentile(values: &[f64], q: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * weight
+}
+
+fn bootstrap_ci(seed: u64, mut statistic: impl FnMut(&mut SplitMix64) -> f64) -> (f64, f64) {
+    let mut rng = SplitMix64(seed);
+    let estimates: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| statistic(&mut rng))
+        .collect();
+    (percentile(&estimates, 0.025), percentile(&estimates, 0.975))
+}
+
+fn resample(values: &[f64], rng: &mut SplitMix64) -> Vec<f64> {
+    (0..values.len())
+        .map(|_| values[rng.below(values.len())])
+        .collect()
+}
+
+/// Small deterministic generator so reports are reproducible run to run.
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 1e-9
+    }
+
+    #[test]
+    fn percentiles_interpolate_between_sorted_values() {
+        let values = [4.0, 1.0, 3.0, 2.0];
+
+        assert!(close(percentile(&values, 0.0), 1.0));
+        assert!(close(percentile(&values, 0.5), 2.5));
+        assert!(close(percentile(&values, 0.95), 3.85));
+        assert!(close(percentile(&values, 1.0), 4.0));
+        assert!(close(median(&[5.0, 1.0, 3.0]), 3.0));
+        // Out-of-range quantiles are clamped.
+        assert!(close(percentile(&values, 1.5), 4.0));
+        assert!(percentile(&[], 0.5).is_nan());
+    }
+
+    #[test]
+    fn single_sample_has_a_degenerate_interval() {
+        let stats = PhaseStatistics::new("clean", &[12.5], 7);
+
+        assert_eq!(stats.samples, 1);
+        assert!(close(stats.p50, 12.5));
+        assert!(close(stats.p95, 12.5));
+        assert!(close(stats.ci_low, 12.5));
+        assert!(close(stats.ci_high, 12.5));
+    }
+
+    #[test]
+    fn identical_samples_have_a_degenerate_interval() {
+        let values = [3.0; 8];
+        let stats = PhaseStatistics::new("second", &values, 7);
+
+        assert!(close(stats.ci_low, 3.0));
+        assert!(close(stats.ci_high, 3.0));
+        assert_eq!(compare(&values, &values, 7), Significance::NotSignificant);
+        assert_eq!(coefficient_of_variation(&values), Some(0.0));
+    }
+
+    #[test]
+    fn interval_brackets_the_median_and_is_reproducible() {
+        let values = [10.0, 10.4, 9.8, 10.1, 10.9, 9.7, 10.2, 10.0, 10.3, 9.9];
+        let stats = PhaseStatistics::new("clean", &values, 42);
+
+        assert!(stats.ci_low <= stats.p50 && stats.p50 <= stats.ci_high);
+        assert!(stats.ci_low >= 9.7 && stats.ci_high <= 10.9);
+        assert!(stats.ci_low < stats.ci_high);
+        let again = PhaseStatistics::new("clean", &values, 42);
+        assert_eq!((stats.ci_low, stats.ci_high), (again.ci_low, again.ci_high));
+    }
+
+    #[test]
+    fn compare_needs_two_samples_a_side_and_finds_clear_shifts() {
+        let baseline = [10.0, 10.2, 9.9, 10.1, 10.0];
+        let slower = [12.0, 12.1, 11.9, 12.2, 12.0];
+        let noisy = [9.0, 11.0, 10.1, 9.5, 10.6];
+
+        assert_eq!(compare(&baseline, &[12.0], 1), Significance::Unknown);
+        assert_eq!(compare(&[10.0], &slower, 1), Significance::Unknown);
+        assert_eq!(compare(&baseline, &slower, 1), Significance::Significant);
+        assert_eq!(compare(&baseline, &noisy, 1), Significance::NotSignificant);
+    }
+
+    #[test]
+    fn coefficient_of_variation_needs_two_values_and_a_positive_mean() {
+        assert_eq!(coefficient_of_variation(&[1.0]), None);
+        assert_eq!(coefficient_of_variation(&[0.0, 0.0]), None);
+        let cv = coefficient_of_variation(&[1.0, 3.0]).unwrap();
+        assert!(close(cv, 2.0_f64.sqrt() / 2.0));
+    }
+}
@@ -0,0 +1,60 @@
+//! Bounded capture of a child process's most recent output, so failures can
+//! show what the tool printed right before it stopped.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::runner::StreamKind;
+
+/// Lines kept per stream. Enough to cover a cargo error with its notes or the
+/// last stretch of a `dx serve` session without holding entire build logs.
+const TAIL_LINES: usize = 200;
+
+#[derive(Debug, Default)]
+pub struct OutputTail {
+    stdout: VecDeque<String>,
+    stderr: VecDeque<String>,
+}
+
+impl OutputTail {
+    pub fn push(&mut self, kind: StreamKind, line: String) {
+        let buffer = match kind {
+            // Marker lines stand in for the payload's stdout.
+            StreamKind::Stdout | StreamKind::Marker => &mut self.stdout,
+            StreamKind::Stderr => &mut self.stderr,
+        };
+        if buffer.len() == TAIL_LINES {
+            buffer.pop_front();
+        }
+        buffer.push_back(line);
+    }
+
+    /// At most the last `count` lines of each stream, rendered like the
+    /// full tail.
+    pub fn recent(&self, count: usize) -> String {
+        let mut text = String::new();
+        let _ = self.write_lines(&mut text, count);
+        text
+    }
+
+    fn write_lines(&self, f: &mut impl fmt::Write, count: usize) -> fmt::Result {
+        for (name, lines) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            if lines.is_empty() {
+                writeln!(f, "--- no {name} output ---")?;
+                continue;
+            }
+            let shown = lines.len().min(count);
+            writeln!(f, "--- last {shown} {name} line(s) ---")?;
+            for line in lines.iter().skip(lines.len() - shown) {
+                writeln!(f, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for OutputTail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_lines(f, TAIL_LINES)
+    }
+}
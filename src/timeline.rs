@@ -0,0 +1,83 @@
+//! Monotonic timestamps on every tool line the harness forwards or logs, and
+//! BEGIN/END markers around each phase, so the timeline of a run can be
+//! rebuilt from its console output or log files alone. A timestamp is the
+//! time since the harness started, in seconds: `[    12.345]`.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::phase::Phase;
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Starts the clock. Later calls, and timestamps taken before, use the
+/// first start.
+pub(crate) fn start() {
+    EPOCH.get_or_init(Instant::now);
+}
+
+/// The current time as a line prefix.
+pub(crate) fn stamp() -> String {
+    let epoch = EPOCH.get_or_init(Instant::now);
+    format!("[{:>10.3}]", epoch.elapsed().as_secs_f64())
+}
+
+/// Marks the start of a phase on the console and in `log`. Its END marker
+/// is written by [`PhaseMarker::end`], or with `failed` when the phase
+/// returns an error instead.
+pub(crate) struct PhaseMarker {
+    phase: Phase,
+    log: Option<PathBuf>,
+    started: Instant,
+    ended: bool,
+}
+
+impl PhaseMarker {
+    pub(crate) fn begin(phase: Phase, log: Option<PathBuf>) -> Self {
+        let marker = Self {
+            phase,
+            log,
+            started: Instant::now(),
+            ended: false,
+        };
+        marker.emit(&format!("BEGIN phase {}", phase.name()));
+        marker
+    }
+
+    pub(crate) fn end(mut self) {
+        self.ended = true;
+        self.emit(&format!(
+            "END phase {} ok {:.3}s",
+            self.phase.name(),
+            self.started.elapsed().as_secs_f64()
+        ));
+    }
+
+    fn emit(&self, message: &str) {
+        let stamp = stamp();
+        println!("{stamp} [bench] {message}");
+        // Markers are a convenience for readers; they must not fail a phase.
+        if let Some(path) = &self.log {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut log| writeln!(log, "{stamp} {message}"));
+        }
+    }
+}
+
+impl Drop for PhaseMarker {
+    fn drop(&mut self) {
+        if !self.ended {
+            self.emit(&format!(
+                "END phase {} failed {:.3}s",
+                self.phase.name(),
+                self.started.elapsed().as_secs_f64()
+            ));
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Size and `wasm-opt` measurements for wasm scenarios.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::{PreparedScenario, Workspace, runner};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WasmSizes {
+    pub raw_bytes: u64,
+    pub optimized_bytes: u64,
+}
+
+/// Runs `wasm-opt -Oz` on the payload produced by the last build, timing it as
+/// its own phase and recording the size before and after.
+pub fn run_wasm_opt(
+    workspace: &Workspace,
+    prepared: &PreparedScenario,
+) -> Result<(Duration, WasmSizes)> {
+    let artifact_dir = workspace.artifact_dir(prepared);
+    let input = workspace.host.join(
+        &artifact_dir,
+        &format!("bench-payload-{}.wasm", prepared.slug),
+    );
+    let output = workspace.host.join(&artifact_dir, "payload.opt.wasm");
+    let raw_bytes = workspace
+        .host
+        .file_size(&input)
+        .context("failed to measure raw wasm artifact")?;
+
+    println!("[bench] Running wasm-opt on {input}");
+    let start = Instant::now();
+    let status = workspace
+        .command("wasm-opt")
+        .arg("-Oz")
+        .arg("--all-features")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .status()
+        .map_err(runner::spawn_error("wasm-opt"))
+        .context("failed to run wasm-opt")?;
+    let elapsed = start.elapsed();
+    if !status.success() {
+        bail!("wasm-opt failed with status {status}");
+    }
+
+    let optimized_bytes = workspace
+        .host
+        .file_size(&output)
+        .context("failed to measure optimized wasm artifact")?;
+    Ok((
+        elapsed,
+        WasmSizes {
+            raw_bytes,
+            optimized_bytes,
+        },
+    ))
+}
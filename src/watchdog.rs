@@ -0,0 +1,98 @@
+//! `--stall-timeout`: notices a build or `dx serve` session that has gone
+//! silent. A rustc hang otherwise blocks the run forever. Once nothing was
+//! printed (and, for dx, no frame reported) for the stall window, the
+//! watchdog dumps the tool's process tree and last output lines, then
+//! either waits another window or stops the phase.
+
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::runner::Process;
+use crate::tail::OutputTail;
+
+static WATCHDOG: OnceLock<Watchdog> = OnceLock::new();
+
+/// Output lines per stream shown in a stall report.
+const REPORT_LINES: usize = 20;
+
+/// What happens when a tool stalls (`--stall-action`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StallAction {
+    /// Report the stall and keep waiting for another window.
+    Extend,
+    /// Report the stall and kill the tool, failing the scenario.
+    #[default]
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Watchdog {
+    pub window: Duration,
+    action: StallAction,
+}
+
+/// Watches every tool started from now on.
+pub(crate) fn install(window: Duration, action: StallAction) {
+    let _ = WATCHDOG.set(Watchdog { window, action });
+}
+
+/// The watchdog in effect, if any.
+pub(crate) fn current() -> Option<Watchdog> {
+    WATCHDOG.get().copied()
+}
+
+impl Watchdog {
+    /// Reports that `tool` printed nothing for `silent`. Returns whether the
+    /// tool should be killed.
+    pub(crate) fn stalled(
+        &self,
+        tool: &str,
+        process: &dyn Process,
+        tail: &OutputTail,
+        silent: Duration,
+    ) -> bool {
+        eprintln!(
+            "[bench][warn] {tool} produced no output for {}s",
+            silent.as_secs()
+        );
+        match process.id().and_then(process_tree) {
+            Some(tree) => eprintln!("[bench][warn] Process tree:\n{tree}"),
+            None => eprintln!("[bench][warn] Process tree unavailable"),
+        }
+        eprint!("{}", tail.recent(REPORT_LINES));
+        match self.action {
+            StallAction::Extend => {
+                eprintln!(
+                    "[bench][warn] Waiting another {}s (--stall-action extend)",
+                    self.window.as_secs()
+                );
+                false
+            }
+            StallAction::Kill => {
+                eprintln!("[bench][warn] Stopping {tool}");
+                true
+            }
+        }
+    }
+}
+
+/// Processes in the group `leader` leads, which is every descendant of a
+/// tool on Unix. Not available elsewhere.
+fn process_tree(leader: u32) -> Option<String> {
+    if !cfg!(unix) {
+        return None;
+    }
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=,pgid=,stat=,etime=,args="])
+        .output()
+        .ok()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let leader = leader.to_string();
+    let members: Vec<&str> = listing
+        .lines()
+        .filter(|line| line.split_whitespace().nth(1) == Some(leader.as_str()))
+        .collect();
+    (!members.is_empty())
+        .then(|| format!("  PID PGID STAT ELAPSED COMMAND\n{}", members.join("\n")))
+}
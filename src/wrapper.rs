@@ -0,0 +1,115 @@
+//! User-provided `RUSTC_WRAPPER` for the `wrapper` cache value, such as
+//! buildcache or a logging wrapper. Local runs put this binary in front of it
+//! as a shim that logs each compilation, so a wrapper that cargo never ran is
+//! caught instead of silently timing plain rustc.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+use crate::Workspace;
+use crate::remote::Host;
+
+/// The user's wrapper, passed to the shim.
+const WRAPPER_ENV: &str = "BENCH_RUSTC_WRAPPER";
+/// File the shim appends one crate name per invocation to.
+const LOG_ENV: &str = "BENCH_RUSTC_WRAPPER_LOG";
+/// Crate name cargo uses when it asks rustc about the target.
+const PROBE_CRATE: &str = "___";
+
+/// `[wrapper]` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RustcWrapper {
+    /// Program cargo runs in front of rustc, a path or a name on `PATH`.
+    pub command: String,
+    /// Extra environment for every cargo invocation, e.g. the wrapper's cache
+    /// directory.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// When cargo runs this binary as the shim, logs the compilation, runs the
+/// user's wrapper, and returns its exit code.
+pub fn shim() -> Option<i32> {
+    let wrapper = std::env::var_os(WRAPPER_ENV)?;
+    let args: Vec<_> = std::env::args_os().skip(1).collect();
+    if let Some(log) = std::env::var_os(LOG_ENV) {
+        let crate_name = args
+            .iter()
+            .position(|arg| arg == "--crate-name")
+            .and_then(|index| args.get(index + 1))
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "-".to_string());
+        // Logging must never fail the build it observes.
+        let _ = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log)
+            .and_then(|mut file| writeln!(file, "{crate_name}"));
+    }
+    match Command::new(&wrapper).args(&args).status() {
+        Ok(status) => Some(status.code().unwrap_or(1)),
+        Err(err) => {
+            eprintln!(
+                "error: failed to run RUSTC_WRAPPER {}: {err}",
+                wrapper.to_string_lossy()
+            );
+            Some(1)
+        }
+    }
+}
+
+impl RustcWrapper {
+    /// Makes cargo in `workspace` compile through the wrapper. Returns the
+    /// shim's log file, or `None` on remote hosts, where this binary does not
+    /// exist and the wrapper runs directly.
+    pub(crate) fn configure(&self, workspace: &mut Workspace) -> Result<Option<String>> {
+        workspace.env.extend(self.env.clone());
+        match workspace.host {
+            Host::Local => {
+                let shim = std::env::current_exe()
+                    .context("failed to locate the harness binary for the wrapper shim")?;
+                let log = workspace
+                    .host
+                    .join(&workspace.exec_dir, "rustc-wrapper.log");
+                workspace.env.extend([
+                    ("RUSTC_WRAPPER".to_string(), shim.display().to_string()),
+                    (WRAPPER_ENV.to_string(), self.command.clone()),
+                    (LOG_ENV.to_string(), log.clone()),
+                ]);
+                Ok(Some(log))
+            }
+            Host::Ssh(_) => {
+                eprintln!(
+                    "[bench][warn] Cannot verify that {} runs on a remote host",
+                    self.command
+                );
+                workspace
+                    .env
+                    .push(("RUSTC_WRAPPER".to_string(), self.command.clone()));
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Crates compiled through the wrapper so far, from the shim's `log`.
+/// Fails when cargo compiled nothing through it, e.g. because another
+/// `RUSTC_WRAPPER` setting took precedence.
+pub(crate) fn verify(workspace: &Workspace, log: &str, command: &str) -> Result<u64> {
+    let compiled = match workspace.host.read(log) {
+        Ok(contents) => String::from_utf8_lossy(&contents)
+            .lines()
+            .filter(|name| *name != PROBE_CRATE && *name != "-")
+            .count() as u64,
+        Err(_) => 0,
+    };
+    if compiled == 0 {
+        bail!("RUSTC_WRAPPER {command} was never invoked for a compilation");
+    }
+    Ok(compiled)
+}
@@ -0,0 +1,47 @@
+//! Parses command lines the way `bench` does.
+
+use std::time::Duration;
+
+use clap::Parser;
+use sample::cli::Cli;
+
+#[test]
+fn budget_accepts_units() {
+    let cli = Cli::try_parse_from(["bench", "--budget", "1h30m"]).unwrap();
+
+    assert_eq!(cli.run.budget, Some(Duration::from_secs(5400)));
+}
+
+#[test]
+fn huge_and_negative_budgets_are_rejected() {
+    for budget in ["1e30", "99999999999999999999h", "-5", "-0.5", "NaN"] {
+        // `=` keeps clap from reading a negative number as a flag.
+        let err = Cli::try_parse_from(["bench", &format!("--budget={budget}")])
+            .expect_err(&format!("budget {budget} was accepted"));
+
+        assert!(
+            err.to_string().contains("invalid duration"),
+            "{budget}: {err}"
+        );
+    }
+}
+
+#[test]
+fn budget_components_may_be_separated_by_spaces() {
+    let cli = Cli::try_parse_from(["bench", "--budget", "1h 30m"]).unwrap();
+
+    assert_eq!(cli.run.budget, Some(Duration::from_secs(5400)));
+}
+
+#[test]
+fn empty_budgets_are_rejected() {
+    for budget in ["", "   "] {
+        let err = Cli::try_parse_from(["bench", "--budget", budget])
+            .expect_err(&format!("budget {budget:?} was accepted"));
+
+        assert!(
+            err.to_string().contains("invalid duration"),
+            "{budget:?}: {err}"
+        );
+    }
+}
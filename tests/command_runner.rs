@@ -0,0 +1,326 @@
+//! Drives the build and hotpatch logic against scripted fake processes.
+
+use std::cell::Cell;
+use std::process::Command;
+use std::time::Duration;
+
+use sample::error::BenchError;
+use sample::hotpatch::{self, DxSession, HotpatchOutcome};
+use sample::runner::{self, CommandRunner, FakeRunner, Script};
+
+const MARKER: &str = "PAYLOAD_READY::test::42";
+const PATCHED: &str = "PAYLOAD_RANDOM_VALUE=7";
+
+fn bench_error(err: &anyhow::Error) -> &BenchError {
+    err.downcast_ref::<BenchError>()
+        .unwrap_or_else(|| panic!("expected a BenchError, got {err:?}"))
+}
+
+/// Spawns `dx` from `runner` and watches it, counting trigger calls.
+fn watch(runner: &FakeRunner, timeout: Duration) -> (anyhow::Result<HotpatchOutcome>, u32) {
+    let mut process = runner
+        .spawn(&mut Command::new("dx"), "dx")
+        .expect("dx script queued");
+    let triggers = Cell::new(0);
+    let outcome = hotpatch::watch(
+        process.as_mut(),
+        MARKER,
+        timeout,
+        Duration::from_secs(5),
+        None,
+        || {
+            triggers.set(triggers.get() + 1);
+            Ok(PATCHED.to_string())
+        },
+        None,
+    );
+    (outcome, triggers.get())
+}
+
+#[test]
+fn build_success_is_timed() {
+    let runner = FakeRunner::new().script(
+        "cargo",
+        Script::new()
+            .stderr("   Compiling bench-payload v0.1.0")
+            .delay(Duration::from_millis(50)),
+    );
+    let mut command = Command::new("cargo");
+    command.arg("build").arg("--locked");
+
+    let elapsed = runner::run_build(&runner, &mut command, "clean").unwrap();
+
+    assert!(elapsed >= Duration::from_millis(50));
+    assert_eq!(
+        runner.invocations(),
+        vec![vec!["cargo", "build", "--locked"]]
+    );
+}
+
+#[test]
+fn build_failure_keeps_output_tail() {
+    let runner = FakeRunner::new().script(
+        "cargo",
+        Script::new()
+            .stderr("error[E0425]: cannot find value `x` in this scope")
+            .exit(101),
+    );
+
+    let err = runner::run_build(&runner, &mut Command::new("cargo"), "second").unwrap_err();
+
+    match bench_error(&err) {
+        BenchError::BuildFailed { phase, status, .. } => {
+            assert_eq!(phase, "second");
+            assert_eq!(status.code(), Some(101));
+        }
+        other => panic!("unexpected error {other:?}"),
+    }
+    assert!(format!("{err:?}").contains("error[E0425]"));
+}
+
+#[test]
+fn raw_output_goes_to_the_log_and_sanitized_output_to_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("build.log");
+    let mut output = b"caf\xe9 \x1b[32mok\x1b[0m\r\n[1/3]\r[2/3]\r[3/3]\n".to_vec();
+    output.extend(std::iter::repeat_n(b'x', 100_000));
+    output.push(b'\n');
+    let runner = FakeRunner::new().script("cargo", Script::new().stdout_bytes(output).exit(101));
+
+    let err = runner::run_build_logged(&runner, &mut Command::new("cargo"), "clean", Some(&log))
+        .unwrap_err();
+
+    let logged = std::fs::read(&log).unwrap();
+    let contains = |needle: &[u8]| logged.windows(needle.len()).any(|window| window == needle);
+    assert!(contains(b"caf\xe9 \x1b[32mok"));
+    assert!(contains(b"[1/3]\r[2/3]\r[3/3]"));
+    assert!(contains(&[b'x'; 100_000]));
+    let shown = format!("{err:?}");
+    assert!(shown.contains("caf\u{fffd} ok"));
+    assert!(shown.contains("[3/3]") && !shown.contains("[1/3]"));
+    assert!(shown.contains("[... 100000 bytes in total]"));
+}
+
+#[test]
+fn missing_tool_is_reported() {
+    let runner = FakeRunner::new();
+
+    let err = runner::run_build(&runner, &mut Command::new("cargo"), "clean").unwrap_err();
+
+    assert!(matches!(bench_error(&err), BenchError::ToolMissing { tool } if tool == "cargo"));
+}
+
+#[test]
+fn verified_hotpatch() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout("Serving app")
+            .stdout(MARKER)
+            .delay(Duration::from_millis(20))
+            .stdout("Hot-patching: applied patch")
+            .stdout(PATCHED)
+            .hang(),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    let outcome = outcome.unwrap();
+    assert_eq!(triggers, 1);
+    assert_eq!(outcome.problem, None);
+    assert!(outcome.elapsed >= Duration::from_millis(20));
+}
+
+#[test]
+fn marker_is_found_in_colored_output_after_invalid_utf8() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout_bytes(b"\xff\xfe garbage\n".to_vec())
+            .stdout_bytes(format!("\x1b[1m{MARKER}\x1b[0m\r\n").into_bytes())
+            .stdout("Hot-patching: applied patch")
+            .stdout(PATCHED)
+            .hang(),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    assert_eq!(outcome.unwrap().problem, None);
+    assert_eq!(triggers, 1);
+}
+
+#[test]
+fn restart_is_not_a_hotpatch() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout(MARKER)
+            .stdout("Hot-patching failed, rebuilding")
+            .stdout(MARKER)
+            .stdout(PATCHED)
+            .hang(),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    assert_eq!(triggers, 1);
+    let problem = outcome.unwrap().problem.expect("restart detected");
+    assert!(problem.contains("restarted"), "{problem}");
+}
+
+#[test]
+fn value_without_patch_log_is_unverified() {
+    let runner =
+        FakeRunner::new().script("dx", Script::new().stdout(MARKER).stdout(PATCHED).hang());
+
+    let (outcome, _) = watch(&runner, Duration::from_secs(5));
+
+    let problem = outcome
+        .unwrap()
+        .problem
+        .expect("missing patch log detected");
+    assert!(problem.contains("did not report a patch"), "{problem}");
+}
+
+#[test]
+fn marker_from_before_the_trigger_is_ignored() {
+    // The payload value printed before the change must not count as patched.
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout("PAYLOAD_RANDOM_VALUE=1")
+            .stdout(MARKER)
+            .stdout("PAYLOAD_RANDOM_VALUE=1")
+            .stdout("Hot-patching: src/main.rs took 412ms")
+            .stdout(PATCHED)
+            .hang(),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    assert_eq!(triggers, 1);
+    assert_eq!(outcome.unwrap().problem, None);
+}
+
+#[test]
+fn ready_marker_timeout() {
+    let runner = FakeRunner::new().script("dx", Script::new().stdout("Building...").hang());
+
+    let (outcome, triggers) = watch(&runner, Duration::from_millis(300));
+
+    let err = outcome.unwrap_err();
+    assert_eq!(triggers, 0);
+    match bench_error(&err) {
+        BenchError::HotpatchTimeout { marker, .. } => assert_eq!(marker, MARKER),
+        other => panic!("unexpected error {other:?}"),
+    }
+    assert!(format!("{err:?}").contains("Building..."));
+}
+
+#[test]
+fn slow_ready_marker_within_timeout() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .delay(Duration::from_millis(400))
+            .stdout(MARKER)
+            .stdout("Hot-patching: src/main.rs took 412ms")
+            .stdout(PATCHED)
+            .hang(),
+    );
+
+    let (outcome, _) = watch(&runner, Duration::from_secs(5));
+
+    assert_eq!(outcome.unwrap().problem, None);
+}
+
+#[test]
+fn exit_before_ready() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new().stderr("error: could not compile").exit(1),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    let err = outcome.unwrap_err();
+    assert_eq!(triggers, 0);
+    assert!(matches!(
+        bench_error(&err),
+        BenchError::HotpatchExited { .. }
+    ));
+    assert!(format!("{err:?}").contains("could not compile"));
+}
+
+#[test]
+fn exit_after_trigger() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout(MARKER)
+            .stderr("thread 'main' panicked")
+            .exit(101),
+    );
+
+    let (outcome, triggers) = watch(&runner, Duration::from_secs(5));
+
+    let err = outcome.unwrap_err();
+    assert_eq!(triggers, 1);
+    assert!(matches!(
+        bench_error(&err),
+        BenchError::HotpatchExited { .. }
+    ));
+}
+
+#[test]
+fn trigger_failure_is_propagated() {
+    let runner = FakeRunner::new().script("dx", Script::new().stdout(MARKER).hang());
+    let mut process = runner.spawn(&mut Command::new("dx"), "dx").unwrap();
+
+    let err = hotpatch::watch(
+        process.as_mut(),
+        MARKER,
+        Duration::from_secs(5),
+        Duration::from_secs(5),
+        None,
+        || anyhow::bail!("failed to update payload source"),
+        None,
+    )
+    .unwrap_err();
+
+    assert!(err.to_string().contains("failed to update payload source"));
+}
+
+#[test]
+fn session_applies_several_patches() {
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout(MARKER)
+            .stdout("Hot-patching: applied patch")
+            .stdout("PAYLOAD_RANDOM_VALUE=1")
+            .delay(Duration::from_millis(20))
+            .stdout("Hot-patching: applied patch")
+            .stdout("PAYLOAD_RANDOM_VALUE=2")
+            .hang(),
+    );
+    let mut process = runner.spawn(&mut Command::new("dx"), "dx").unwrap();
+
+    let mut session = DxSession::start(process.as_mut(), MARKER, None, None).unwrap();
+    session.wait_ready(Duration::from_secs(5)).unwrap();
+    let first = session
+        .patch(Duration::from_secs(5), || {
+            Ok("PAYLOAD_RANDOM_VALUE=1".to_string())
+        })
+        .unwrap();
+    let second = session
+        .patch(Duration::from_secs(5), || {
+            Ok("PAYLOAD_RANDOM_VALUE=2".to_string())
+        })
+        .unwrap();
+    session.finish().unwrap();
+
+    assert_eq!(first.problem, None);
+    assert_eq!(second.problem, None);
+    assert!(second.elapsed >= Duration::from_millis(20));
+}
@@ -0,0 +1,244 @@
+//! Drives the dx session state machine with hand-made stream events, and a
+//! session with a scripted fake dx.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use sample::error::BenchError;
+use sample::hotpatch::{DxMachine, DxSession, DxState};
+use sample::runner::{CommandRunner, FakeRunner, Script, StreamEvent, StreamKind};
+
+const MARKER: &str = "PAYLOAD_READY::machine::1";
+
+fn line(text: &str) -> StreamEvent {
+    StreamEvent::Line(StreamKind::Stdout, text.to_string())
+}
+
+fn feed(machine: &mut DxMachine, events: &[StreamEvent]) {
+    for event in events {
+        machine.on_event(event);
+    }
+}
+
+fn ready() -> DxMachine {
+    let mut machine = DxMachine::new(MARKER);
+    machine.on_event(&line(MARKER));
+    assert_eq!(*machine.state(), DxState::Ready);
+    machine
+}
+
+fn problem(machine: &DxMachine) -> Option<String> {
+    match machine.state() {
+        DxState::Patched { problem, .. } => problem.clone(),
+        other => panic!("expected a patched session, got {other:?}"),
+    }
+}
+
+#[test]
+fn starts_waiting_for_the_ready_marker() {
+    let mut machine = DxMachine::new(MARKER);
+    feed(
+        &mut machine,
+        &[
+            line("Serving your app"),
+            StreamEvent::Closed(StreamKind::Stderr),
+        ],
+    );
+
+    assert_eq!(*machine.state(), DxState::Starting);
+    assert!(!machine.state().accepts_patch());
+    machine.on_event(&line(&format!("[app] {MARKER}")));
+    assert_eq!(*machine.state(), DxState::Ready);
+}
+
+#[test]
+fn patch_with_dx_log_is_verified() {
+    let mut machine = ready();
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(&mut machine, &[line("Hot-patching: applied patch")]);
+    assert!(matches!(machine.state(), DxState::PatchPending { .. }));
+
+    machine.on_event(&line("PAYLOAD_RANDOM_VALUE=7"));
+
+    assert_eq!(problem(&machine), None);
+    assert!(machine.state().accepts_patch());
+}
+
+#[test]
+fn terminal_formatting_does_not_hide_markers() {
+    let mut machine = DxMachine::new(MARKER);
+    machine.on_event(&line(&format!(
+        "\x1b[2K\x1b]8;;file:///app\x1b\\[app]\x1b]8;;\x1b\\ \x1b[1m{MARKER}\x1b[0m\r\x1b[K"
+    )));
+    assert_eq!(*machine.state(), DxState::Ready);
+
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(
+        &mut machine,
+        &[
+            line("\x1b[36mHot-patching:\x1b[0m applied patch"),
+            line("[=>   ] 1/4\r[====>] 4/4\r\x1b(BPAYLOAD_RANDOM_VALUE=7\x1b[m"),
+        ],
+    );
+
+    assert_eq!(problem(&machine), None);
+}
+
+#[test]
+fn restart_is_not_a_hotpatch() {
+    let mut machine = ready();
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(
+        &mut machine,
+        &[
+            line("Hot-patching failed, rebuilding"),
+            line(MARKER),
+            line("PAYLOAD_RANDOM_VALUE=7"),
+        ],
+    );
+
+    assert!(problem(&machine).is_some_and(|problem| problem.contains("restarted")));
+}
+
+#[test]
+fn heartbeat_is_not_a_restart() {
+    let marker =
+        "PAYLOAD_SYSTEM_IS_READY__default-linker-incremental-dx-hotpatch__00000000000051aa";
+    let mut machine = DxMachine::new(marker);
+    machine.on_event(&line(&format!("[app] PAYLOAD_HEARTBEAT::{marker}::600")));
+    assert_eq!(*machine.state(), DxState::Starting);
+    machine.on_event(&line(&format!("[app] {marker}")));
+    assert_eq!(*machine.state(), DxState::Ready);
+
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(
+        &mut machine,
+        &[
+            line(&format!("[app] PAYLOAD_HEARTBEAT::{marker}::1200")),
+            line("Hot-patching: src/main.rs took 412ms"),
+            line(&format!("[app] PAYLOAD_HEARTBEAT::{marker}::1800")),
+            line("[app] PAYLOAD_RANDOM_VALUE=7"),
+        ],
+    );
+
+    assert_eq!(problem(&machine), None);
+}
+
+#[test]
+fn slug_mentioning_hotpatch_is_not_a_patch_log() {
+    let marker =
+        "PAYLOAD_SYSTEM_IS_READY__default-linker-incremental-dx-hotpatch__00000000000051aa";
+    let mut machine = DxMachine::new(marker);
+    machine.on_event(&line(marker));
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(
+        &mut machine,
+        &[
+            line("Compiling bench-payload-default-linker-incremental-dx-hotpatch v0.1.0"),
+            line(&format!("PAYLOAD_HEARTBEAT::{marker}::600")),
+            line("PAYLOAD_RANDOM_VALUE=7"),
+        ],
+    );
+
+    assert!(problem(&machine).is_some_and(|problem| problem.contains("did not report")));
+}
+
+#[test]
+fn value_without_patch_log_is_unverified() {
+    let mut machine = ready();
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    machine.on_event(&line("PAYLOAD_RANDOM_VALUE=7"));
+
+    assert!(problem(&machine).is_some_and(|problem| problem.contains("did not report")));
+}
+
+#[test]
+fn frame_times_are_split_by_patch() {
+    let mut machine = ready();
+    feed(
+        &mut machine,
+        &[
+            line("PAYLOAD_FIRST_FRAME_MS=120.5"),
+            StreamEvent::FrameTime(16.0),
+            StreamEvent::FrameTime(17.0),
+        ],
+    );
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=7".to_string());
+    feed(
+        &mut machine,
+        &[
+            StreamEvent::FrameTime(48.0),
+            line("Hot-patching: applied patch"),
+            line("PAYLOAD_RANDOM_VALUE=7"),
+        ],
+    );
+
+    let DxState::Patched { metrics, .. } = machine.state().clone() else {
+        panic!("expected a patched session, got {:?}", machine.state());
+    };
+    assert_eq!(metrics.get("first_frame_ms"), Some(&120.5));
+    assert_eq!(metrics.get("frame_ms_median"), Some(&17.0));
+    assert_eq!(metrics.get("patch_frame_ms_max"), Some(&48.0));
+
+    // The next patch only sees its own frames and no first frame.
+    machine.begin_patch("PAYLOAD_RANDOM_VALUE=8".to_string());
+    feed(
+        &mut machine,
+        &[
+            StreamEvent::FrameTime(20.0),
+            line("Hot-patching: applied patch"),
+            line("PAYLOAD_RANDOM_VALUE=8"),
+        ],
+    );
+    let DxState::Patched { metrics, .. } = machine.state().clone() else {
+        panic!("expected a patched session, got {:?}", machine.state());
+    };
+    assert_eq!(metrics.get("first_frame_ms"), None);
+    assert_eq!(metrics.get("frame_ms_median"), Some(&20.0));
+}
+
+#[test]
+fn failed_session_ignores_further_output() {
+    let mut machine = ready();
+    machine.fail("output closed");
+    feed(
+        &mut machine,
+        &[line(MARKER), line("PAYLOAD_RANDOM_VALUE=7")],
+    );
+
+    assert_eq!(
+        *machine.state(),
+        DxState::Failed("output closed".to_string())
+    );
+    assert!(!machine.state().accepts_patch());
+}
+
+#[test]
+fn patch_that_never_lands_times_out() {
+    let heartbeat = format!("[app] PAYLOAD_HEARTBEAT::{MARKER}::600");
+    let mut script = Script::new()
+        .stdout(MARKER)
+        .stdout("Hot-patching: src/main.rs took 412ms");
+    // Output keeps coming, so only the patch deadline can end the wait.
+    for _ in 0..20 {
+        script = script.delay(Duration::from_millis(100)).stdout(&heartbeat);
+    }
+    let runner = FakeRunner::new().script("dx", script.hang());
+    let mut process = runner.spawn(&mut Command::new("dx"), "dx").unwrap();
+    let mut session = DxSession::start(process.as_mut(), MARKER, None, None).unwrap();
+    session.wait_ready(Duration::from_secs(5)).unwrap();
+
+    let started = Instant::now();
+    let err = session
+        .patch(Duration::from_millis(300), || {
+            Ok("PAYLOAD_RANDOM_VALUE=7".to_string())
+        })
+        .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(matches!(
+        err.downcast_ref::<BenchError>(),
+        Some(BenchError::PatchTimeout { expected, .. }) if expected == "PAYLOAD_RANDOM_VALUE=7"
+    ));
+    assert!(matches!(session.state(), DxState::Failed(_)));
+}
@@ -0,0 +1,102 @@
+//! Replays recorded `dx serve` sessions and checks that hotpatch detection
+//! still reaches the verdict of the recorded run.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use sample::hotpatch;
+use sample::recording::{Recording, Verdict};
+use sample::runner::{CommandRunner, FakeRunner, Script};
+
+#[test]
+fn recordings_keep_their_verdicts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/recordings");
+    let mut replayed = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "ron") {
+            continue;
+        }
+        let recording = Recording::load(&path).unwrap();
+        let expected = recording
+            .verdict
+            .clone()
+            .unwrap_or_else(|| panic!("{} has no verdict", path.display()));
+
+        let verdict = Verdict::of(&recording.replay(false));
+
+        assert_eq!(verdict, expected, "{}", path.display());
+        replayed += 1;
+    }
+    assert!(replayed > 0, "no recordings in {}", dir.display());
+}
+
+#[test]
+fn recorded_session_replays_identically() {
+    let marker = "PAYLOAD_READY::round-trip::1";
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new()
+            .stdout("Serving your app")
+            .stdout(marker)
+            .stderr("warning: unused variable")
+            .delay(Duration::from_millis(30))
+            .stdout("Hot-patching: src/main.rs took 412ms")
+            .stdout("PAYLOAD_RANDOM_VALUE=2")
+            .hang(),
+    );
+    let mut process = runner.spawn(&mut Command::new("dx"), "dx").unwrap();
+    let mut recording = Recording::new(marker, Some("dx test".to_string()));
+
+    let live = hotpatch::watch(
+        process.as_mut(),
+        marker,
+        Duration::from_secs(5),
+        Duration::from_secs(5),
+        None,
+        || Ok("PAYLOAD_RANDOM_VALUE=2".to_string()),
+        Some(&mut recording),
+    );
+    recording.verdict = Some(Verdict::of(&live));
+
+    assert_eq!(recording.verdict, Some(Verdict::Verified));
+    assert_eq!(recording.lines.len(), 5);
+    assert!(recording.lines[3].at_ms >= 30);
+    assert_eq!(
+        recording.patched_line.as_deref(),
+        Some("PAYLOAD_RANDOM_VALUE=2")
+    );
+
+    let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    recording.save(&path).unwrap();
+    let loaded = Recording::load(&path).unwrap();
+    let replayed = loaded.replay(true).unwrap();
+    assert_eq!(Verdict::of(&Ok(replayed)), Verdict::Verified);
+}
+
+#[test]
+fn exit_code_is_recorded() {
+    let marker = "PAYLOAD_READY::exit::1";
+    let runner = FakeRunner::new().script(
+        "dx",
+        Script::new().stdout(marker).stderr("panicked").exit(101),
+    );
+    let mut process = runner.spawn(&mut Command::new("dx"), "dx").unwrap();
+    let mut recording = Recording::new(marker, None);
+
+    let live = hotpatch::watch(
+        process.as_mut(),
+        marker,
+        Duration::from_secs(5),
+        Duration::from_secs(5),
+        None,
+        || Ok("PAYLOAD_RANDOM_VALUE=3".to_string()),
+        Some(&mut recording),
+    );
+
+    assert_eq!(Verdict::of(&live), Verdict::Exited);
+    assert_eq!(recording.exit_code, Some(101));
+    assert_eq!(recording.lines.len(), 2);
+}